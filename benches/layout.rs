@@ -0,0 +1,209 @@
+//! Benchmarks of the layout per backend, stage, graph family and size, and thread count.
+//! The graphs are generated with a fixed seed, see `generators::GraphKind`.
+//!
+//! `cargo bench --bench layout -- cpu_stages/degree` runs a single group or stage.
+
+use concentric_layout::cpu::{NodeConnections, NodePositions, Normalize};
+use concentric_layout::entities::GraphLayoutData;
+use concentric_layout::generators::GraphKind;
+use concentric_layout::{ComputingConfig, ConcentricLayout, LayoutOptions};
+use criterion::{BatchSize, BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use rayon::ThreadPoolBuilder;
+use std::sync::Arc;
+use std::time::Duration;
+
+const SEED: u64 = 0;
+
+/// Graph families and sizes, the full meshes are smaller as their edges grow with the square of the nodes
+fn get_graphs() -> Vec<(&'static str, GraphLayoutData)> {
+    let mut graphs: Vec<(&'static str, GraphKind)> = [100, 200]
+        .into_iter()
+        .map(|nodes| ("full_mesh", GraphKind::FullMesh { nodes }))
+        .collect();
+    for nodes in [1_000, 5_000, 10_000] {
+        graphs.extend([
+            (
+                "erdos_renyi",
+                GraphKind::ErdosRenyi {
+                    nodes,
+                    edges: nodes * 4,
+                },
+            ),
+            (
+                "barabasi_albert",
+                GraphKind::BarabasiAlbert {
+                    nodes,
+                    edges_per_node: 4,
+                },
+            ),
+            ("telco", GraphKind::Telco { nodes, fan_out: 4 }),
+        ]);
+    }
+    graphs
+        .into_iter()
+        .map(|(family, kind)| (family, kind.generate(SEED)))
+        .collect()
+}
+
+/// Few samples, a layout of the large graphs takes up to seconds
+fn get_config() -> Criterion {
+    Criterion::default()
+        .sample_size(10)
+        .warm_up_time(Duration::from_secs(1))
+        .measurement_time(Duration::from_secs(5))
+}
+
+/// Whole layout per backend, the thread pool and the gpu adapter are created once
+fn bench_backends(criterion: &mut Criterion) {
+    let thread_pool = Arc::new(ThreadPoolBuilder::new().build().unwrap());
+    #[cfg(feature = "gpu")]
+    let adapter = pollster::block_on(concentric_layout::gpu::GpuAdapter::new()).ok();
+    let mut group = criterion.benchmark_group("backend");
+    for (family, graph) in get_graphs().iter() {
+        group.throughput(Throughput::Elements(graph.nodes.len() as u64));
+        let options = LayoutOptions::new().backend(ComputingConfig::Cpu(0));
+        let mut layout = ConcentricLayout::new(&graph.nodes, &graph.edges, options);
+        layout.thread_pool = Some(thread_pool.clone());
+        group.bench_function(
+            BenchmarkId::new(format!("cpu/{}", family), graph.nodes.len()),
+            |bencher| bencher.iter(|| layout.execute_blocking().unwrap()),
+        );
+        #[cfg(feature = "gpu")]
+        if let Some(adapter) = &adapter {
+            let options = LayoutOptions::new().backend(ComputingConfig::Gpu);
+            let mut layout = ConcentricLayout::new(&graph.nodes, &graph.edges, options);
+            layout.gpu_adapter = Some(adapter.clone());
+            group.bench_function(
+                BenchmarkId::new(format!("gpu/{}", family), graph.nodes.len()),
+                |bencher| bencher.iter(|| layout.execute_blocking().unwrap()),
+            );
+        }
+    }
+    group.finish();
+}
+
+/// Each cpu stage on the output of the previous one
+fn bench_cpu_stages(criterion: &mut Criterion) {
+    let options = LayoutOptions::default();
+    let mut group = criterion.benchmark_group("cpu_stages");
+    for (family, graph) in get_graphs().iter() {
+        let nodes = graph.nodes.len();
+        group.throughput(Throughput::Elements(nodes as u64));
+        let node_connections = NodeConnections::get(&graph.nodes, &graph.edges).unwrap();
+        let values = Normalize::get_values(&node_connections);
+        let normalize_data = Normalize::sort(values.clone()).unwrap();
+        group.bench_function(
+            BenchmarkId::new(format!("degree/{}", family), nodes),
+            |bencher| bencher.iter(|| NodeConnections::get(&graph.nodes, &graph.edges).unwrap()),
+        );
+        group.bench_function(
+            BenchmarkId::new(format!("normalize/{}", family), nodes),
+            |bencher| bencher.iter(|| Normalize::get_values(&node_connections)),
+        );
+        group.bench_function(
+            BenchmarkId::new(format!("sort/{}", family), nodes),
+            |bencher| {
+                bencher.iter_batched(
+                    || values.clone(),
+                    |values| Normalize::sort(values).unwrap(),
+                    BatchSize::LargeInput,
+                )
+            },
+        );
+        group.bench_function(
+            BenchmarkId::new(format!("positions/{}", family), nodes),
+            |bencher| bencher.iter(|| NodePositions::get(&normalize_data, &options).unwrap()),
+        );
+    }
+    group.finish();
+}
+
+/// Each gpu stage on the output of the previous one, skipped without a gpu adapter
+#[cfg(feature = "gpu")]
+fn bench_gpu_stages(criterion: &mut Criterion) {
+    use concentric_layout::gpu::node_positions::NodePositions;
+    use concentric_layout::gpu::normalize::Normalize;
+    use concentric_layout::gpu::{GpuAdapter, NodeConnections};
+
+    let Ok(adapter) = pollster::block_on(GpuAdapter::new()) else {
+        eprintln!("gpu_stages: no gpu adapter");
+        return;
+    };
+    let options = LayoutOptions::default();
+    let mut group = criterion.benchmark_group("gpu_stages");
+    for (family, graph) in get_graphs().iter() {
+        let nodes = graph.nodes.len();
+        group.throughput(Throughput::Elements(nodes as u64));
+        let node_connections = NodeConnections::new(&adapter, &graph.nodes, &graph.edges);
+        let node_connections_data = pollster::block_on(node_connections.execute()).unwrap();
+        let normalize =
+            Normalize::new(&adapter, &graph.nodes, &graph.edges, &node_connections_data);
+        let normalize_data = pollster::block_on(normalize.execute()).unwrap();
+        let positions = NodePositions::new(
+            &adapter,
+            &graph.nodes,
+            &graph.edges,
+            &normalize_data,
+            &options,
+        )
+        .unwrap();
+        group.bench_function(
+            BenchmarkId::new(format!("degree/{}", family), nodes),
+            |bencher| bencher.iter(|| pollster::block_on(node_connections.execute()).unwrap()),
+        );
+        group.bench_function(
+            BenchmarkId::new(format!("normalize_sort/{}", family), nodes),
+            |bencher| bencher.iter(|| pollster::block_on(normalize.execute()).unwrap()),
+        );
+        group.bench_function(
+            BenchmarkId::new(format!("positions/{}", family), nodes),
+            |bencher| bencher.iter(|| pollster::block_on(positions.execute()).unwrap()),
+        );
+    }
+    group.finish();
+}
+
+/// Cpu layout of the largest random graph, from 1 thread to the available parallelism
+fn bench_cpu_threads(criterion: &mut Criterion) {
+    let graph = GraphKind::ErdosRenyi {
+        nodes: 10_000,
+        edges: 40_000,
+    }
+    .generate(SEED);
+    let max_threads = std::thread::available_parallelism()
+        .map(|value| value.get())
+        .unwrap_or(1);
+    let mut group = criterion.benchmark_group("cpu_threads");
+    group.throughput(Throughput::Elements(graph.nodes.len() as u64));
+    let mut num_threads = 1;
+    while num_threads <= max_threads {
+        let options = LayoutOptions::new().backend(ComputingConfig::Cpu(num_threads));
+        let mut layout = ConcentricLayout::new(&graph.nodes, &graph.edges, options);
+        layout.thread_pool = Some(Arc::new(
+            ThreadPoolBuilder::new()
+                .num_threads(num_threads)
+                .build()
+                .unwrap(),
+        ));
+        group.bench_function(
+            BenchmarkId::new("erdos_renyi_10000", num_threads),
+            |bencher| bencher.iter(|| layout.execute_blocking().unwrap()),
+        );
+        num_threads *= 2;
+    }
+    group.finish();
+}
+
+#[cfg(feature = "gpu")]
+criterion_group! {
+    name = benches;
+    config = get_config();
+    targets = bench_backends, bench_cpu_stages, bench_gpu_stages, bench_cpu_threads
+}
+#[cfg(not(feature = "gpu"))]
+criterion_group! {
+    name = benches;
+    config = get_config();
+    targets = bench_backends, bench_cpu_stages, bench_cpu_threads
+}
+criterion_main!(benches);
@@ -0,0 +1,758 @@
+use clap::{Parser, Subcommand, ValueEnum};
+use concentric_layout::entities::{GraphLayoutData, NodePositionData};
+use concentric_layout::formats::{GraphFormat, Svg, SvgOptions};
+use concentric_layout::generators::GraphKind;
+#[cfg(feature = "gpu")]
+use concentric_layout::gpu::{Backends, GpuOptions, PowerPreference};
+use concentric_layout::{
+    BoundingBox, ComputingConfig, ConcentricLayout, LayoutMode, LayoutOptions, LayoutQuality,
+    Levels, NodeLevel, PinnedNode, QualityOptions, RingShape, RingStagger, StageTiming,
+    ValidationError,
+};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Instant;
+
+/// Exit code when the layout or the output fails.
+const EXIT_FAILURE: u8 = 1;
+/// Exit code when the input graph cannot be read, parsed or is invalid.
+/// Invalid arguments exit with 2 (clap).
+const EXIT_INVALID_INPUT: u8 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum Backend {
+    /// CPU parallel computing
+    Cpu,
+    /// GPU parallel computing
+    Gpu,
+    /// GPU for large graphs when an adapter is available, otherwise CPU
+    Auto,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum Mode {
+    /// Concentric rings
+    Rings,
+    /// 3D spherical shells, adds the z coordinate
+    Shells,
+    /// Golden angle spiral without ring boundaries
+    Spiral,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum Stagger {
+    /// Every ring starts at the start angle
+    None,
+    /// Odd rings turned by half of their angle between two nodes
+    HalfStep,
+    /// Ring `i` turned by `i` times the golden angle
+    GoldenAngle,
+    /// Each ring turned by a random part of its angle between two nodes, see `--stagger-seed`
+    Random,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum Shape {
+    /// Circles
+    Circle,
+    /// Ellipses of `--aspect-ratio`
+    Ellipse,
+    /// Rectangles of `--aspect-ratio` with corners of `--corner-radius`
+    RoundedRectangle,
+    /// Squares
+    Square,
+}
+
+#[cfg(feature = "gpu")]
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum GpuPower {
+    /// Often the integrated gpu
+    Low,
+    /// Often the discrete gpu
+    High,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum Kind {
+    /// Every ordered pair of nodes connected
+    FullMesh,
+    /// `--edges` random pairs of nodes
+    ErdosRenyi,
+    /// Scale-free, each new node connected to `--degree` nodes
+    BarabasiAlbert,
+    /// First node connected to every other node
+    Star,
+    /// Cycle of the nodes
+    Ring,
+    /// `--degree` children per node
+    Tree,
+    /// Rows of `--columns` nodes
+    Grid,
+    /// Core, aggregation and access nodes, `--degree` children per core and aggregation node
+    Telco,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Generate a seeded synthetic graph, e.g. a benchmark input
+    Generate(GenerateArgs),
+}
+
+#[derive(Debug, clap::Args)]
+struct GenerateArgs {
+    /// Kind of graph
+    #[arg(value_enum)]
+    kind: Kind,
+    /// Number of nodes
+    #[arg(short, long)]
+    nodes: u32,
+    /// Number of edges of erdos-renyi. Default: 4 per node
+    #[arg(long)]
+    edges: Option<u32>,
+    /// Edges per node of barabasi-albert, children per node of tree and telco. Default: 2, 2 and 3
+    #[arg(long)]
+    degree: Option<u32>,
+    /// Columns of grid. Default: square grid
+    #[arg(long)]
+    columns: Option<u32>,
+    /// Seed of the random kinds, the same seed generates the same graph
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+    /// Output file. Default: stdout
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// Output format (json, cytoscape, graphml, gexf or dot). Default: detected from the output extension, otherwise json
+    #[arg(short, long)]
+    format: Option<GraphFormat>,
+}
+
+/// Compute the concentric layout of a graph file.
+#[derive(Debug, Parser)]
+#[command(name = "concentric-layout", version, about)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// Graph file (json, cytoscape, graphml, gexf or dot)
+    #[arg(required = true)]
+    input: Option<PathBuf>,
+    /// Format of the input file. Default: detected from the extension or the content
+    #[arg(long)]
+    input_format: Option<GraphFormat>,
+    /// Output file. Default: stdout
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// Output format (json, cytoscape, graphml, gexf, dot or svg). Default: detected from the output extension, otherwise json
+    #[arg(short, long)]
+    format: Option<GraphFormat>,
+    /// Canvas width of the svg output in pixels
+    #[arg(long, default_value_t = 1000)]
+    svg_width: u32,
+    /// Canvas height of the svg output in pixels
+    #[arg(long, default_value_t = 1000)]
+    svg_height: u32,
+    /// Draw the node labels on the svg output, the original ids of the input file
+    #[arg(long)]
+    svg_labels: bool,
+    /// Do not draw the ring guides on the svg output
+    #[arg(long)]
+    svg_no_guides: bool,
+    /// Layout options file (json or toml), see `LayoutOptions`. The arguments below override it
+    #[arg(long)]
+    options: Option<PathBuf>,
+    /// Computing backend. Default: cpu with `--threads`, the backend of the options file, otherwise auto
+    #[arg(long, value_enum)]
+    backend: Option<Backend>,
+    /// Number of threads of the cpu backend, selects it unless `--backend` is set (or gpu in the
+    /// options file). Default: available parallelism
+    #[arg(long)]
+    threads: Option<usize>,
+    /// Smallest number of nodes computed on the gpu by the auto backend
+    #[arg(long)]
+    gpu_min_nodes: Option<usize>,
+    /// Graphics APIs of the gpu adapter, e.g. `vulkan,gl`. Default: `WGPU_BACKEND`, otherwise all
+    #[cfg(feature = "gpu")]
+    #[arg(long)]
+    gpu_backends: Option<String>,
+    /// Power preference of the gpu adapter. Default: `WGPU_POWER_PREF`, otherwise none
+    #[cfg(feature = "gpu")]
+    #[arg(long, value_enum)]
+    gpu_power: Option<GpuPower>,
+    /// Use the software gpu adapter, e.g. llvmpipe
+    #[cfg(feature = "gpu")]
+    #[arg(long)]
+    gpu_fallback_adapter: bool,
+    /// Only use a gpu adapter whose name contains this text. Default: `WGPU_ADAPTER_NAME`
+    #[cfg(feature = "gpu")]
+    #[arg(long)]
+    gpu_adapter_name: Option<String>,
+    /// X coordinate of the center
+    #[arg(long, allow_negative_numbers = true)]
+    cx: Option<f32>,
+    /// Y coordinate of the center
+    #[arg(long, allow_negative_numbers = true)]
+    cy: Option<f32>,
+    /// Z coordinate of the center of the shells
+    #[arg(long, allow_negative_numbers = true)]
+    cz: Option<f32>,
+    /// Angle in degrees of the first node of each ring
+    #[arg(long, allow_negative_numbers = true)]
+    start_angle: Option<f32>,
+    /// Placement of the nodes. Default: rings
+    #[arg(long, value_enum)]
+    mode: Option<Mode>,
+    /// Offset of the start angle per ring, breaks the alignment of the first nodes
+    #[arg(long, value_enum)]
+    stagger: Option<Stagger>,
+    /// Seed of the random stagger
+    #[arg(long, default_value_t = 0)]
+    stagger_seed: u64,
+    /// Shape of the rings
+    #[arg(long, value_enum)]
+    shape: Option<Shape>,
+    /// Width / height of the ellipses and rectangles. Default: from `--bounding-box`, otherwise 1
+    #[arg(long)]
+    aspect_ratio: Option<f32>,
+    /// Corner radius of the rounded rectangles, from 0 to 1 times the ring radius
+    #[arg(long, default_value_t = 0.0)]
+    corner_radius: f32,
+    /// Move the finished layout into this rectangle, `X1,Y1,W,H`
+    #[arg(long, value_parser = parse_bounding_box, allow_hyphen_values = true)]
+    bounding_box: Option<BoundingBox>,
+    /// Space kept free inside each side of the bounding box. Default: 30
+    #[arg(long)]
+    padding: Option<f32>,
+    /// Only centre the layout in the bounding box, without scaling it
+    #[arg(long)]
+    no_fit: bool,
+    /// Put a node on a ring level, `ID:LEVEL`, 0 is the centre, at most 1024. Can be repeated
+    #[arg(long, value_parser = parse_level)]
+    level: Vec<NodeLevel>,
+    /// Keep a node at a coordinate, `ID:X,Y`. Can be repeated
+    #[arg(long, value_parser = parse_pin, allow_hyphen_values = true)]
+    pin: Vec<PinnedNode>,
+    /// Keep a node on a ring at an angle in degrees, `ID:RING,ANGLE`. Can be repeated
+    #[arg(long, value_parser = parse_pin_ring, allow_hyphen_values = true)]
+    pin_ring: Vec<PinnedNode>,
+    /// Write the `LayoutStats` of the computation to this json file
+    #[arg(long)]
+    stats: Option<PathBuf>,
+    /// Compute the `LayoutQuality` metrics and write them to this json file
+    #[arg(long)]
+    quality: Option<PathBuf>,
+    /// Write the discs of the parent nodes of a compound graph to this json file
+    #[arg(long)]
+    compounds: Option<PathBuf>,
+    /// Node diameter of the quality overlaps and of the svg output. Default: 30
+    #[arg(long)]
+    node_size: Option<f32>,
+    /// Do not print the timing summary
+    #[arg(short, long)]
+    quiet: bool,
+}
+
+/// Parse a `X1,Y1,W,H` bounding box
+fn parse_bounding_box(value: &str) -> Result<BoundingBox, String> {
+    let values = value
+        .split(',')
+        .map(|item| item.trim().parse::<f32>())
+        .collect::<Result<Vec<f32>, _>>()
+        .map_err(|error| error.to_string())?;
+    let [x1, y1, w, h] = values[..] else {
+        return Err("expected X1,Y1,W,H".to_string());
+    };
+    let bounding_box = BoundingBox::new(x1, y1, w, h);
+    if !bounding_box.is_valid() {
+        return Err("the width and height must be positive".to_string());
+    }
+    Ok(bounding_box)
+}
+
+/// Parse a `ID:LEVEL` ring level
+fn parse_level(value: &str) -> Result<NodeLevel, String> {
+    let Some((node_id, level)) = value.split_once(':') else {
+        return Err("expected ID:LEVEL".to_string());
+    };
+    let node_id = node_id
+        .trim()
+        .parse::<u32>()
+        .map_err(|error| error.to_string())?;
+    let level = level
+        .trim()
+        .parse::<u32>()
+        .map_err(|error| error.to_string())?;
+    Ok(NodeLevel::new(node_id, level))
+}
+
+/// Parse a `ID:A,B` pin into the node id and both values
+fn parse_pin_values(value: &str) -> Result<(u32, f32, f32), String> {
+    let Some((node_id, values)) = value.split_once(':') else {
+        return Err("expected ID:A,B".to_string());
+    };
+    let node_id = node_id
+        .trim()
+        .parse::<u32>()
+        .map_err(|error| error.to_string())?;
+    let values = values
+        .split(',')
+        .map(|item| item.trim().parse::<f32>())
+        .collect::<Result<Vec<f32>, _>>()
+        .map_err(|error| error.to_string())?;
+    let [a, b] = values[..] else {
+        return Err("expected ID:A,B".to_string());
+    };
+    Ok((node_id, a, b))
+}
+
+/// Parse a `ID:X,Y` pinned coordinate
+fn parse_pin(value: &str) -> Result<PinnedNode, String> {
+    let (node_id, x, y) = parse_pin_values(value)?;
+    Ok(PinnedNode::coordinate(node_id, x, y))
+}
+
+/// Parse a `ID:RING,ANGLE` pinned ring position
+fn parse_pin_ring(value: &str) -> Result<PinnedNode, String> {
+    let (node_id, ring, angle_degree) = parse_pin_values(value)?;
+    if ring < 0.0 || ring.fract() != 0.0 {
+        return Err("the ring must be a positive integer".to_string());
+    }
+    Ok(PinnedNode::ring(node_id, ring as u32, angle_degree))
+}
+
+fn read_graph(path: &Path, format: Option<GraphFormat>) -> anyhow::Result<GraphLayoutData> {
+    let content = std::fs::read_to_string(path)?;
+    let format = format
+        .or_else(|| GraphFormat::from_extension(path))
+        .unwrap_or_else(|| GraphFormat::detect(&content));
+    format.import(&content)
+}
+
+fn get_graph_kind(args: &GenerateArgs) -> GraphKind {
+    let nodes = args.nodes;
+    match args.kind {
+        Kind::FullMesh => GraphKind::FullMesh { nodes },
+        Kind::ErdosRenyi => GraphKind::ErdosRenyi {
+            nodes,
+            edges: args.edges.unwrap_or(nodes.saturating_mul(4)),
+        },
+        Kind::BarabasiAlbert => GraphKind::BarabasiAlbert {
+            nodes,
+            edges_per_node: args.degree.unwrap_or(2),
+        },
+        Kind::Star => GraphKind::Star { nodes },
+        Kind::Ring => GraphKind::Ring { nodes },
+        Kind::Tree => GraphKind::Tree {
+            nodes,
+            arity: args.degree.unwrap_or(2),
+        },
+        Kind::Grid => GraphKind::Grid {
+            nodes,
+            columns: args
+                .columns
+                .unwrap_or_else(|| (nodes as f64).sqrt().ceil() as u32),
+        },
+        Kind::Telco => GraphKind::Telco {
+            nodes,
+            fan_out: args.degree.unwrap_or(3),
+        },
+    }
+}
+
+fn get_output_format(format: Option<GraphFormat>, output: Option<&Path>) -> GraphFormat {
+    format
+        .or_else(|| output.and_then(GraphFormat::from_extension))
+        .unwrap_or(GraphFormat::Json)
+}
+
+/// Write to the output file, otherwise to stdout
+fn write_output(output: Option<&Path>, content: String) -> anyhow::Result<()> {
+    match output {
+        Some(path) => Ok(std::fs::write(path, content)?),
+        None => {
+            let mut stdout = std::io::stdout().lock();
+            stdout.write_all(content.as_bytes())?;
+            Ok(stdout.write_all(b"\n")?)
+        }
+    }
+}
+
+fn generate(args: &GenerateArgs) -> ExitCode {
+    let graph = get_graph_kind(args).generate(args.seed);
+    let output = args.output.as_deref();
+    let result = match get_output_format(args.format, output) {
+        GraphFormat::Svg => Err(anyhow::anyhow!("svg requires a computed layout")),
+        format => format.export(&graph.nodes, &graph.edges, &[]),
+    }
+    .and_then(|content| write_output(output, content));
+    if let Err(error) = result {
+        eprintln!("error: unable to write the output: {}", error);
+        return ExitCode::from(EXIT_FAILURE);
+    }
+    ExitCode::SUCCESS
+}
+
+fn get_computing_config(
+    backend: Backend,
+    threads: Option<usize>,
+) -> anyhow::Result<ComputingConfig> {
+    let threads = threads.unwrap_or_else(|| {
+        std::thread::available_parallelism()
+            .map(|value| value.get())
+            .unwrap_or(1)
+    });
+    let config = match backend {
+        Backend::Cpu => ComputingConfig::Cpu(threads),
+        #[cfg(feature = "gpu")]
+        Backend::Gpu => ComputingConfig::Gpu,
+        #[cfg(not(feature = "gpu"))]
+        Backend::Gpu => anyhow::bail!("gpu backend requires the `gpu` feature"),
+        Backend::Auto => ComputingConfig::Auto,
+    };
+    Ok(config)
+}
+
+/// Apply the wgpu environment variables then the arguments over the gpu options of the file
+#[cfg(feature = "gpu")]
+fn get_gpu_options(args: &Args, options: GpuOptions) -> GpuOptions {
+    let mut options = options.with_env();
+    if let Some(backends) = &args.gpu_backends {
+        options.backends = Backends::from_comma_list(backends);
+    }
+    if let Some(power) = args.gpu_power {
+        options.power_preference = match power {
+            GpuPower::Low => PowerPreference::LowPower,
+            GpuPower::High => PowerPreference::HighPerformance,
+        };
+    }
+    if args.gpu_fallback_adapter {
+        options.force_fallback_adapter = true;
+    }
+    if let Some(adapter_name) = &args.gpu_adapter_name {
+        options.adapter_name = Some(adapter_name.clone());
+    }
+    options
+}
+
+/// Read the options file then apply the arguments over it
+fn get_layout_options(args: &Args) -> anyhow::Result<LayoutOptions> {
+    let mut options = match &args.options {
+        Some(path) => LayoutOptions::from_file(path)?,
+        None => LayoutOptions::default(),
+    };
+    // The auto backend has no thread count, `--threads` alone selects the cpu
+    let backend = args.backend.or(match (&args.options, &options.backend) {
+        #[cfg(feature = "gpu")]
+        (Some(_), ComputingConfig::Gpu) => None,
+        _ if args.threads.is_some() => Some(Backend::Cpu),
+        (None, _) => Some(Backend::Auto),
+        _ => None,
+    });
+    if let Some(backend) = backend {
+        options.backend = get_computing_config(backend, args.threads)?;
+    }
+    if let Some(gpu_min_nodes) = args.gpu_min_nodes {
+        options.gpu_min_nodes = gpu_min_nodes;
+    }
+    #[cfg(feature = "gpu")]
+    {
+        options.gpu = get_gpu_options(args, options.gpu);
+    }
+    if let Some(cx) = args.cx {
+        options.cx = cx;
+    }
+    if let Some(cy) = args.cy {
+        options.cy = cy;
+    }
+    if let Some(cz) = args.cz {
+        options.cz = cz;
+    }
+    if let Some(start_angle) = args.start_angle {
+        options.start_angle = start_angle;
+    }
+    if let Some(mode) = args.mode {
+        options.mode = match mode {
+            Mode::Rings => LayoutMode::Rings,
+            Mode::Shells => LayoutMode::Shells,
+            Mode::Spiral => LayoutMode::Spiral,
+        };
+    }
+    if let Some(stagger) = args.stagger {
+        options.stagger = match stagger {
+            Stagger::None => RingStagger::None,
+            Stagger::HalfStep => RingStagger::HalfStep,
+            Stagger::GoldenAngle => RingStagger::GoldenAngle,
+            Stagger::Random => RingStagger::Random {
+                seed: args.stagger_seed,
+            },
+        };
+    }
+    if let Some(shape) = args.shape {
+        options.shape = match shape {
+            Shape::Circle => RingShape::Circle,
+            Shape::Ellipse => RingShape::Ellipse {
+                aspect_ratio: args.aspect_ratio,
+            },
+            Shape::RoundedRectangle => RingShape::RoundedRectangle {
+                aspect_ratio: args.aspect_ratio,
+                corner_radius: args.corner_radius,
+            },
+            Shape::Square => RingShape::RoundedRectangle {
+                aspect_ratio: Some(1.0),
+                corner_radius: 0.0,
+            },
+        };
+    }
+    if let Some(bounding_box) = args.bounding_box {
+        options.bounding_box = Some(bounding_box);
+    }
+    if let Some(padding) = args.padding {
+        options.padding = padding;
+    }
+    if args.no_fit {
+        options.fit = false;
+    }
+    if !args.level.is_empty() {
+        options.levels = args.level.clone();
+    }
+    if !args.pin.is_empty() || !args.pin_ring.is_empty() {
+        options.pinned = [args.pin.as_slice(), args.pin_ring.as_slice()].concat();
+    }
+    Ok(options)
+}
+
+fn format_stage(timing: &StageTiming) -> String {
+    let mut result = format!("{:.3} ms", timing.micros as f64 / 1000.0);
+    let details = [
+        ("upload", timing.upload_micros),
+        ("dispatch", timing.dispatch_micros),
+        ("readback", timing.readback_micros),
+    ]
+    .iter()
+    .filter_map(|(name, micros)| {
+        micros.map(|micros| format!("{} {:.3} ms", name, micros as f64 / 1000.0))
+    })
+    .collect::<Vec<String>>();
+    if !details.is_empty() {
+        result = format!("{} ({})", result, details.join(", "));
+    }
+    result
+}
+
+fn get_svg_options(args: &Args, graph: &GraphLayoutData, layout: &LayoutOptions) -> SvgOptions {
+    let mut options = SvgOptions::new()
+        .size(args.svg_width, args.svg_height)
+        .ring_guides(!args.svg_no_guides && layout.mode != LayoutMode::Spiral)
+        .ring_geometry(layout.get_ring_geometry())
+        .labels(args.svg_labels);
+    if args.svg_labels && !graph.source_ids.is_empty() {
+        options = options.node_labels(graph.source_ids.clone());
+    }
+    if let Some(node_size) = args.node_size {
+        options = options.node_size(node_size);
+    }
+    options
+}
+
+/// Write a json report, e.g. the stats or the quality
+fn write_report<T: serde::Serialize>(path: &Path, report: &T) -> anyhow::Result<()> {
+    let content = serde_json::to_string_pretty(report)?;
+    Ok(std::fs::write(path, content)?)
+}
+
+fn print_quality(quality: &LayoutQuality) {
+    eprintln!("overlaps {}", quality.overlaps);
+    eprintln!(
+        "nearest  min {:.2}, avg {:.2}",
+        quality.min_neighbour_distance, quality.avg_neighbour_distance
+    );
+    eprintln!(
+        "length   min {:.2}, max {:.2}, mean {:.2}, std dev {:.2}",
+        quality.edge_length.min,
+        quality.edge_length.max,
+        quality.edge_length.mean,
+        quality.edge_length.std_dev
+    );
+    eprintln!("crossing {}", quality.crossings);
+    eprintln!(
+        "angles   min {:.2}°, avg {:.2}°",
+        quality.min_angular_resolution, quality.avg_angular_resolution
+    );
+    eprintln!("occupied {:.1} %", quality.ring_occupancy * 100.0);
+}
+
+fn print_summary(layout: &ConcentricLayout, positions: &[NodePositionData], total_micros: u128) {
+    let backend = match layout
+        .used_backend
+        .as_ref()
+        .unwrap_or(&layout.options.backend)
+    {
+        ComputingConfig::Cpu(threads) => format!("cpu ({} threads)", threads),
+        #[cfg(feature = "gpu")]
+        ComputingConfig::Gpu => "gpu".to_string(),
+        ComputingConfig::Auto => "auto".to_string(),
+    };
+    let rings = positions
+        .iter()
+        .map(|item| item.index + 1)
+        .max()
+        .unwrap_or(0);
+    let layout_micros = layout
+        .timer
+        .as_ref()
+        .and_then(|timer| timer.micros)
+        .unwrap_or(0);
+    eprintln!("backend  {}", backend);
+    if let Some(error) = &layout.gpu_error {
+        eprintln!("gpu      fell back to cpu: {}", error);
+    }
+    eprintln!("nodes    {}", layout.nodes.len());
+    eprintln!("edges    {}", layout.edges.len());
+    eprintln!("rings    {}", rings);
+    if !layout.compounds.is_empty() {
+        eprintln!("parents  {}", layout.compounds.len());
+    }
+    if let Some(stats) = &layout.stats {
+        eprintln!("degree   {}", format_stage(&stats.degree));
+        eprintln!("norm     {}", format_stage(&stats.normalize));
+        eprintln!("sort     {}", format_stage(&stats.sort));
+        eprintln!("position {}", format_stage(&stats.positions));
+        if let Some(bounding_box) = &stats.bounding_box {
+            eprintln!(
+                "bounds   {:.1},{:.1} {:.1}x{:.1}",
+                bounding_box.x1, bounding_box.y1, bounding_box.w, bounding_box.h
+            );
+        }
+    }
+    eprintln!("layout   {:.3} ms", layout_micros as f64 / 1000.0);
+    eprintln!("total    {:.3} ms", total_micros as f64 / 1000.0);
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    if let Some(Command::Generate(generate_args)) = &args.command {
+        return generate(generate_args);
+    }
+    let timer = Instant::now();
+
+    // Required by clap without a subcommand
+    let input = args.input.as_deref().unwrap_or(Path::new(""));
+    let graph = match read_graph(input, args.input_format) {
+        Ok(graph) => graph,
+        Err(error) => {
+            eprintln!("error: unable to read {}: {}", input.display(), error);
+            return ExitCode::from(EXIT_INVALID_INPUT);
+        }
+    };
+    let options = match get_layout_options(&args) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("error: {}", error);
+            return ExitCode::from(EXIT_FAILURE);
+        }
+    };
+    if let Err(error) = Levels::validate(&options.levels, &graph.nodes) {
+        eprintln!("error: {}", error);
+        return ExitCode::from(EXIT_INVALID_INPUT);
+    }
+    let mut layout = ConcentricLayout::new(&graph.nodes, &graph.edges, options);
+    layout.parents = &graph.parents;
+    let positions = match layout.execute_blocking() {
+        Ok(positions) => positions,
+        Err(error) => {
+            eprintln!("error: {}", error);
+            return match error.downcast_ref::<ValidationError>() {
+                Some(_) => ExitCode::from(EXIT_INVALID_INPUT),
+                None => ExitCode::from(EXIT_FAILURE),
+            };
+        }
+    };
+
+    let output = args.output.as_deref();
+    let result = match get_output_format(args.format, output) {
+        GraphFormat::Svg => Ok(Svg::render(
+            &graph.nodes,
+            &graph.edges,
+            &positions,
+            &get_svg_options(&args, &graph, &layout.options),
+        )),
+        format => format.export(&graph.nodes, &graph.edges, &positions),
+    }
+    .and_then(|content| write_output(output, content));
+    if let Err(error) = result {
+        eprintln!("error: unable to write the output: {}", error);
+        return ExitCode::from(EXIT_FAILURE);
+    }
+    if let Some(path) = &args.stats
+        && let Err(error) = write_report(path, &layout.stats)
+    {
+        eprintln!("error: unable to write the stats: {}", error);
+        return ExitCode::from(EXIT_FAILURE);
+    }
+    if let Some(path) = &args.compounds
+        && let Err(error) = write_report(path, &layout.compounds)
+    {
+        eprintln!("error: unable to write the compounds: {}", error);
+        return ExitCode::from(EXIT_FAILURE);
+    }
+    let quality = args.quality.as_ref().map(|path| {
+        let fit_scale = layout.stats.as_ref().and_then(|stats| stats.fit_scale);
+        let mut options =
+            QualityOptions::from_layout(&layout.options).fit_scale(fit_scale.unwrap_or(1.0));
+        if let Some(node_size) = args.node_size {
+            options = options.node_size(node_size);
+        }
+        (
+            path,
+            LayoutQuality::compute(&positions, &graph.edges, &options),
+        )
+    });
+    if let Some((path, quality)) = &quality
+        && let Err(error) = write_report(path, quality)
+    {
+        eprintln!("error: unable to write the quality: {}", error);
+        return ExitCode::from(EXIT_FAILURE);
+    }
+    if !args.quiet {
+        print_summary(&layout, &positions, timer.elapsed().as_micros());
+        if let Some((_, quality)) = &quality {
+            print_quality(quality);
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+#[cfg(test)]
+pub mod test_cli {
+    use super::*;
+    use concentric_layout::entities::{Edge, Node};
+
+    #[test]
+    fn test_threads_without_backend() {
+        let args = Args::parse_from(["concentric-layout", "graph.json", "--threads", "2"]);
+        let options = get_layout_options(&args).unwrap();
+        assert_eq!(options.backend, ComputingConfig::Cpu(2));
+        let args = Args::parse_from(["concentric-layout", "graph.json"]);
+        assert_eq!(
+            get_layout_options(&args).unwrap().backend,
+            ComputingConfig::Auto
+        );
+
+        let nodes: Vec<Node> = (1..=10).map(|id| Node { id }).collect();
+        let edges: Vec<Edge> = (2..=10)
+            .map(|id| Edge {
+                id,
+                source_id: 1,
+                target_id: id,
+            })
+            .collect();
+        let mut layout = ConcentricLayout::new(&nodes, &edges, options);
+        layout.execute_blocking().unwrap();
+        #[cfg(feature = "parallel")]
+        let threads = 2;
+        #[cfg(not(feature = "parallel"))]
+        let threads = 1;
+        assert_eq!(layout.stats.unwrap().threads, threads);
+        assert_eq!(layout.used_backend, Some(ComputingConfig::Cpu(threads)));
+    }
+}
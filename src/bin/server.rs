@@ -0,0 +1,57 @@
+use clap::Parser;
+use concentric_layout::gpu::GpuOptions;
+use concentric_layout::server::{ServerConfig, serve};
+
+/// HTTP service computing concentric layouts.
+#[derive(Debug, Parser)]
+#[command(name = "concentric-layout-server", version, about)]
+struct Args {
+    /// Address to listen on
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    address: String,
+    /// Maximum size of a request body in bytes
+    #[arg(long)]
+    max_body_bytes: Option<usize>,
+    /// Maximum number of nodes per request
+    #[arg(long)]
+    max_nodes: Option<usize>,
+    /// Maximum number of edges per request
+    #[arg(long)]
+    max_edges: Option<usize>,
+    /// Number of threads of the shared cpu thread pool. Default: available parallelism
+    #[arg(long)]
+    threads: Option<usize>,
+    /// Maximum number of layouts computed at the same time
+    #[arg(long)]
+    max_concurrent_layouts: Option<usize>,
+    /// Do not use the gpu
+    #[arg(long)]
+    no_gpu: bool,
+    /// Only use a gpu adapter whose name contains this text. Default: `WGPU_ADAPTER_NAME`
+    #[arg(long)]
+    gpu_adapter_name: Option<String>,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+    let default = ServerConfig::default();
+    let mut gpu_options = GpuOptions::from_env();
+    if let Some(adapter_name) = args.gpu_adapter_name {
+        gpu_options.adapter_name = Some(adapter_name);
+    }
+    let config = ServerConfig {
+        address: args.address,
+        max_body_bytes: args.max_body_bytes.unwrap_or(default.max_body_bytes),
+        max_nodes: args.max_nodes.unwrap_or(default.max_nodes),
+        max_edges: args.max_edges.unwrap_or(default.max_edges),
+        cpu_threads: args.threads.unwrap_or(default.cpu_threads),
+        max_concurrent_layouts: args
+            .max_concurrent_layouts
+            .unwrap_or(default.max_concurrent_layouts),
+        gpu: !args.no_gpu,
+        gpu_options,
+    };
+    eprintln!("listening on {}", config.address);
+    serve(config).await
+}
@@ -0,0 +1,482 @@
+//! Compound (nested) layouts, as the Cytoscape.js compound nodes: the children of a parent, e.g. the
+//! devices of a rack or the racks of a site, are laid out concentrically inside the disc of the parent
+//! and the parents are laid out with their siblings, up to the top level.
+
+use crate::entities::{Edge, Node, NodePositionData};
+use crate::{BoundingBox, LayoutMode, LayoutOptions, RingShape, ValidationPolicy, Viewport};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Parent of a node, e.g. the rack of a device or the site of a rack
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct NodeParent {
+    pub node_id: u32,
+    pub parent_id: u32,
+}
+
+impl NodeParent {
+    pub fn new(node_id: u32, parent_id: u32) -> Self {
+        Self { node_id, parent_id }
+    }
+}
+
+/// Disc of a parent node holding the layout of its children, in the final coordinates.
+/// `width` and `height` are its diameter, as the size of a Cytoscape.js compound node.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CompoundNode {
+    pub node_id: u32,
+    pub parent_id: Option<u32>,
+    pub x: f32,
+    pub y: f32,
+    pub radius: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+/// Children of a parent (of the top level without parent) and the edges between them.
+/// The positions are around the centre of the parent, the top level around `cx`, `cy`.
+#[derive(Debug, Clone, Default)]
+pub struct Container {
+    pub parent_id: Option<u32>,
+    pub nodes: Vec<Node>,
+    pub edges: Vec<Edge>,
+    pub positions: Vec<NodePositionData>,
+}
+
+/// Final positions of a compound layout, see `Compound::get_result`.
+#[derive(Debug, Clone, Default)]
+pub struct CompoundResult {
+    pub positions: Vec<NodePositionData>,
+    pub compounds: Vec<CompoundNode>,
+    /// Bounding box of the nodes and discs
+    pub bounding_box: Option<BoundingBox>,
+    /// Factor of the fit into `options.bounding_box`, see `Viewport::get_scale`
+    pub fit_scale: Option<f32>,
+}
+
+/// Layout of a compound graph, one concentric layout per container from the deepest parents to the
+/// top level. See `ConcentricLayout::parents`.
+#[derive(Debug, Clone, Default)]
+pub struct Compound {
+    /// Containers in the order of the layouts, the top level is the last one
+    pub containers: Vec<Container>,
+    /// Radius of the disc of each parent, known once its children are laid out
+    pub radiuses: HashMap<u32, f32>,
+    pub options: LayoutOptions,
+    parents: HashMap<u32, u32>,
+}
+
+impl Compound {
+    /// Group the nodes by parent. An edge between two containers connects their ancestors which are
+    /// siblings, e.g. two racks of a site for an edge between two of their devices, and an edge between a
+    /// node and one of its ancestors is left out.
+    pub fn new(
+        nodes: &[Node],
+        edges: &[Edge],
+        parents: &[NodeParent],
+        options: &LayoutOptions,
+    ) -> anyhow::Result<Self> {
+        if options.mode == LayoutMode::Shells {
+            anyhow::bail!("compound layouts are 2D, the shells mode is not supported");
+        }
+        let parents = Self::get_parents(nodes, parents)?;
+        if !options.pinned.is_empty() && options.bounding_box.is_some() {
+            anyhow::bail!(
+                "pinned nodes would move with the bounding box of a compound layout, set only one of them"
+            );
+        }
+        if let Some(item) = options
+            .pinned
+            .iter()
+            .find(|item| parents.contains_key(&item.node_id))
+        {
+            anyhow::bail!(
+                "pinned node {} has a parent, only the top level nodes can be pinned",
+                item.node_id
+            );
+        }
+        let get_path = |node_id: u32| {
+            let mut path = vec![node_id];
+            while let Some(parent_id) = parents.get(path.last().unwrap()) {
+                path.push(*parent_id);
+            }
+            path.reverse();
+            path
+        };
+        let mut containers: Vec<Container> = Vec::new();
+        let mut container_index: HashMap<Option<u32>, usize> = HashMap::new();
+        let mut depths: Vec<usize> = Vec::new();
+        let mut get_container = |containers: &mut Vec<Container>, parent_id: Option<u32>| {
+            *container_index.entry(parent_id).or_insert_with(|| {
+                containers.push(Container {
+                    parent_id,
+                    ..Default::default()
+                });
+                depths.push(
+                    parent_id
+                        .map(|node_id| get_path(node_id).len())
+                        .unwrap_or(0),
+                );
+                containers.len() - 1
+            })
+        };
+        for node in nodes {
+            let index = get_container(&mut containers, parents.get(&node.id).copied());
+            containers[index].nodes.push(*node);
+        }
+        for edge in edges {
+            let source = get_path(edge.source_id);
+            let target = get_path(edge.target_id);
+            let common = source
+                .iter()
+                .zip(target.iter())
+                .take_while(|(a, b)| a == b)
+                .count();
+            if common == source.len() || common == target.len() {
+                continue;
+            }
+            let index = get_container(
+                &mut containers,
+                common.checked_sub(1).map(|index| source[index]),
+            );
+            containers[index].edges.push(Edge {
+                id: edge.id,
+                source_id: source[common],
+                target_id: target[common],
+            });
+        }
+        // Deepest parents first, the top level last
+        let mut order: Vec<usize> = (0..containers.len()).collect();
+        order.sort_by_key(|index| std::cmp::Reverse(depths[*index]));
+        let mut containers: Vec<Option<Container>> = containers.into_iter().map(Some).collect();
+        Ok(Self {
+            containers: order
+                .iter()
+                .filter_map(|index| containers[*index].take())
+                .collect(),
+            radiuses: HashMap::new(),
+            options: options.clone(),
+            parents,
+        })
+    }
+
+    /// The nodes and the parents are in the graph, with a single parent per node and without cycle
+    pub fn validate(parents: &[NodeParent], nodes: &[Node]) -> anyhow::Result<()> {
+        Self::get_parents(nodes, parents).map(|_| ())
+    }
+
+    /// Parent of each node. The nodes and the parents are in the graph, with a single parent per
+    /// node and without cycle.
+    fn get_parents(nodes: &[Node], parents: &[NodeParent]) -> anyhow::Result<HashMap<u32, u32>> {
+        let node_ids: HashSet<u32> = nodes.iter().map(|node| node.id).collect();
+        let mut result = HashMap::with_capacity(parents.len());
+        for item in parents {
+            for node_id in [item.node_id, item.parent_id] {
+                if !node_ids.contains(&node_id) {
+                    anyhow::bail!("node {} of a parent is not in the graph", node_id);
+                }
+            }
+            if result.insert(item.node_id, item.parent_id).is_some() {
+                anyhow::bail!("node {} has more than one parent", item.node_id);
+            }
+        }
+        for node_id in result.keys() {
+            let mut current = *node_id;
+            for _ in 0..=result.len() {
+                match result.get(&current) {
+                    Some(parent_id) if parent_id == node_id => {
+                        anyhow::bail!("node {} is its own ancestor", node_id);
+                    }
+                    Some(parent_id) => current = *parent_id,
+                    None => break,
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    /// Options of the layout of a container. The spacing and the radius step grow by the diameter
+    /// of the largest child disc so the discs do not overlap. The parents are laid out around 0, 0,
+    /// the bounding box only applies to the final positions, see `get_result`.
+    pub fn get_options(&self, container: &Container) -> LayoutOptions {
+        let node_ids: HashSet<u32> = container.nodes.iter().map(|node| node.id).collect();
+        let footprint = container
+            .nodes
+            .iter()
+            .filter_map(|node| self.radiuses.get(&node.id))
+            .fold(0_f32, |result, radius| result.max(*radius));
+        let mut options = self.options.clone();
+        options.min_node_spacing += 2.0 * footprint;
+        options.step_radius += (2.0 * footprint).ceil() as u32;
+        options.validation = ValidationPolicy::Skip;
+        options
+            .levels
+            .retain(|item| node_ids.contains(&item.node_id));
+        options
+            .pinned
+            .retain(|item| node_ids.contains(&item.node_id));
+        // The aspect ratio is resolved before the bounding box is removed
+        if let Some(bounding_box) = options.bounding_box.take().filter(BoundingBox::is_valid) {
+            let ratio = Some(bounding_box.w / bounding_box.h);
+            match &mut options.shape {
+                RingShape::Ellipse { aspect_ratio }
+                | RingShape::RoundedRectangle { aspect_ratio, .. } => {
+                    *aspect_ratio = aspect_ratio.or(ratio);
+                }
+                RingShape::Circle => {}
+            }
+        }
+        if container.parent_id.is_some() {
+            options.cx = 0.0;
+            options.cy = 0.0;
+        }
+        options
+    }
+
+    /// Keep the positions of the container at `index`, the disc of its parent holds the children
+    /// discs plus half of `min_node_spacing`.
+    pub fn set_positions(&mut self, index: usize, positions: Vec<NodePositionData>) {
+        let container = &mut self.containers[index];
+        if let Some(parent_id) = container.parent_id {
+            let radius = positions
+                .iter()
+                .map(|item| {
+                    (item.x - item.cx).hypot(item.y - item.cy)
+                        + self.radiuses.get(&item.node_id).copied().unwrap_or(0.0)
+                })
+                .fold(0_f32, f32::max);
+            self.radiuses
+                .insert(parent_id, radius + self.options.min_node_spacing / 2.0);
+        }
+        container.positions = positions;
+    }
+
+    /// Positions of all the nodes and the parent discs in the final coordinates: the children
+    /// follow their parent from the top level down, then everything is moved into the
+    /// `options.bounding_box` with the discs inside it, see `Viewport::fit`.
+    pub fn get_result(self) -> anyhow::Result<CompoundResult> {
+        let mut centres: HashMap<u32, (f32, f32)> = HashMap::new();
+        let mut positions: Vec<NodePositionData> = Vec::new();
+        for container in self.containers.into_iter().rev() {
+            let (cx, cy) = match container.parent_id {
+                Some(parent_id) => centres.get(&parent_id).copied().unwrap_or_default(),
+                None => (0.0, 0.0),
+            };
+            for item in container.positions {
+                let item = NodePositionData {
+                    x: item.x + cx,
+                    y: item.y + cy,
+                    cx: item.cx + cx,
+                    cy: item.cy + cy,
+                    ..item
+                };
+                centres.insert(item.node_id, (item.x, item.y));
+                positions.push(item);
+            }
+        }
+        // Two opposite corners per disc keep the discs inside the bounding box
+        let total_nodes = positions.len();
+        let mut compounds: Vec<(u32, f32)> = self.radiuses.into_iter().collect();
+        compounds.sort_by_key(|(node_id, _)| *node_id);
+        for (node_id, radius) in compounds.iter() {
+            let (x, y) = centres.get(node_id).copied().unwrap_or_default();
+            for (x, y) in [(x - radius, y - radius), (x + radius, y + radius)] {
+                positions.push(NodePositionData {
+                    x,
+                    y,
+                    ..Default::default()
+                });
+            }
+        }
+        let fit_scale = Viewport::get_scale(&positions, &self.options);
+        let bounding_box = Viewport::fit(&mut positions, &self.options)?;
+        let compounds = compounds
+            .iter()
+            .zip(positions[total_nodes..].chunks(2))
+            .map(|((node_id, _), corners)| {
+                let radius = (corners[1].x - corners[0].x) / 2.0;
+                CompoundNode {
+                    node_id: *node_id,
+                    parent_id: self.parents.get(node_id).copied(),
+                    x: corners[0].x + radius,
+                    y: corners[0].y + radius,
+                    radius,
+                    width: 2.0 * radius,
+                    height: 2.0 * radius,
+                }
+            })
+            .collect();
+        positions.truncate(total_nodes);
+        Ok(CompoundResult {
+            positions,
+            compounds,
+            bounding_box,
+            fit_scale,
+        })
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+pub mod test_compound {
+    use super::*;
+    use crate::ComputingConfig;
+    use crate::ConcentricLayout;
+
+    /// 2 sites of 3 racks of 4 devices, the devices of a rack are a star and the racks of a site
+    /// are linked by an edge between their first devices
+    fn get_sites() -> (Vec<Node>, Vec<Edge>, Vec<NodeParent>) {
+        let mut nodes = Vec::new();
+        let mut edges = Vec::new();
+        let mut parents = Vec::new();
+        let mut add_edge = |source_id: u32, target_id: u32| {
+            edges.push(Edge {
+                id: edges.len() as u32,
+                source_id,
+                target_id,
+            })
+        };
+        for site in 0..2 {
+            let site_id = 100 + site;
+            nodes.push(Node { id: site_id });
+            for rack in 0..3 {
+                let rack_id = 10 + site * 10 + rack;
+                nodes.push(Node { id: rack_id });
+                parents.push(NodeParent::new(rack_id, site_id));
+                let first = 1000 + rack_id * 10;
+                for device in 0..4 {
+                    nodes.push(Node { id: first + device });
+                    parents.push(NodeParent::new(first + device, rack_id));
+                    if device > 0 {
+                        add_edge(first, first + device);
+                    }
+                }
+                if rack > 0 {
+                    add_edge(1000 + (10 + site * 10) * 10, first);
+                }
+            }
+        }
+        add_edge(1100, 1200);
+        (nodes, edges, parents)
+    }
+
+    #[tokio::test]
+    async fn test_compound() {
+        let (nodes, edges, parents) = get_sites();
+        let options = LayoutOptions::new().backend(ComputingConfig::Cpu(2));
+        let compound = Compound::new(&nodes, &edges, &parents, &options).unwrap();
+        // The racks first, then the sites, then the top level with the edge between the sites
+        let parent_ids: Vec<Option<u32>> = compound
+            .containers
+            .iter()
+            .map(|container| container.parent_id)
+            .collect();
+        assert_eq!(parent_ids.len(), 9);
+        assert!(
+            parent_ids[..6]
+                .iter()
+                .all(|item| item.is_some_and(|id| id < 100))
+        );
+        assert_eq!(parent_ids[8], None);
+        let top = compound.containers.last().unwrap();
+        assert_eq!(top.edges.len(), 1);
+        assert_eq!((top.edges[0].source_id, top.edges[0].target_id), (100, 101));
+
+        let mut layout = ConcentricLayout::new(&nodes, &edges, options.clone());
+        layout.parents = &parents;
+        let positions = layout.execute().await.unwrap();
+        assert_eq!(positions.len(), nodes.len());
+        assert_eq!(layout.compounds.len(), 8);
+        let get = |node_id: u32| {
+            *positions
+                .iter()
+                .find(|item| item.node_id == node_id)
+                .unwrap()
+        };
+        let get_disc = |node_id: u32| {
+            *layout
+                .compounds
+                .iter()
+                .find(|item| item.node_id == node_id)
+                .unwrap()
+        };
+        for disc in layout.compounds.iter() {
+            // The disc is centred on the parent and holds its children
+            let parent = get(disc.node_id);
+            assert!((parent.x - disc.x).abs() < 0.01 && (parent.y - disc.y).abs() < 0.01);
+            assert_eq!(disc.width, 2.0 * disc.radius);
+            let children: Vec<u32> = parents
+                .iter()
+                .filter(|item| item.parent_id == disc.node_id)
+                .map(|item| item.node_id)
+                .collect();
+            for child_id in children.iter() {
+                let child = get(*child_id);
+                let child_radius = layout
+                    .compounds
+                    .iter()
+                    .find(|item| item.node_id == *child_id)
+                    .map_or(0.0, |item| item.radius);
+                let distance = (child.x - disc.x).hypot(child.y - disc.y);
+                assert!(distance + child_radius <= disc.radius + 0.01);
+            }
+            // The discs of the siblings do not overlap
+            for other_id in children.iter() {
+                for sibling_id in children.iter().filter(|id| *id > other_id) {
+                    if let (Some(a), Some(b)) = (
+                        layout
+                            .compounds
+                            .iter()
+                            .find(|item| item.node_id == *other_id),
+                        layout
+                            .compounds
+                            .iter()
+                            .find(|item| item.node_id == *sibling_id),
+                    ) {
+                        assert!((a.x - b.x).hypot(a.y - b.y) >= a.radius + b.radius - 0.01);
+                    }
+                }
+            }
+        }
+        assert_eq!(get_disc(10).parent_id, Some(100));
+        assert_eq!(get_disc(100).parent_id, None);
+        let stats = layout.stats.as_ref().unwrap();
+        assert_eq!((stats.nodes, stats.edges), (nodes.len(), edges.len()));
+
+        #[cfg(feature = "gpu")]
+        {
+            let mut layout = ConcentricLayout::new(
+                &nodes,
+                &edges,
+                options.clone().backend(ComputingConfig::Gpu),
+            );
+            layout.parents = &parents;
+            let gpu = layout.execute().await.unwrap();
+            assert_eq!(gpu.len(), positions.len());
+            for item in positions.iter() {
+                let other = gpu.iter().find(|gpu| gpu.node_id == item.node_id).unwrap();
+                assert!((other.x - item.x).abs() < 0.01 && (other.y - item.y).abs() < 0.01);
+            }
+        }
+
+        let cycle = [NodeParent::new(10, 11), NodeParent::new(11, 10)];
+        let mut layout = ConcentricLayout::new(&nodes, &edges, options.clone());
+        layout.parents = &cycle;
+        assert!(layout.execute().await.is_err());
+        let mut pinned = ConcentricLayout::new(
+            &nodes,
+            &edges,
+            options
+                .clone()
+                .bounding_box(BoundingBox::new(0.0, 0.0, 800.0, 600.0))
+                .pinned(vec![crate::PinnedNode::coordinate(100, 0.0, 0.0)]),
+        );
+        pinned.parents = &parents;
+        assert!(pinned.execute().await.is_err());
+        let unknown = [NodeParent::new(10, 42)];
+        layout.parents = &unknown;
+        assert!(layout.execute().await.is_err());
+    }
+}
@@ -1,74 +1,524 @@
-use crate::Timer;
+use crate::compound::{Compound, CompoundNode, CompoundResult, Container, NodeParent};
 use crate::cpu::CpuConcentric;
 use crate::entities::{Edge, Node, NodePositionData};
-use crate::gpu::GpuConcentric;
+#[cfg(feature = "gpu")]
+use crate::gpu::{GpuAdapter, GpuConcentric};
+use crate::trace::event;
+use crate::{LayoutOptions, LayoutStats, Timer, Validation, ValidationPolicy};
+#[cfg(feature = "parallel")]
+use rayon::ThreadPool;
+#[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
 use rayon::ThreadPoolBuilder;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "parallel")]
+use std::sync::Arc;
+use web_time::Instant;
 
 /// The kind of computing
 /// Kinds:
-/// - CPU: use cpu parallel computing. It accepts the number of threads as parameter.
-/// - GPU: use gpu parallel computing
-#[derive(Debug, Clone)]
+/// - CPU: use cpu parallel computing. It accepts the number of threads as parameter, 0 for automatic.
+/// - GPU: use gpu parallel computing. Requires the `gpu` feature.
+/// - Auto: gpu when an adapter is available and the graph has at least `options.gpu_min_nodes` nodes,
+///   otherwise cpu with one thread per core. A failing gpu computation falls back to the cpu.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum ComputingConfig {
     Cpu(usize),
+    #[cfg(feature = "gpu")]
     Gpu,
+    Auto,
 }
 
+impl Default for ComputingConfig {
+    fn default() -> Self {
+        ComputingConfig::Cpu(0)
+    }
+}
+
+/// Layout of a borrowed graph, the nodes and edges are not copied.
 #[derive(Debug)]
-pub struct ConcentricLayout {
-    pub config: ComputingConfig,
-    pub nodes: Vec<Node>,
-    pub edges: Vec<Edge>,
-    pub cx: Option<f32>,
-    pub cy: Option<f32>,
+pub struct ConcentricLayout<'a> {
+    pub options: LayoutOptions,
+    pub nodes: &'a [Node],
+    pub edges: &'a [Edge],
+    /// Parents of the nodes of a compound graph, see `Compound`. Each parent holds the layout of its
+    /// children inside a disc and is laid out with its siblings. Default: none, a flat layout
+    pub parents: &'a [NodeParent],
+    /// Thread pool used by the cpu computing instead of spawning a new one. e.g. shared by a server
+    #[cfg(feature = "parallel")]
+    pub thread_pool: Option<Arc<ThreadPool>>,
+    /// Adapter used by the gpu computing instead of requesting a new one. e.g. shared by a server
+    #[cfg(feature = "gpu")]
+    pub gpu_adapter: Option<GpuAdapter>,
     pub timer: Option<Timer>,
+    /// Timings per stage and sizes of the last computation, see `LayoutStats`
+    pub stats: Option<LayoutStats>,
+    /// Backend that computed the last result, `Auto` is resolved to `Cpu` or `Gpu`
+    pub used_backend: Option<ComputingConfig>,
+    /// Error of the gpu computation when `Auto` fell back to the cpu
+    pub gpu_error: Option<String>,
+    /// Discs of the parents of the last computation, empty without `parents`
+    pub compounds: Vec<CompoundNode>,
 }
 
-impl ConcentricLayout {
-    pub fn new(
-        config: &ComputingConfig,
-        nodes: &Vec<Node>,
-        edges: &Vec<Edge>,
-        cx: &Option<f32>,
-        cy: &Option<f32>,
-    ) -> Self {
+impl<'a> ConcentricLayout<'a> {
+    pub fn new(nodes: &'a [Node], edges: &'a [Edge], options: LayoutOptions) -> Self {
         Self {
-            config: config.clone(),
-            nodes: nodes.to_owned(),
-            edges: edges.to_owned(),
-            cx: cx.to_owned(),
-            cy: cy.to_owned(),
+            options,
+            nodes,
+            edges,
+            parents: &[],
+            #[cfg(feature = "parallel")]
+            thread_pool: None,
+            #[cfg(feature = "gpu")]
+            gpu_adapter: None,
             timer: None,
+            stats: None,
+            used_backend: None,
+            gpu_error: None,
+            compounds: Vec::new(),
         }
     }
 
     /// Run/Execute the calculation using CPU.
     /// Parameters:
-    /// - num_threads: Total number of threads to spawn for parallel computing.
-    async fn run_cpu(&mut self, num_threads: usize) -> anyhow::Result<Vec<NodePositionData>> {
-        let builder = ThreadPoolBuilder::new().num_threads(num_threads).build()?;
-        builder.install(|| -> anyhow::Result<Vec<NodePositionData>> {
-            let mut layout = CpuConcentric::new(&self.nodes, &self.edges, &self.cx, &self.cy);
-            let result = layout.get()?;
-            self.timer = Some(layout.timer);
-            Ok(result)
-        })
+    /// - num_threads: Total number of threads to spawn for parallel computing. Ignored when `thread_pool` is set.
+    #[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+    fn run_cpu(&mut self, num_threads: usize) -> anyhow::Result<Vec<NodePositionData>> {
+        let thread_pool = match &self.thread_pool {
+            Some(thread_pool) => thread_pool.clone(),
+            None => Arc::new(ThreadPoolBuilder::new().num_threads(num_threads).build()?),
+        };
+        // The pool threads do not inherit the subscriber and the current span of the caller
+        #[cfg(feature = "tracing")]
+        let (dispatch, span) = (
+            tracing::dispatcher::get_default(|dispatch| dispatch.clone()),
+            tracing::Span::current(),
+        );
+        let result = thread_pool.install(|| {
+            #[cfg(feature = "tracing")]
+            let _guards = (tracing::dispatcher::set_default(&dispatch), span.enter());
+            self.run_cpu_layout()
+        })?;
+        self.used_backend = Some(ComputingConfig::Cpu(thread_pool.current_num_threads()));
+        Ok(result)
+    }
+
+    /// Run/Execute the calculation using CPU on the current thread.
+    /// Without the `parallel` feature or on wasm32, where threads cannot be spawned, the layout runs sequentially.
+    #[cfg(any(not(feature = "parallel"), target_arch = "wasm32"))]
+    fn run_cpu(&mut self, _num_threads: usize) -> anyhow::Result<Vec<NodePositionData>> {
+        let result = self.run_cpu_layout()?;
+        self.used_backend = Some(ComputingConfig::Cpu(1));
+        Ok(result)
+    }
+
+    fn run_cpu_layout(&mut self) -> anyhow::Result<Vec<NodePositionData>> {
+        let mut layout = CpuConcentric::new(self.nodes, self.edges, &self.options);
+        let result = layout.get()?;
+        self.timer = Some(layout.timer);
+        self.stats = Some(layout.stats);
+        Ok(result)
     }
 
     /// Run/Execute the calculation using GPU. Number of threads is determine automatically based on the number of nodes
     /// multiple by default workgroup size 64. See gpu/wgsl to adjust the configured workgroup_size.
+    #[cfg(feature = "gpu")]
     async fn run_gpu(&mut self) -> anyhow::Result<Vec<NodePositionData>> {
-        let mut layout = GpuConcentric::new(&self.nodes, &self.edges, &self.cx, &self.cy);
+        let mut layout = GpuConcentric::new(self.nodes, self.edges, &self.options);
+        layout.adapter = self.gpu_adapter.clone();
         let result = layout.get().await?;
         self.timer = Some(layout.timer);
+        self.stats = Some(layout.stats);
+        self.used_backend = Some(ComputingConfig::Gpu);
         Ok(result)
     }
 
+    /// Run the gpu computation for `Auto` when the graph is large enough and an adapter is available.
+    /// Returns None when the cpu has to be used, the gpu error is kept in `gpu_error`.
+    #[cfg(feature = "gpu")]
+    async fn try_run_gpu(&mut self) -> Option<Vec<NodePositionData>> {
+        if self.nodes.len() < self.options.gpu_min_nodes {
+            event!(
+                debug,
+                gpu_min_nodes = self.options.gpu_min_nodes,
+                "auto backend: graph below the gpu threshold"
+            );
+            return None;
+        }
+        if self.gpu_adapter.is_none() {
+            let adapter = GpuAdapter::with_options(&self.options.gpu).await;
+            #[cfg(feature = "tracing")]
+            if let Err(error) = &adapter {
+                tracing::debug!(%error, "auto backend: no gpu adapter available");
+            }
+            self.gpu_adapter = adapter.ok();
+        }
+        self.gpu_adapter.as_ref()?;
+        match self.run_gpu().await {
+            Ok(result) => Some(result),
+            Err(error) => {
+                event!(warn, %error, "auto backend: gpu computation failed, falling back to the cpu");
+                self.gpu_error = Some(error.to_string());
+                None
+            }
+        }
+    }
+
+    /// Run/Execute the calculation on the backend selected by `ComputingConfig::Auto`.
+    async fn run_auto(&mut self) -> anyhow::Result<Vec<NodePositionData>> {
+        self.gpu_error = None;
+        #[cfg(feature = "gpu")]
+        if let Some(result) = self.try_run_gpu().await {
+            return Ok(result);
+        }
+        self.run_cpu(0)
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        if self.options.validation == ValidationPolicy::Skip {
+            event!(debug, "validation skipped");
+            return Ok(());
+        }
+        let result = Validation::validate(self.nodes, self.edges);
+        #[cfg(feature = "tracing")]
+        match &result {
+            Ok(()) => tracing::debug!("validation passed"),
+            Err(error) => tracing::warn!(%error, "validation failed"),
+        }
+        Ok(result?)
+    }
+
+    /// Report the outcome of a computation as a `tracing` event
+    #[cfg(feature = "tracing")]
+    fn trace_result(&self, result: &anyhow::Result<Vec<NodePositionData>>) {
+        match result {
+            Ok(_) => tracing::info!(
+                used_backend = ?self.used_backend,
+                total_micros = self.stats.as_ref().map(|stats| stats.total_micros),
+                "layout computed"
+            ),
+            Err(error) => tracing::error!(%error, "layout failed"),
+        }
+    }
+
     /// The function to call to execute cpu or gpu computation.
+    /// The nodes and edges are validated first unless `options.validation` is `Skip`, see `Validation`.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "concentric_layout.execute",
+            skip_all,
+            fields(nodes = self.nodes.len(), edges = self.edges.len(), backend = ?self.options.backend)
+        )
+    )]
     pub async fn execute(&mut self) -> anyhow::Result<Vec<NodePositionData>> {
-        match self.config {
-            ComputingConfig::Cpu(num_threads) => self.run_cpu(num_threads).await,
+        self.validate()?;
+        let result = match self.parents.is_empty() {
+            true => self.run().await,
+            false => self.run_compound().await,
+        };
+        #[cfg(feature = "tracing")]
+        self.trace_result(&result);
+        result
+    }
+
+    async fn run(&mut self) -> anyhow::Result<Vec<NodePositionData>> {
+        match self.options.backend {
+            ComputingConfig::Cpu(num_threads) => self.run_cpu(num_threads),
+            #[cfg(feature = "gpu")]
             ComputingConfig::Gpu => self.run_gpu().await,
+            ComputingConfig::Auto => self.run_auto().await,
+        }
+    }
+
+    /// Layout of each container of the compound graph, from the deepest parents to the top level
+    async fn run_compound(&mut self) -> anyhow::Result<Vec<NodePositionData>> {
+        let timer = Instant::now();
+        let mut compound = Compound::new(self.nodes, self.edges, self.parents, &self.options)?;
+        // A single adapter for all the containers
+        #[cfg(feature = "gpu")]
+        if self.options.backend == ComputingConfig::Gpu && self.gpu_adapter.is_none() {
+            self.gpu_adapter = Some(GpuAdapter::with_options(&self.options.gpu).await?);
+        }
+        let mut stats = None;
+        for index in 0..compound.containers.len() {
+            let mut layout = self.get_container_layout(&compound, &compound.containers[index]);
+            let result = layout.run().await;
+            let positions = self.add_container_result(layout, &mut stats, result)?;
+            compound.set_positions(index, positions);
         }
+        self.set_compound_result(compound, stats, timer)
+    }
+
+    /// Blocking version of `execute`, no async runtime is needed.
+    /// The gpu computation is driven on the current thread until it is done.
+    /// Blocking on the gpu is not possible on wasm32, use `execute` instead.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "concentric_layout.execute",
+            skip_all,
+            fields(nodes = self.nodes.len(), edges = self.edges.len(), backend = ?self.options.backend)
+        )
+    )]
+    pub fn execute_blocking(&mut self) -> anyhow::Result<Vec<NodePositionData>> {
+        self.validate()?;
+        let result = match self.parents.is_empty() {
+            true => self.run_blocking(),
+            false => self.run_compound_blocking(),
+        };
+        #[cfg(feature = "tracing")]
+        self.trace_result(&result);
+        result
+    }
+
+    fn run_blocking(&mut self) -> anyhow::Result<Vec<NodePositionData>> {
+        match self.options.backend {
+            ComputingConfig::Cpu(num_threads) => self.run_cpu(num_threads),
+            #[cfg(all(feature = "gpu", not(target_arch = "wasm32")))]
+            ComputingConfig::Gpu => pollster::block_on(self.run_gpu()),
+            #[cfg(all(feature = "gpu", target_arch = "wasm32"))]
+            ComputingConfig::Gpu => Err(anyhow::anyhow!(
+                "blocking gpu computation is not supported on wasm32"
+            )),
+            #[cfg(all(feature = "gpu", not(target_arch = "wasm32")))]
+            ComputingConfig::Auto => pollster::block_on(self.run_auto()),
+            #[cfg(any(not(feature = "gpu"), target_arch = "wasm32"))]
+            ComputingConfig::Auto => self.run_cpu(0),
+        }
+    }
+
+    /// Blocking version of `run_compound`
+    fn run_compound_blocking(&mut self) -> anyhow::Result<Vec<NodePositionData>> {
+        let timer = Instant::now();
+        let mut compound = Compound::new(self.nodes, self.edges, self.parents, &self.options)?;
+        #[cfg(all(feature = "gpu", not(target_arch = "wasm32")))]
+        if self.options.backend == ComputingConfig::Gpu && self.gpu_adapter.is_none() {
+            self.gpu_adapter = Some(pollster::block_on(GpuAdapter::with_options(
+                &self.options.gpu,
+            ))?);
+        }
+        let mut stats = None;
+        for index in 0..compound.containers.len() {
+            let mut layout = self.get_container_layout(&compound, &compound.containers[index]);
+            let result = layout.run_blocking();
+            let positions = self.add_container_result(layout, &mut stats, result)?;
+            compound.set_positions(index, positions);
+        }
+        self.set_compound_result(compound, stats, timer)
+    }
+
+    /// Layout of the children of a parent, sharing the thread pool and the gpu adapter
+    fn get_container_layout<'b>(
+        &self,
+        compound: &Compound,
+        container: &'b Container,
+    ) -> ConcentricLayout<'b> {
+        let options = compound.get_options(container);
+        ConcentricLayout {
+            #[cfg(feature = "parallel")]
+            thread_pool: self.thread_pool.clone(),
+            #[cfg(feature = "gpu")]
+            gpu_adapter: self.gpu_adapter.clone(),
+            ..ConcentricLayout::new(&container.nodes, &container.edges, options)
+        }
+    }
+
+    /// Keep the backend, the gpu adapter and the stage timings of a container layout
+    fn add_container_result(
+        &mut self,
+        layout: ConcentricLayout,
+        stats: &mut Option<LayoutStats>,
+        result: anyhow::Result<Vec<NodePositionData>>,
+    ) -> anyhow::Result<Vec<NodePositionData>> {
+        let positions = result?;
+        #[cfg(feature = "gpu")]
+        if self.gpu_adapter.is_none() {
+            self.gpu_adapter = layout.gpu_adapter;
+        }
+        self.used_backend = layout.used_backend;
+        if layout.gpu_error.is_some() {
+            self.gpu_error = layout.gpu_error;
+        }
+        if let Some(layout_stats) = layout.stats {
+            *stats = Some(match stats.take() {
+                Some(stats) => LayoutStats {
+                    backend: layout_stats.backend,
+                    threads: layout_stats.threads,
+                    gpu_timestamps: layout_stats.gpu_timestamps,
+                    degree: stats.degree.add(&layout_stats.degree),
+                    normalize: stats.normalize.add(&layout_stats.normalize),
+                    sort: stats.sort.add(&layout_stats.sort),
+                    positions: stats.positions.add(&layout_stats.positions),
+                    ..stats
+                },
+                None => layout_stats,
+            });
+        }
+        Ok(positions)
+    }
+
+    /// Final positions and parent discs, the stats sum the stages of the container layouts
+    fn set_compound_result(
+        &mut self,
+        compound: Compound,
+        stats: Option<LayoutStats>,
+        timer: Instant,
+    ) -> anyhow::Result<Vec<NodePositionData>> {
+        let CompoundResult {
+            positions,
+            compounds,
+            bounding_box,
+            fit_scale,
+        } = compound.get_result()?;
+        let elapsed = timer.elapsed();
+        self.compounds = compounds;
+        self.stats = Some(LayoutStats {
+            nodes: self.nodes.len(),
+            edges: self.edges.len(),
+            rings: LayoutStats::count_rings(&positions),
+            total_micros: elapsed.as_micros() as u64,
+            bounding_box,
+            fit_scale,
+            ..stats.unwrap_or_default()
+        });
+        self.timer = Some(Timer {
+            micros: Some(elapsed.as_micros()),
+            millis: Some(elapsed.as_millis()),
+            seconds: Some(elapsed.as_secs()),
+        });
+        Ok(positions)
+    }
+}
+
+#[cfg(test)]
+pub mod test_concentric_layout_blocking {
+    use super::*;
+    use crate::RingOrder;
+
+    fn get_sample_graph() -> (Vec<Node>, Vec<Edge>) {
+        let nodes = (1..=10).map(|id| Node { id }).collect();
+        let edges = (2..=10)
+            .map(|id| Edge {
+                id,
+                source_id: 1,
+                target_id: id,
+            })
+            .collect();
+        (nodes, edges)
+    }
+
+    #[test]
+    fn test_execute_blocking_cpu() {
+        let (nodes, edges) = get_sample_graph();
+        let options = LayoutOptions::new().backend(ComputingConfig::Cpu(2));
+        let mut layout = ConcentricLayout::new(&nodes, &edges, options);
+        let positions = layout.execute_blocking().unwrap();
+        assert_eq!(positions.len(), nodes.len());
+        assert_eq!(positions[0].node_id, 1);
+        assert!(layout.timer.is_some());
+        // Without the `parallel` feature the cpu backend runs on the current thread
+        #[cfg(feature = "parallel")]
+        let threads = 2;
+        #[cfg(not(feature = "parallel"))]
+        let threads = 1;
+        assert_eq!(layout.used_backend, Some(ComputingConfig::Cpu(threads)));
+        let stats = layout.stats.unwrap();
+        assert_eq!(stats.backend, ComputingConfig::Cpu(threads));
+        assert_eq!(stats.threads, threads);
+        assert_eq!((stats.nodes, stats.edges, stats.rings), (10, 9, 3));
+        assert_eq!(stats.degree.dispatch_micros, None);
+    }
+
+    #[test]
+    fn test_execute_blocking_auto() {
+        let (nodes, edges) = get_sample_graph();
+        // Below the threshold: cpu
+        let options = LayoutOptions::new().backend(ComputingConfig::Auto);
+        let mut layout = ConcentricLayout::new(&nodes, &edges, options);
+        let expected = layout.execute_blocking().unwrap();
+        assert_eq!(expected.len(), nodes.len());
+        assert!(matches!(layout.used_backend, Some(ComputingConfig::Cpu(_))));
+
+        #[cfg(feature = "gpu")]
+        {
+            let options = LayoutOptions::new()
+                .backend(ComputingConfig::Auto)
+                .gpu_min_nodes(1);
+            let mut layout = ConcentricLayout::new(&nodes, &edges, options);
+            let positions = layout.execute_blocking().unwrap();
+            assert_eq!(layout.used_backend, Some(ComputingConfig::Gpu));
+            assert_eq!(positions.len(), expected.len());
+            assert_eq!(positions[0].node_id, expected[0].node_id);
+        }
+    }
+
+    #[cfg(feature = "gpu")]
+    #[test]
+    fn test_execute_blocking_auto_fallback() {
+        use crate::gpu::GpuAdapter;
+        use wgpu::Limits;
+
+        let nodes: Vec<Node> = (1..=250).map(|id| Node { id }).collect();
+        let options = LayoutOptions::new()
+            .backend(ComputingConfig::Auto)
+            .gpu_min_nodes(1);
+        let mut layout = ConcentricLayout::new(&nodes, &[], options);
+        // The nodes do not fit a storage buffer of the adapter
+        let adapter = pollster::block_on(GpuAdapter::with_limits(Some(Limits {
+            max_storage_buffer_binding_size: 512,
+            ..Limits::default()
+        })));
+        layout.gpu_adapter = adapter.ok();
+        let positions = layout.execute_blocking().unwrap();
+        assert_eq!(positions.len(), nodes.len());
+        assert!(matches!(layout.used_backend, Some(ComputingConfig::Cpu(_))));
+        assert!(layout.gpu_error.is_some());
+    }
+
+    #[test]
+    fn test_execute_blocking_options() {
+        let (nodes, edges) = get_sample_graph();
+        let options = LayoutOptions::new()
+            .center(100.0, 50.0)
+            .order(RingOrder::DegreeAscending);
+        let mut layout = ConcentricLayout::new(&nodes, &edges, options);
+        let positions = layout.execute_blocking().unwrap();
+        assert_ne!(positions[0].node_id, 1);
+        assert_eq!((positions[0].x, positions[0].y), (100.0, 50.0));
+
+        let options = LayoutOptions::new().validation(ValidationPolicy::Skip);
+        let mut layout = ConcentricLayout::new(&nodes, &[], options);
+        assert!(layout.execute_blocking().is_ok());
+        let mut layout = ConcentricLayout::new(&[], &edges, LayoutOptions::new());
+        assert!(layout.execute_blocking().is_err());
+    }
+
+    #[cfg(feature = "gpu")]
+    #[test]
+    fn test_execute_blocking_gpu() {
+        let (nodes, edges) = get_sample_graph();
+        let options = LayoutOptions::new().backend(ComputingConfig::Gpu);
+        let mut layout = ConcentricLayout::new(&nodes, &edges, options.clone());
+        let positions = layout.execute_blocking().unwrap();
+        assert_eq!(positions.len(), nodes.len());
+        assert_eq!(positions[0].node_id, 1);
+        let stats = layout.stats.unwrap();
+        assert_eq!(stats.backend, ComputingConfig::Gpu);
+        assert_eq!((stats.nodes, stats.edges, stats.rings), (10, 9, 3));
+        for stage in [stats.degree, stats.normalize, stats.sort, stats.positions] {
+            assert!(stage.dispatch_micros.is_some());
+            assert!(stage.micros >= stage.upload_micros.unwrap_or(0));
+        }
+        assert!(stats.degree.readback_micros.is_some());
+        assert!(stats.total_micros >= stats.degree.micros + stats.positions.micros);
+
+        // No edges: every node has the same degree
+        let mut layout = ConcentricLayout::new(&nodes, &[], options);
+        let positions = layout.execute_blocking().unwrap();
+        assert_eq!(positions.len(), nodes.len());
     }
 }
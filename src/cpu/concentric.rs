@@ -1,39 +1,77 @@
-use crate::Timer;
 use crate::cpu::{NodeConnections, NodePositions, Normalize};
-use crate::entities::{Edge, Node, NodeConnectionsData, NodePositionData, NormalizeData};
-use serde::{Deserialize, Serialize};
-use std::time::Instant;
+use crate::entities::{
+    Edge, Node, NodeConnectionsData, NodePositionData, NormalizeData, NormalizeValue,
+};
+use crate::parallel::prelude::current_num_threads;
+use crate::{
+    BoundingBox, ComputingConfig, LayoutOptions, LayoutStats, Levels, Pinning, StageTiming, Timer,
+    Viewport,
+};
+#[cfg(feature = "serde")]
+use serde::Serialize;
+use web_time::Instant;
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
-pub struct CpuConcentric {
+/// CPU computing of the layout. The nodes and edges are borrowed, not copied.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct CpuConcentric<'a> {
     pub timer: Timer,
-    pub nodes: Vec<Node>,
-    pub edges: Vec<Edge>,
+    pub stats: LayoutStats,
+    pub nodes: &'a [Node],
+    pub edges: &'a [Edge],
     pub node_connections: NodeConnectionsData,
     pub normalized_values: NormalizeData,
     pub node_positions: Vec<NodePositionData>,
-    pub default_cx: Option<f32>,
-    pub default_cy: Option<f32>,
+    pub options: LayoutOptions,
 }
 
-impl CpuConcentric {
-    pub fn new(nodes: &Vec<Node>, edges: &Vec<Edge>, cx: &Option<f32>, cy: &Option<f32>) -> Self {
+impl<'a> CpuConcentric<'a> {
+    pub fn new(nodes: &'a [Node], edges: &'a [Edge], options: &LayoutOptions) -> Self {
         Self {
-            nodes: nodes.to_owned(),
-            edges: edges.to_owned(),
-            default_cx: cx.to_owned(),
-            default_cy: cy.to_owned(),
+            nodes,
+            edges,
+            options: options.clone(),
             ..Default::default()
         }
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "cpu.layout",
+            skip_all,
+            fields(nodes = self.nodes.len(), edges = self.edges.len(), threads = current_num_threads())
+        )
+    )]
     pub fn get(&mut self) -> anyhow::Result<Vec<NodePositionData>> {
         let timer = Instant::now();
+        self.options.validate()?;
+        Levels::validate(&self.options.levels, self.nodes)?;
+        Pinning::validate(&self.options.pinned, self.nodes)?;
+        let threads = current_num_threads();
+        self.stats = LayoutStats {
+            backend: ComputingConfig::Cpu(threads),
+            threads,
+            nodes: self.nodes.len(),
+            edges: self.edges.len(),
+            ..Default::default()
+        };
         self.count_node_connections()?;
         self.normalize_node_connections()?;
         self.calculate_node_positions()?;
+        self.stats.fit_scale = Viewport::get_scale(&self.node_positions, &self.options);
+        Viewport::fit(&mut self.node_positions, &self.options)?;
+        // The pinned nodes keep their coordinates, they are not moved into the bounding box
+        Pinning::apply(
+            &mut self.node_positions,
+            &self.options,
+            self.stats.fit_scale.unwrap_or(1.0),
+        )?;
+        self.stats.bounding_box = BoundingBox::from_positions(&self.node_positions);
         let elapsed = timer.elapsed();
         let data = self.node_positions.clone();
+        self.stats.rings = LayoutStats::count_rings(&data);
+        self.stats.total_micros = elapsed.as_micros() as u64;
         self.timer = Timer {
             micros: Some(elapsed.as_micros()),
             millis: Some(elapsed.as_millis()),
@@ -43,23 +81,55 @@ impl CpuConcentric {
     }
 
     /// 1. Count the number of edges/paths per node
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "cpu.degree", skip_all)
+    )]
     fn count_node_connections(&mut self) -> anyhow::Result<()> {
-        let result = NodeConnections::get(&self.nodes, &self.edges)?;
-        self.node_connections = result.clone();
+        let timer = Instant::now();
+        let result = NodeConnections::get(self.nodes, self.edges)?;
+        self.node_connections = result;
+        self.stats.degree = StageTiming::new(timer.elapsed());
         Ok(())
     }
 
-    /// 2. Normalize Node Connections
+    /// 2. Normalize Node Connections, ordered from the centre to the outer rings
     fn normalize_node_connections(&mut self) -> anyhow::Result<()> {
-        let result = Normalize::get(&self.node_connections)?;
-        self.normalized_values = result.clone();
+        let values = self.get_normalized_values();
+        self.sort_normalized_values(values)
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "cpu.normalize", skip_all)
+    )]
+    fn get_normalized_values(&mut self) -> Vec<NormalizeValue> {
+        let timer = Instant::now();
+        let values = Normalize::get_values(&self.node_connections);
+        self.stats.normalize = StageTiming::new(timer.elapsed());
+        values
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "cpu.sort", skip_all))]
+    fn sort_normalized_values(&mut self, values: Vec<NormalizeValue>) -> anyhow::Result<()> {
+        let timer = Instant::now();
+        let mut result = Normalize::sort(values)?;
+        self.options.order.apply(&mut result.values);
+        Levels::arrange(&mut result.values, &self.options)?;
+        self.normalized_values = result;
+        self.stats.sort = StageTiming::new(timer.elapsed());
         Ok(())
     }
 
     /// 3. Node Posititons
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "cpu.positions", skip_all)
+    )]
     fn calculate_node_positions(&mut self) -> anyhow::Result<()> {
-        self.node_positions =
-            NodePositions::get(&self.normalized_values, self.default_cx, self.default_cy);
+        let timer = Instant::now();
+        self.node_positions = NodePositions::get(&self.normalized_values, &self.options)?;
+        self.stats.positions = StageTiming::new(timer.elapsed());
         Ok(())
     }
 }
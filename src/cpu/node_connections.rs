@@ -1,15 +1,16 @@
 use crate::entities::{Edge, Node, NodeConnectionValue, NodeConnectionsData};
-use rayon::iter::IntoParallelRefIterator;
-use rayon::iter::ParallelIterator;
+use crate::parallel::prelude::*;
+#[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct NodeConnections {}
 
 impl NodeConnections {
     /// Get the connection count per node.
     /// Highest count will be the central node
-    pub fn get(nodes: &Vec<Node>, edges: &Vec<Edge>) -> anyhow::Result<NodeConnectionsData> {
+    pub fn get(nodes: &[Node], edges: &[Edge]) -> anyhow::Result<NodeConnectionsData> {
         let values: Vec<NodeConnectionValue> = nodes
             .par_iter()
             .map(|node| {
@@ -18,7 +19,7 @@ impl NodeConnections {
                     .filter(|item| item.source_id == node.id || item.target_id == node.id)
                     .count() as u32;
                 NodeConnectionValue {
-                    node_id: node.id.clone(),
+                    node_id: node.id,
                     total,
                 }
             })
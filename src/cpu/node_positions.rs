@@ -1,82 +1,330 @@
-use std::f32::consts::PI;
-
-use rayon::{
-    iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator},
-    slice::ParallelSliceMut,
-};
-
 use crate::entities::{NodePositionData, NormalizeData, RingCapacity};
+use crate::parallel::prelude::*;
+use crate::{LayoutMode, LayoutOptions, Levels};
 
 #[derive(Debug)]
 pub struct NodePositions {}
 
 impl NodePositions {
+    /// Place the nodes on the rings following the normalized (sorted) order.
+    /// The centre, start angle, ring stagger, shape and ring geometry come from the options, see `LayoutOptions`.
+    /// Off circles, the angles give the arc length along the perimeter and the node angle is seen from the centre.
     pub fn get(
         normalize_data: &NormalizeData,
-        cx: Option<f32>,
-        cy: Option<f32>,
-    ) -> Vec<NodePositionData> {
+        options: &LayoutOptions,
+    ) -> anyhow::Result<Vec<NodePositionData>> {
+        match options.mode {
+            LayoutMode::Rings => {}
+            LayoutMode::Shells => return Self::get_shells(normalize_data, options),
+            LayoutMode::Spiral => return Ok(Self::get_spiral(normalize_data, options)),
+        }
         let ring_capacity: Vec<RingCapacity> =
-            RingCapacity::generate(normalize_data.values.len() as u32, Some(20));
-        let cx = cx.unwrap_or(0.0);
-        let cy = cy.unwrap_or(0.0);
-        let mut result = ring_capacity
+            Levels::get_ring_capacity(&normalize_data.values, options)?;
+        let geometry = options.get_ring_geometry();
+        let (cx, cy, cz) = (options.cx, options.cy, options.cz);
+        let ring_offsets = options.stagger.get_offsets(&ring_capacity);
+        let mut result: Vec<NodePositionData> = ring_capacity
             .par_iter()
-            .fold(
-                || {
-                    let result: Vec<NodePositionData> = Vec::new();
-                    result
-                },
-                |mut result, capacity| {
-                    let start = capacity.range[0] as usize;
-                    let end = capacity.range[1] as usize;
-                    let nodes: Vec<u32> = normalize_data
-                        .values
-                        .get(start..end)
-                        .unwrap_or_default()
-                        .par_iter()
-                        .map(|item| item.node_id.to_owned())
-                        .collect();
+            .zip(ring_offsets.par_iter())
+            .flat_map(|(capacity, ring_offset)| {
+                let start_angle = options.start_angle + ring_offset;
+                let start = capacity.range[0] as usize;
+                let end = capacity.range[1] as usize;
+                let nodes: Vec<u32> = normalize_data
+                    .values
+                    .get(start..end)
+                    .unwrap_or_default()
+                    .par_iter()
+                    .map(|item| item.node_id.to_owned())
+                    .collect();
 
-                    let total_nodes = nodes.len();
-                    let step_angle = 360_f32 / total_nodes as f32;
-                    let mut items: Vec<NodePositionData> = nodes
-                        .par_iter()
-                        .enumerate()
-                        .map(|(index, node_id)| {
-                            let angle_degree = index as f32 * step_angle;
-                            let angle_radian = angle_degree * (PI / 180_f32);
-                            let ring_radius = capacity.radius;
-                            let x = cx + ring_radius as f32 * angle_radian.cos();
-                            let y = cy + ring_radius as f32 * angle_radian.sin();
-                            NodePositionData {
-                                index: capacity.index,
-                                angle_degree,
-                                angle_radian,
-                                cx,
-                                cy,
-                                x,
-                                y,
-                                node_id: node_id.to_owned(),
-                                radius: ring_radius,
-                            }
-                        })
-                        .collect();
-                    result.append(&mut items);
-                    result
-                },
-            )
-            .reduce(
-                || {
-                    let result: Vec<NodePositionData> = Vec::new();
-                    result
-                },
-                |mut result, mut values| {
-                    result.append(&mut values);
-                    result
-                },
-            );
+                let total_nodes = nodes.len();
+                let step_angle = 360_f32 / total_nodes as f32;
+                nodes
+                    .par_iter()
+                    .enumerate()
+                    .map(|(index, node_id)| {
+                        let ring_radius = capacity.radius;
+                        let (x, y, angle_degree) = geometry.get_position(
+                            ring_radius as f32,
+                            (start_angle + index as f32 * step_angle).rem_euclid(360_f32),
+                        );
+                        let angle_radian = angle_degree.to_radians();
+                        let x = cx + x;
+                        let y = cy + y;
+                        NodePositionData {
+                            index: capacity.index,
+                            angle_degree,
+                            angle_radian,
+                            cx,
+                            cy,
+                            x,
+                            y,
+                            node_id: node_id.to_owned(),
+                            radius: ring_radius,
+                            cz,
+                            z: cz,
+                            polar_degree: 90.0,
+                            azimuth_degree: angle_degree,
+                            pinned: false,
+                        }
+                    })
+                    .collect::<Vec<NodePositionData>>()
+            })
+            .collect();
+        result.par_sort_by(|a, b| a.index.cmp(&b.index));
+        Ok(result)
+    }
+    /// Place the nodes along a Fermat spiral following the normalized (sorted) order, see `LayoutMode::Spiral`.
+    pub fn get_spiral(
+        normalize_data: &NormalizeData,
+        options: &LayoutOptions,
+    ) -> Vec<NodePositionData> {
+        let (cx, cy, cz) = (options.cx, options.cy, options.cz);
+        let indexes: Vec<u32> = (0..normalize_data.values.len() as u32).collect();
+        indexes
+            .par_iter()
+            .map(|index| {
+                let radius = options.min_node_spacing * (*index as f32).sqrt();
+                let angle_degree = (options.start_angle + LayoutMode::get_spiral_angle(*index))
+                    .rem_euclid(360_f32);
+                let angle_radian = angle_degree.to_radians();
+                NodePositionData {
+                    index: 0,
+                    angle_degree,
+                    angle_radian,
+                    cx,
+                    cy,
+                    x: cx + radius * angle_radian.cos(),
+                    y: cy + radius * angle_radian.sin(),
+                    node_id: normalize_data.values[*index as usize].node_id,
+                    radius: radius.round() as u32,
+                    cz,
+                    z: cz,
+                    polar_degree: 90.0,
+                    azimuth_degree: angle_degree,
+                    pinned: false,
+                }
+            })
+            .collect()
+    }
+    /// Place the nodes on spherical shells following the normalized (sorted) order, see `LayoutMode::Shells`.
+    /// The node `j` of a shell of `n` nodes is at the height `1 - 2 (j + 0.5) / n` of the unit sphere
+    /// and turned by `j` golden angles from the start angle (Fibonacci sphere).
+    pub fn get_shells(
+        normalize_data: &NormalizeData,
+        options: &LayoutOptions,
+    ) -> anyhow::Result<Vec<NodePositionData>> {
+        let ring_capacity = Levels::get_ring_capacity(&normalize_data.values, options)?;
+        let (cx, cy, cz) = (options.cx, options.cy, options.cz);
+        let mut result: Vec<NodePositionData> = ring_capacity
+            .par_iter()
+            .flat_map(|capacity| {
+                let nodes = normalize_data
+                    .values
+                    .get(capacity.range[0] as usize..capacity.range[1] as usize)
+                    .unwrap_or_default();
+                let total_nodes = nodes.len() as f32;
+                let radius = capacity.radius as f32;
+                nodes
+                    .par_iter()
+                    .enumerate()
+                    .map(|(index, item)| {
+                        let height = 1_f32 - 2_f32 * (index as f32 + 0.5) / total_nodes;
+                        let polar_radian = height.clamp(-1.0, 1.0).acos();
+                        let azimuth_degree = (options.start_angle
+                            + LayoutMode::get_spiral_angle(index as u32))
+                        .rem_euclid(360_f32);
+                        let azimuth_radian = azimuth_degree.to_radians();
+                        let distance = radius * polar_radian.sin();
+                        NodePositionData {
+                            index: capacity.index,
+                            radius: capacity.radius,
+                            angle_degree: azimuth_degree,
+                            angle_radian: azimuth_radian,
+                            cx,
+                            cy,
+                            x: cx + distance * azimuth_radian.cos(),
+                            y: cy + distance * azimuth_radian.sin(),
+                            node_id: item.node_id,
+                            cz,
+                            z: cz + radius * height,
+                            polar_degree: polar_radian.to_degrees(),
+                            azimuth_degree,
+                            pinned: false,
+                        }
+                    })
+                    .collect::<Vec<NodePositionData>>()
+            })
+            .collect();
         result.par_sort_by(|a, b| a.index.cmp(&b.index));
-        result
+        Ok(result)
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+pub mod test_cpu_node_positions {
+    use super::*;
+    use crate::generators::GraphKind;
+    use crate::{ComputingConfig, ConcentricLayout};
+
+    #[test]
+    fn test_shells() {
+        let graph = GraphKind::Telco {
+            nodes: 2_000,
+            fan_out: 4,
+        }
+        .generate(0);
+        let options = LayoutOptions::new()
+            .backend(ComputingConfig::Cpu(2))
+            .mode(LayoutMode::Shells)
+            .center(10.0, 20.0)
+            .cz(30.0);
+        let mut layout = ConcentricLayout::new(&graph.nodes, &graph.edges, options.clone());
+        let positions = layout.execute_blocking().unwrap();
+        assert_eq!(positions.len(), graph.nodes.len());
+        assert_eq!(
+            (positions[0].x, positions[0].y, positions[0].z),
+            (10.0, 20.0, 30.0)
+        );
+        // The surface grows faster than the perimeter: fewer shells than rings
+        let rings = {
+            let options = options.clone().mode(LayoutMode::Rings);
+            let mut layout = ConcentricLayout::new(&graph.nodes, &graph.edges, options);
+            layout.execute_blocking().unwrap()
+        };
+        assert!(positions.last().unwrap().index < rings.last().unwrap().index);
+        assert!(
+            rings
+                .iter()
+                .all(|item| item.z == 30.0 && item.polar_degree == 90.0)
+        );
+        for item in positions.iter() {
+            let (x, y, z) = (item.x - item.cx, item.y - item.cy, item.z - item.cz);
+            let distance = (x * x + y * y + z * z).sqrt();
+            assert!((distance - item.radius as f32).abs() < 0.01, "{:?}", item);
+            let polar = (z / distance.max(f32::EPSILON)).clamp(-1.0, 1.0).acos();
+            if item.radius > 0 {
+                assert!((polar.to_degrees() - item.polar_degree).abs() < 0.01);
+            }
+            assert_eq!(item.azimuth_degree, item.angle_degree);
+        }
+        // The nodes of a shell are not closer than the spacing
+        for shell in 1..=positions.last().unwrap().index {
+            let nodes: Vec<&NodePositionData> = positions
+                .iter()
+                .filter(|item| item.index == shell)
+                .collect();
+            for (index, item) in nodes.iter().enumerate() {
+                for other in nodes[index + 1..].iter() {
+                    let distance = ((item.x - other.x).powi(2)
+                        + (item.y - other.y).powi(2)
+                        + (item.z - other.z).powi(2))
+                    .sqrt();
+                    assert!(distance >= options.min_node_spacing * 0.99, "{}", distance);
+                }
+            }
+        }
+
+        #[cfg(feature = "gpu")]
+        {
+            let by_height = |positions: &[NodePositionData]| {
+                let mut points: Vec<(u32, f32, f32, f32)> = positions
+                    .iter()
+                    .map(|item| (item.index, item.z, item.x, item.y))
+                    .collect();
+                points.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.total_cmp(&b.1)));
+                points
+            };
+            let options = options.backend(ComputingConfig::Gpu);
+            let mut layout = ConcentricLayout::new(&graph.nodes, &graph.edges, options);
+            let gpu_positions = layout.execute_blocking().unwrap();
+            for (point, expected) in by_height(&gpu_positions)
+                .iter()
+                .zip(by_height(&positions).iter())
+            {
+                assert_eq!(point.0, expected.0);
+                assert!(
+                    (point.1 - expected.1).abs() < 0.05,
+                    "{:?} {:?}",
+                    point,
+                    expected
+                );
+                assert!(
+                    (point.2 - expected.2).abs() < 0.05,
+                    "{:?} {:?}",
+                    point,
+                    expected
+                );
+                assert!(
+                    (point.3 - expected.3).abs() < 0.05,
+                    "{:?} {:?}",
+                    point,
+                    expected
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_spiral() {
+        let graph = GraphKind::BarabasiAlbert {
+            nodes: 2_000,
+            edges_per_node: 2,
+        }
+        .generate(0);
+        let options = LayoutOptions::new()
+            .backend(ComputingConfig::Cpu(2))
+            .mode(LayoutMode::Spiral);
+        let mut layout = ConcentricLayout::new(&graph.nodes, &graph.edges, options.clone());
+        let positions = layout.execute_blocking().unwrap();
+        assert_eq!(positions.len(), graph.nodes.len());
+        assert_eq!(layout.stats.as_ref().unwrap().rings, 1);
+        assert_eq!((positions[0].x, positions[0].y), (0.0, 0.0));
+        // Highest degree at the centre, following the sort of the rings mode
+        let rings = {
+            let mut layout = ConcentricLayout::new(
+                &graph.nodes,
+                &graph.edges,
+                options.clone().mode(LayoutMode::Rings),
+            );
+            layout.execute_blocking().unwrap()
+        };
+        assert_eq!(positions[0].node_id, rings[0].node_id);
+        let mut points: Vec<(f32, f32)> = positions.iter().map(|item| (item.x, item.y)).collect();
+        points.sort_by(|a, b| a.0.total_cmp(&b.0));
+        for (index, (x, y)) in points.iter().enumerate() {
+            for (other_x, other_y) in points[index + 1..]
+                .iter()
+                .take_while(|(other_x, _)| other_x - x < options.min_node_spacing)
+            {
+                assert!((other_x - x).hypot(other_y - y) >= options.min_node_spacing * 0.99);
+            }
+        }
+
+        #[cfg(feature = "gpu")]
+        {
+            let by_distance = |positions: &[NodePositionData]| {
+                let mut points: Vec<(f32, f32)> = positions
+                    .iter()
+                    .map(|item| (item.x - item.cx, item.y - item.cy))
+                    .collect();
+                points.sort_by(|a, b| a.0.hypot(a.1).total_cmp(&b.0.hypot(b.1)));
+                points
+            };
+            let mut layout = ConcentricLayout::new(
+                &graph.nodes,
+                &graph.edges,
+                options.backend(ComputingConfig::Gpu),
+            );
+            let gpu_positions = layout.execute_blocking().unwrap();
+            assert_eq!(gpu_positions[0].node_id, positions[0].node_id);
+            for ((x, y), (expected_x, expected_y)) in by_distance(&gpu_positions)
+                .iter()
+                .zip(by_distance(&positions).iter())
+            {
+                assert!((x - expected_x).abs() < 0.1 && (y - expected_y).abs() < 0.1);
+            }
+        }
     }
 }
@@ -1,10 +1,11 @@
 use crate::entities::NodeConnectionsData;
 use crate::entities::{NormalizeData, NormalizeValue};
+use crate::parallel::prelude::*;
 use anyhow::anyhow;
-use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
-use rayon::slice::ParallelSliceMut;
+#[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Normalize {}
 
 impl Normalize {
@@ -12,10 +13,15 @@ impl Normalize {
     /// Formula: normalized_value = (degree - min_degree) / (max_degree - min_degree)
     /// degree - is the number of edges per nodes. Refer to the connections per node count
     pub fn get(node_connections: &NodeConnectionsData) -> anyhow::Result<NormalizeData> {
+        Self::sort(Self::get_values(node_connections))
+    }
+
+    /// Normalized value per node, in the order of the node connections
+    pub fn get_values(node_connections: &NodeConnectionsData) -> Vec<NormalizeValue> {
         let max_degree = node_connections.max_degree;
         let min_degree = node_connections.min_degree;
 
-        let mut values: Vec<NormalizeValue> = node_connections
+        node_connections
             .values
             .par_iter()
             .map(|item| {
@@ -23,7 +29,7 @@ impl Normalize {
                 let normalized_value =
                     (item.total - min_degree) as f32 / (max_degree - min_degree) as f32;
                 NormalizeValue {
-                    node_id: item.node_id.clone(),
+                    node_id: item.node_id,
                     value: if normalized_value.is_nan() {
                         0.0
                     } else {
@@ -31,8 +37,11 @@ impl Normalize {
                     },
                 }
             })
-            .collect::<Vec<NormalizeValue>>();
+            .collect::<Vec<NormalizeValue>>()
+    }
 
+    /// Sort the normalized values by descending value, the highest degree first
+    pub fn sort(mut values: Vec<NormalizeValue>) -> anyhow::Result<NormalizeData> {
         values.par_sort_by(|a, b| {
             b.value
                 .partial_cmp(&a.value)
@@ -0,0 +1,67 @@
+use crate::compound::NodeParent;
+use crate::entities::{Edge, Node, NodePositionData};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// The computed coordinate of a node.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct NodeCoordinate {
+    pub node_id: u32,
+    pub x: f32,
+    pub y: f32,
+}
+
+/// Position of a node keyed by its original id. Can be used as is by the Cytoscape.js `preset` layout.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PresetPosition {
+    pub x: f32,
+    pub y: f32,
+}
+
+/// Graph together with the coordinates of its nodes.
+/// Nodes without coordinate (no position found on the imported file) are not included in `coordinates`.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct GraphLayoutData {
+    pub nodes: Vec<Node>,
+    pub edges: Vec<Edge>,
+    pub coordinates: Vec<NodeCoordinate>,
+    /// Original id of the imported nodes which ids are not numeric and were renumbered.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub source_ids: HashMap<u32, String>,
+    /// Parents of the nodes of a compound graph, see `ConcentricLayout::parents`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub parents: Vec<NodeParent>,
+}
+
+impl GraphLayoutData {
+    /// Get the original id of a node. See `source_ids`.
+    pub fn get_source_id(&self, node_id: u32) -> String {
+        match self.source_ids.get(&node_id) {
+            Some(source_id) => source_id.to_owned(),
+            None => node_id.to_string(),
+        }
+    }
+
+    /// Get the computed positions keyed by the original node id.
+    pub fn get_preset_positions(
+        &self,
+        positions: &[NodePositionData],
+    ) -> HashMap<String, PresetPosition> {
+        positions
+            .iter()
+            .map(|item| {
+                (
+                    self.get_source_id(item.node_id),
+                    PresetPosition {
+                        x: item.x,
+                        y: item.y,
+                    },
+                )
+            })
+            .collect()
+    }
+}
@@ -1,10 +1,12 @@
 pub mod edge;
+pub mod graph;
 pub mod node;
 pub mod node_connections;
 pub mod node_positions;
 pub mod normalize;
 pub mod ring;
 pub use edge::Edge;
+pub use graph::{GraphLayoutData, NodeCoordinate, PresetPosition};
 pub use node::Node;
 pub use node_connections::{NodeConnectionValue, NodeConnectionsData};
 pub use node_positions::NodePositionData;
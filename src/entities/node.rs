@@ -1,7 +1,11 @@
+#[cfg(feature = "gpu")]
 use bytemuck::{Pod, Zeroable};
+#[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, Pod, Zeroable)]
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "gpu", derive(Pod, Zeroable))]
 #[repr(C)]
 pub struct Node {
     pub id: u32,
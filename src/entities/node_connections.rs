@@ -1,13 +1,16 @@
-use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use crate::parallel::prelude::*;
+#[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct NodeConnectionValue {
     pub node_id: u32,
     pub total: u32,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct NodeConnectionsData {
     pub max_degree: u32,
     pub min_degree: u32,
@@ -17,7 +20,7 @@ pub struct NodeConnectionsData {
 impl NodeConnectionsData {
     pub fn compute(values: Vec<NodeConnectionValue>) -> Self {
         let mut values = values;
-        values.sort_by(|a, b| b.total.cmp(&a.total));
+        values.sort_by_key(|item| std::cmp::Reverse(item.total));
         let totals = values
             .par_iter()
             .map(|item| item.total)
@@ -1,8 +1,17 @@
-use bytemuck::{Pod, Zeroable};
+#[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, Pod, Zeroable)]
-#[repr(C)]
+/// Position of a node.
+/// Parameters:
+/// - index, radius: ring (or shell) of the node
+/// - angle_degree, angle_radian: angle around the centre in the x, y plane
+/// - cx, cy, cz: centre of the layout
+/// - x, y, z: coordinates, `z` is `cz` in 2D
+/// - polar_degree: angle from the z axis, 90 in 2D
+/// - azimuth_degree: angle around the z axis, `angle_degree`
+/// - pinned: the node was placed by the caller, see `PinnedNode`
+#[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct NodePositionData {
     pub index: u32,
     pub radius: u32,
@@ -13,4 +22,14 @@ pub struct NodePositionData {
     pub x: f32,
     pub y: f32,
     pub node_id: u32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub cz: f32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub z: f32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub polar_degree: f32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub azimuth_degree: f32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub pinned: bool,
 }
@@ -1,13 +1,18 @@
+#[cfg(feature = "gpu")]
 use bytemuck::{Pod, Zeroable};
+#[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct NormalizeData {
     pub max_value: f32,
     pub values: Vec<NormalizeValue>,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, Pod, Zeroable)]
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "gpu", derive(Pod, Zeroable))]
 #[repr(C)]
 pub struct NormalizeValue {
     pub node_id: u32,
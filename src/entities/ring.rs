@@ -1,7 +1,14 @@
+use crate::shape::RingGeometry;
+#[cfg(feature = "gpu")]
 use bytemuck::{Pod, Zeroable};
 use std::f32::consts::PI;
 
-#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+/// Part of a shell surface covered by the nodes. The Fibonacci sphere keeps the nearest nodes at
+/// about 0.87 times the mean spacing, 0.75 of the area keeps them at least `min_node_spacing` apart.
+pub const SHELL_PACKING: f32 = 0.75;
+
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "gpu", derive(Pod, Zeroable))]
 #[repr(C)]
 pub struct RingCapacity {
     pub index: u32,
@@ -12,41 +19,109 @@ pub struct RingCapacity {
 
 impl RingCapacity {
     pub fn get_radius(radius: u32, ring_index: u32) -> u32 {
-        radius * ring_index
+        radius.saturating_mul(ring_index)
+    }
+
+    /// Radius of the ring `ring_index`, 0 for the centre. Saturates at `u32::MAX`
+    pub fn get_ring_radius(step_radius: u32, ring_index: u32) -> u32 {
+        match ring_index {
+            0 => 0,
+            _ => Self::get_radius(
+                step_radius.saturating_add(ring_index.saturating_add(1).saturating_mul(10)),
+                ring_index,
+            ),
+        }
+    }
+
+    /// A ring without capacity is skipped for the next, larger one. Once the radius stops growing
+    /// (`u32::MAX`) no ring can hold a node: the node spacing is too large.
+    pub fn check_max_nodes(max_nodes: u32, radius: u32) -> anyhow::Result<()> {
+        if max_nodes == 0 && radius == u32::MAX {
+            anyhow::bail!("no ring can hold a node, the node spacing is too large for the rings");
+        }
+        Ok(())
     }
 
-    pub fn get_max_nodes(radius: u32) -> u32 {
+    /// Number of nodes fitting on the ring circumference with at least `min_node_spacing` between them
+    pub fn get_max_nodes(radius: u32, min_node_spacing: f32) -> u32 {
+        (((2_f32 * PI) * radius as f32) / min_node_spacing).floor() as u32
+    }
+
+    pub fn generate(
+        total_nodes: u32,
+        step_radius: Option<u32>,
+    ) -> anyhow::Result<Vec<RingCapacity>> {
         const L_MIN: f32 = 40_f32;
-        (((2_f32 * PI) * radius as f32) / L_MIN).floor() as u32
+        Self::generate_with_spacing(total_nodes, step_radius, L_MIN)
+    }
+
+    pub fn generate_with_spacing(
+        total_nodes: u32,
+        step_radius: Option<u32>,
+        min_node_spacing: f32,
+    ) -> anyhow::Result<Vec<RingCapacity>> {
+        Self::generate_with_geometry(
+            total_nodes,
+            step_radius,
+            min_node_spacing,
+            &RingGeometry::Circle,
+        )
+    }
+
+    /// Number of nodes fitting on the surface of a sphere, `min_node_spacing` squared per node
+    pub fn get_max_shell_nodes(radius: u32, min_node_spacing: f32) -> u32 {
+        let area = 4_f32 * PI * (radius as f32).powi(2);
+        (SHELL_PACKING * area / min_node_spacing.powi(2)).floor() as u32
     }
 
-    pub fn generate(total_nodes: u32, step_radius: Option<u32>) -> Vec<RingCapacity> {
+    /// Rings of the given shape, the capacity follows the perimeter, see `RingGeometry::get_max_nodes`
+    pub fn generate_with_geometry(
+        total_nodes: u32,
+        step_radius: Option<u32>,
+        min_node_spacing: f32,
+        geometry: &RingGeometry,
+    ) -> anyhow::Result<Vec<RingCapacity>> {
+        Self::generate_with_capacity(total_nodes, step_radius, |radius| {
+            geometry.get_max_nodes(radius, min_node_spacing)
+        })
+    }
+
+    /// Spherical shells with the radiuses of the rings, the capacity follows the surface area
+    pub fn generate_shells(
+        total_nodes: u32,
+        step_radius: Option<u32>,
+        min_node_spacing: f32,
+    ) -> anyhow::Result<Vec<RingCapacity>> {
+        Self::generate_with_capacity(total_nodes, step_radius, |radius| {
+            Self::get_max_shell_nodes(radius, min_node_spacing)
+        })
+    }
+
+    /// Rings until the nodes fit, `get_max_nodes` gives the capacity of a ring from its radius.
+    /// The centre holds a single node. Fails when no ring can hold a node, see `check_max_nodes`.
+    fn generate_with_capacity(
+        total_nodes: u32,
+        step_radius: Option<u32>,
+        get_max_nodes: impl Fn(u32) -> u32,
+    ) -> anyhow::Result<Vec<RingCapacity>> {
         let mut total_max_nodes: u32 = 0;
         let mut ring_index: u32 = 0;
         let step_radius = step_radius.unwrap_or(10);
         let mut data: Vec<RingCapacity> = Vec::new();
         loop {
-            if total_nodes < total_max_nodes as u32 {
+            if total_nodes < total_max_nodes {
                 break;
             }
-            let min_radius = if ring_index == 0 {
-                0
-            } else {
-                step_radius + (10 * (ring_index + 1))
-            };
             let start_index = total_max_nodes;
-            let radius = if ring_index == 0 {
-                0
-            } else {
-                Self::get_radius(min_radius, ring_index)
-            };
+            let radius = Self::get_ring_radius(step_radius, ring_index);
             let max_nodes = if ring_index == 0 {
                 1
             } else {
-                Self::get_max_nodes(radius)
+                get_max_nodes(radius)
             };
-            total_max_nodes += max_nodes;
-            let end_index = if total_max_nodes as u32 > total_nodes {
+            Self::check_max_nodes(max_nodes, radius)?;
+            total_max_nodes = total_max_nodes.saturating_add(max_nodes);
+            let end_index = if total_max_nodes > total_nodes {
                 total_nodes
             } else {
                 total_max_nodes
@@ -59,19 +134,38 @@ impl RingCapacity {
             });
             ring_index += 1;
         }
-        data
+        Ok(data)
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, not(target_arch = "wasm32")))]
 pub mod test_ring_entity {
     use super::*;
 
-    #[tokio::test]
-    async fn test_ring_capacity() {
-        let data = RingCapacity::generate(56, Some(10));
+    #[test]
+    fn test_ring_capacity() {
+        let data = RingCapacity::generate(56, Some(10)).unwrap();
         assert!(!data.is_empty());
         assert!(data.iter().map(|item| item.max_nodes).sum::<u32>() >= 56);
         println!("{:#?}", data);
     }
+
+    #[test]
+    fn test_ring_radius_saturates() {
+        assert_eq!(RingCapacity::get_ring_radius(100, 0), 0);
+        assert_eq!(RingCapacity::get_ring_radius(100, 1), 120);
+        assert_eq!(RingCapacity::get_ring_radius(100, 4_000_000_000), u32::MAX);
+        assert_eq!(RingCapacity::get_ring_radius(u32::MAX, 1), u32::MAX);
+    }
+
+    #[test]
+    fn test_ring_capacity_limits() {
+        // A ring without capacity is skipped, the next ones hold the nodes
+        let data = RingCapacity::generate_with_spacing(5, Some(10), 200.0).unwrap();
+        assert_eq!(data[1].max_nodes, 0);
+        assert_eq!(data.last().unwrap().range[1], 5);
+        assert!(RingCapacity::generate_with_spacing(5, Some(10), 1e30).is_err());
+        let data = RingCapacity::generate_with_spacing(5, Some(10), 0.0).unwrap();
+        assert_eq!(data[1].max_nodes, u32::MAX);
+    }
 }
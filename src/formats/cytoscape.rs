@@ -0,0 +1,168 @@
+use crate::entities::{Edge, GraphLayoutData, Node, NodePositionData};
+use crate::formats::{RawGraph, positions_by_node_id};
+use anyhow::anyhow;
+use serde_json::{Value, json};
+
+/// Cytoscape.js elements exporter/importer.
+#[derive(Debug)]
+pub struct Cytoscape {}
+
+impl Cytoscape {
+    /// Export as `{"elements": {"nodes": [..], "edges": [..]}}` with each node `position` set.
+    /// Edge ids are prefixed with `e` since Cytoscape.js shares the id space between nodes and edges.
+    pub fn export(
+        nodes: &[Node],
+        edges: &[Edge],
+        positions: &[NodePositionData],
+    ) -> anyhow::Result<String> {
+        let positions = positions_by_node_id(positions);
+        let nodes: Vec<Value> = nodes
+            .iter()
+            .map(|node| match positions.get(&node.id) {
+                Some(position) => json!({
+                    "data": { "id": node.id.to_string(), "ring": position.index },
+                    "position": { "x": position.x, "y": position.y },
+                }),
+                None => json!({ "data": { "id": node.id.to_string() } }),
+            })
+            .collect();
+        let edges: Vec<Value> = edges
+            .iter()
+            .map(|edge| {
+                json!({
+                    "data": {
+                        "id": format!("e{}", edge.id),
+                        "source": edge.source_id.to_string(),
+                        "target": edge.target_id.to_string(),
+                    }
+                })
+            })
+            .collect();
+        Ok(serde_json::to_string_pretty(
+            &json!({ "elements": { "nodes": nodes, "edges": edges } }),
+        )?)
+    }
+
+    /// Import Cytoscape.js elements. Accepted forms:
+    /// - `{"elements": {"nodes": [..], "edges": [..]}}`
+    /// - `{"elements": [..]}`
+    /// - `[..]`
+    ///
+    /// The `parent` data of the compound nodes is kept in `parents`.
+    pub fn import(content: &str) -> anyhow::Result<GraphLayoutData> {
+        let value = serde_json::from_str::<Value>(content)?;
+        Self::from_value(&value)
+    }
+
+    /// Import from an already parsed JSON value. See `import`.
+    pub fn from_value(value: &Value) -> anyhow::Result<GraphLayoutData> {
+        let elements = match value.get("elements") {
+            Some(elements) => elements,
+            None => value,
+        };
+        let elements: Vec<&Value> = match elements {
+            Value::Array(items) => items.iter().collect(),
+            Value::Object(groups) => ["nodes", "edges"]
+                .iter()
+                .filter_map(|group| groups.get(*group).and_then(|items| items.as_array()))
+                .flatten()
+                .collect(),
+            _ => return Err(anyhow!("cytoscape elements must be an array or object")),
+        };
+        let mut graph = RawGraph::default();
+        let mut edges: Vec<(Option<String>, String, String)> = Vec::new();
+        let mut parents: Vec<(String, String)> = Vec::new();
+        for element in elements {
+            let data = element
+                .get("data")
+                .ok_or_else(|| anyhow!("cytoscape element without `data`"))?;
+            let get_id = |name: &str| -> Option<String> {
+                match data.get(name) {
+                    Some(Value::String(value)) => Some(value.to_owned()),
+                    Some(Value::Number(value)) => Some(value.to_string()),
+                    _ => None,
+                }
+            };
+            let is_edge = match element.get("group").and_then(|group| group.as_str()) {
+                Some(group) => group == "edges",
+                None => data.get("source").is_some(),
+            };
+            if is_edge {
+                let source = get_id("source").ok_or_else(|| anyhow!("edge without `source`"))?;
+                let target = get_id("target").ok_or_else(|| anyhow!("edge without `target`"))?;
+                edges.push((get_id("id"), source, target));
+            } else {
+                let id = get_id("id").ok_or_else(|| anyhow!("node without `id`"))?;
+                let position = element.get("position").and_then(|position| {
+                    let x = position.get("x")?.as_f64()? as f32;
+                    let y = position.get("y")?.as_f64()? as f32;
+                    Some((x, y))
+                });
+                if let Some(parent) = get_id("parent") {
+                    parents.push((id.to_owned(), parent));
+                }
+                graph.add_node(&id, position);
+            }
+        }
+        // Edges and parents are added after the nodes so the node ids are resolved in declaration order.
+        for (id, parent) in parents.iter() {
+            graph.add_parent(id, parent);
+        }
+        for (id, source, target) in edges.iter() {
+            graph.add_edge(id.as_deref(), source, target);
+        }
+        Ok(graph.build())
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+pub mod test_cytoscape {
+    use super::*;
+    use crate::compound::NodeParent;
+    use crate::formats::test_formats::{assert_round_trip, get_sample_layout};
+
+    #[test]
+    fn test_cytoscape_round_trip() {
+        let (sample_data, positions) = get_sample_layout();
+        let content =
+            Cytoscape::export(&sample_data.nodes, &sample_data.edges, &positions).unwrap();
+        let imported = Cytoscape::import(&content);
+        assert!(imported.is_ok(), "{:?}", imported.err());
+        assert_round_trip(&sample_data, &positions, &imported.unwrap());
+    }
+
+    #[test]
+    fn test_cytoscape_import_elements_array() {
+        let content = r#"[
+            { "group": "nodes", "data": { "id": "a" } },
+            { "data": { "id": "b" }, "position": { "x": 3, "y": 4 } },
+            { "data": { "id": "ab", "source": "a", "target": "b" } }
+        ]"#;
+        let imported = Cytoscape::import(content).unwrap();
+        assert_eq!(imported.nodes.len(), 2);
+        assert_eq!(imported.edges.len(), 1);
+        assert_eq!(imported.coordinates.len(), 1);
+        assert_eq!(imported.coordinates[0].node_id, 1);
+    }
+
+    #[test]
+    fn test_cytoscape_import_compound_nodes() {
+        let content = r#"[
+            { "data": { "id": "site" } },
+            { "data": { "id": "rack", "parent": "site" } },
+            { "data": { "id": "a", "parent": "rack" } },
+            { "data": { "id": "b", "parent": "rack" } },
+            { "data": { "id": "ab", "source": "a", "target": "b" } }
+        ]"#;
+        let imported = Cytoscape::import(content).unwrap();
+        assert_eq!(imported.nodes.len(), 4);
+        assert_eq!(
+            imported.parents,
+            vec![
+                NodeParent::new(1, 0),
+                NodeParent::new(2, 1),
+                NodeParent::new(3, 1)
+            ]
+        );
+    }
+}
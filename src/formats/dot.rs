@@ -0,0 +1,299 @@
+use crate::entities::{Edge, GraphLayoutData, Node, NodePositionData};
+use crate::formats::{RawGraph, positions_by_node_id};
+use anyhow::anyhow;
+use std::collections::HashMap;
+
+/// Graphviz DOT exporter/importer.
+/// The coordinates are stored on the `pos` attribute as `"x,y!"` so the graph can be rendered as is with `neato -n`.
+#[derive(Debug)]
+pub struct Dot {}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Id(String),
+    EdgeOp,
+    Symbol(char),
+}
+
+impl Dot {
+    /// Export the graph with the computed positions embedded.
+    pub fn export(nodes: &[Node], edges: &[Edge], positions: &[NodePositionData]) -> String {
+        let positions = positions_by_node_id(positions);
+        let mut output = String::new();
+        output.push_str("digraph G {\n");
+        output.push_str("  node [shape=circle];\n");
+        for node in nodes.iter() {
+            match positions.get(&node.id) {
+                Some(position) => {
+                    output.push_str(&format!(
+                        "  {} [pos=\"{},{}!\", ring={}, radius={}];\n",
+                        node.id, position.x, position.y, position.index, position.radius
+                    ));
+                }
+                None => {
+                    output.push_str(&format!("  {};\n", node.id));
+                }
+            }
+        }
+        for edge in edges.iter() {
+            output.push_str(&format!(
+                "  {} -> {} [id={}];\n",
+                edge.source_id, edge.target_id, edge.id
+            ));
+        }
+        output.push_str("}\n");
+        output
+    }
+
+    /// Import a DOT document. Coordinates are read from the `pos` node attribute.
+    /// Subgraphs are flattened and default attribute statements (`node [..]`, `edge [..]`, `graph [..]`) are ignored.
+    pub fn import(content: &str) -> anyhow::Result<GraphLayoutData> {
+        let tokens = Self::tokenize(content)?;
+        let mut graph = RawGraph::default();
+        let mut position = 0;
+        let next_id = |position: usize| -> Option<&str> {
+            match tokens.get(position) {
+                Some(Token::Id(value)) => Some(value.as_str()),
+                _ => None,
+            }
+        };
+
+        // Header: [strict] (graph | digraph) [id] {
+        if next_id(position).is_some_and(|value| value.eq_ignore_ascii_case("strict")) {
+            position += 1;
+        }
+        match next_id(position) {
+            Some(value)
+                if value.eq_ignore_ascii_case("graph") || value.eq_ignore_ascii_case("digraph") =>
+            {
+                position += 1;
+            }
+            _ => return Err(anyhow!("expected `graph` or `digraph` keyword")),
+        }
+        if next_id(position).is_some() {
+            position += 1;
+        }
+        if tokens.get(position) != Some(&Token::Symbol('{')) {
+            return Err(anyhow!("expected `{{` after the graph header"));
+        }
+
+        while position < tokens.len() {
+            match &tokens[position] {
+                Token::Symbol(_) => {
+                    position += 1;
+                }
+                Token::EdgeOp => {
+                    return Err(anyhow!("unexpected edge operator"));
+                }
+                Token::Id(value) => {
+                    let keyword = value.to_ascii_lowercase();
+                    if keyword == "subgraph" {
+                        position += 1;
+                        if next_id(position).is_some() {
+                            position += 1;
+                        }
+                        continue;
+                    }
+                    if ["node", "edge", "graph"].contains(&keyword.as_str())
+                        && tokens.get(position + 1) == Some(&Token::Symbol('['))
+                    {
+                        position += 1;
+                        Self::parse_attributes(&tokens, &mut position)?;
+                        continue;
+                    }
+                    if tokens.get(position + 1) == Some(&Token::Symbol('=')) {
+                        // Graph attribute. e.g. `rankdir=LR`
+                        position += 3;
+                        continue;
+                    }
+                    let mut chain: Vec<String> = vec![Self::strip_port(value)];
+                    position += 1;
+                    while tokens.get(position) == Some(&Token::EdgeOp) {
+                        match next_id(position + 1) {
+                            Some(target) => chain.push(Self::strip_port(target)),
+                            None => return Err(anyhow!("expected node id after edge operator")),
+                        }
+                        position += 2;
+                    }
+                    let attributes = Self::parse_attributes(&tokens, &mut position)?;
+                    if chain.len() == 1 {
+                        let node_position = match attributes.get("pos") {
+                            Some(pos) => Some(Self::parse_pos(pos)?),
+                            None => None,
+                        };
+                        graph.add_node(&chain[0], node_position);
+                    } else {
+                        let edge_id = if chain.len() == 2 {
+                            attributes.get("id").map(|value| value.as_str())
+                        } else {
+                            None
+                        };
+                        for pair in chain.windows(2) {
+                            graph.add_edge(edge_id, &pair[0], &pair[1]);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(graph.build())
+    }
+
+    /// Parse consecutive attribute lists (`[a=b, c=d][e=f]`) starting at `position`.
+    /// `position` is moved after the last list.
+    fn parse_attributes(
+        tokens: &[Token],
+        position: &mut usize,
+    ) -> anyhow::Result<HashMap<String, String>> {
+        let mut attributes = HashMap::new();
+        while tokens.get(*position) == Some(&Token::Symbol('[')) {
+            *position += 1;
+            loop {
+                match tokens.get(*position) {
+                    Some(Token::Symbol(']')) => {
+                        *position += 1;
+                        break;
+                    }
+                    Some(Token::Symbol(';')) | Some(Token::Symbol(',')) => {
+                        *position += 1;
+                    }
+                    Some(Token::Id(key)) => {
+                        if tokens.get(*position + 1) == Some(&Token::Symbol('=')) {
+                            match tokens.get(*position + 2) {
+                                Some(Token::Id(value)) => {
+                                    attributes.insert(key.to_owned(), value.to_owned());
+                                }
+                                _ => return Err(anyhow!("expected value for attribute `{}`", key)),
+                            }
+                            *position += 3;
+                        } else {
+                            *position += 1;
+                        }
+                    }
+                    _ => return Err(anyhow!("unterminated attribute list")),
+                }
+            }
+        }
+        Ok(attributes)
+    }
+
+    /// Parse a `pos` attribute. e.g. `"10.5,-3!"`
+    fn parse_pos(value: &str) -> anyhow::Result<(f32, f32)> {
+        let value = value.trim().trim_end_matches('!');
+        let mut parts = value.split(',');
+        let mut parse = || -> anyhow::Result<f32> {
+            parts
+                .next()
+                .ok_or_else(|| anyhow!("invalid pos `{}`", value))?
+                .trim()
+                .parse::<f32>()
+                .map_err(|error| anyhow!("invalid pos `{}`: {}", value, error))
+        };
+        Ok((parse()?, parse()?))
+    }
+
+    /// Remove the port/compass point of a node id. e.g. `a:n` -> `a`
+    fn strip_port(value: &str) -> String {
+        value.split(':').next().unwrap_or(value).to_string()
+    }
+
+    fn tokenize(content: &str) -> anyhow::Result<Vec<Token>> {
+        let mut tokens = Vec::new();
+        let chars: Vec<char> = content.chars().collect();
+        let mut index = 0;
+        while index < chars.len() {
+            let current = chars[index];
+            let next = chars.get(index + 1).copied();
+            if current.is_whitespace() {
+                index += 1;
+            } else if current == '#' || (current == '/' && next == Some('/')) {
+                while index < chars.len() && chars[index] != '\n' {
+                    index += 1;
+                }
+            } else if current == '/' && next == Some('*') {
+                index += 2;
+                while index < chars.len()
+                    && !(chars[index] == '*' && chars.get(index + 1) == Some(&'/'))
+                {
+                    index += 1;
+                }
+                index += 2;
+            } else if current == '-' && (next == Some('>') || next == Some('-')) {
+                tokens.push(Token::EdgeOp);
+                index += 2;
+            } else if "{}[];,=".contains(current) {
+                tokens.push(Token::Symbol(current));
+                index += 1;
+            } else if current == '"' {
+                let mut value = String::new();
+                index += 1;
+                loop {
+                    match chars.get(index) {
+                        Some('"') => break,
+                        Some('\\') if chars.get(index + 1) == Some(&'"') => {
+                            value.push('"');
+                            index += 2;
+                        }
+                        Some(character) => {
+                            value.push(*character);
+                            index += 1;
+                        }
+                        None => return Err(anyhow!("unterminated quoted string")),
+                    }
+                }
+                index += 1;
+                tokens.push(Token::Id(value));
+            } else if current.is_alphanumeric() || "_.-:".contains(current) {
+                let start = index;
+                while index < chars.len()
+                    && (chars[index].is_alphanumeric()
+                        || "_.:".contains(chars[index])
+                        || (chars[index] == '-'
+                            && !matches!(chars.get(index + 1), Some('>') | Some('-'))))
+                {
+                    index += 1;
+                }
+                tokens.push(Token::Id(chars[start..index].iter().collect()));
+            } else {
+                return Err(anyhow!("unexpected character `{}`", current));
+            }
+        }
+        Ok(tokens)
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+pub mod test_dot {
+    use super::*;
+    use crate::formats::test_formats::{assert_round_trip, get_sample_layout};
+
+    #[test]
+    fn test_dot_round_trip() {
+        let (sample_data, positions) = get_sample_layout();
+        let content = Dot::export(&sample_data.nodes, &sample_data.edges, &positions);
+        let imported = Dot::import(&content);
+        assert!(imported.is_ok(), "{:?}", imported.err());
+        assert_round_trip(&sample_data, &positions, &imported.unwrap());
+    }
+
+    #[test]
+    fn test_dot_import_statements() {
+        let content = r#"
+            // Comment
+            strict graph "net" {
+                graph [rankdir=LR];
+                node [shape=box];
+                rankdir = LR
+                core [pos="1,2!"];
+                core -- agg1 -- access1;
+                subgraph cluster_0 { agg2; }
+                "core" -- agg2 [label="uplink"]
+            }
+        "#;
+        let imported = Dot::import(content).unwrap();
+        assert_eq!(imported.nodes.len(), 4);
+        assert_eq!(imported.edges.len(), 3);
+        assert_eq!(imported.coordinates.len(), 1);
+        assert_eq!(imported.coordinates[0].x, 1.0);
+        assert_eq!(imported.coordinates[0].y, 2.0);
+    }
+}
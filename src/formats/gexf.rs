@@ -0,0 +1,135 @@
+use crate::entities::{Edge, GraphLayoutData, Node, NodePositionData};
+use crate::formats::{
+    RawGraph, get_required_xml_attribute, get_xml_attribute, positions_by_node_id,
+};
+use anyhow::anyhow;
+use quick_xml::Reader;
+use quick_xml::events::Event;
+
+/// GEXF 1.3 exporter/importer.
+/// The coordinates are stored on `viz:position`, the ring index and radius as node attributes.
+#[derive(Debug)]
+pub struct Gexf {}
+
+impl Gexf {
+    /// Export the graph with the computed positions embedded.
+    pub fn export(nodes: &[Node], edges: &[Edge], positions: &[NodePositionData]) -> String {
+        let positions = positions_by_node_id(positions);
+        let mut output = String::new();
+        output.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        output.push_str("<gexf xmlns=\"http://gexf.net/1.3\" xmlns:viz=\"http://gexf.net/1.3/viz\" version=\"1.3\">\n");
+        output.push_str("  <graph defaultedgetype=\"directed\" mode=\"static\">\n");
+        output.push_str("    <attributes class=\"node\">\n");
+        output.push_str("      <attribute id=\"ring\" title=\"ring\" type=\"integer\"/>\n");
+        output.push_str("      <attribute id=\"radius\" title=\"radius\" type=\"integer\"/>\n");
+        output.push_str("    </attributes>\n");
+        output.push_str("    <nodes>\n");
+        for node in nodes.iter() {
+            match positions.get(&node.id) {
+                Some(position) => {
+                    output.push_str(&format!(
+                        "      <node id=\"{}\" label=\"{}\">\n",
+                        node.id, node.id
+                    ));
+                    output.push_str("        <attvalues>\n");
+                    output.push_str(&format!(
+                        "          <attvalue for=\"ring\" value=\"{}\"/>\n",
+                        position.index
+                    ));
+                    output.push_str(&format!(
+                        "          <attvalue for=\"radius\" value=\"{}\"/>\n",
+                        position.radius
+                    ));
+                    output.push_str("        </attvalues>\n");
+                    output.push_str(&format!(
+                        "        <viz:position x=\"{}\" y=\"{}\" z=\"{}\"/>\n",
+                        position.x, position.y, position.z
+                    ));
+                    output.push_str("      </node>\n");
+                }
+                None => {
+                    output.push_str(&format!(
+                        "      <node id=\"{}\" label=\"{}\"/>\n",
+                        node.id, node.id
+                    ));
+                }
+            }
+        }
+        output.push_str("    </nodes>\n");
+        output.push_str("    <edges>\n");
+        for edge in edges.iter() {
+            output.push_str(&format!(
+                "      <edge id=\"{}\" source=\"{}\" target=\"{}\"/>\n",
+                edge.id, edge.source_id, edge.target_id
+            ));
+        }
+        output.push_str("    </edges>\n");
+        output.push_str("  </graph>\n");
+        output.push_str("</gexf>\n");
+        output
+    }
+
+    /// Import a GEXF document. Coordinates are read from `viz:position`.
+    pub fn import(content: &str) -> anyhow::Result<GraphLayoutData> {
+        let mut reader = Reader::from_str(content);
+        reader.config_mut().trim_text(true);
+        let mut graph = RawGraph::default();
+        let mut current_node: Option<(String, Option<(f32, f32)>)> = None;
+        loop {
+            match reader.read_event()? {
+                Event::Start(element) if element.local_name().as_ref() == b"node" => {
+                    let id = get_required_xml_attribute(&element, "id")?;
+                    current_node = Some((id, None));
+                }
+                Event::Empty(element) if element.local_name().as_ref() == b"node" => {
+                    let id = get_required_xml_attribute(&element, "id")?;
+                    graph.add_node(&id, None);
+                }
+                Event::End(element) if element.local_name().as_ref() == b"node" => {
+                    if let Some((id, position)) = current_node.take() {
+                        graph.add_node(&id, position);
+                    }
+                }
+                Event::Start(element) | Event::Empty(element)
+                    if element.local_name().as_ref() == b"position" =>
+                {
+                    if let Some((_, position)) = &mut current_node {
+                        let parse = |name: &str| -> anyhow::Result<f32> {
+                            get_required_xml_attribute(&element, name)?
+                                .trim()
+                                .parse::<f32>()
+                                .map_err(|error| anyhow!("invalid `{}` position: {}", name, error))
+                        };
+                        *position = Some((parse("x")?, parse("y")?));
+                    }
+                }
+                Event::Start(element) | Event::Empty(element)
+                    if element.local_name().as_ref() == b"edge" =>
+                {
+                    let id = get_xml_attribute(&element, "id")?;
+                    let source = get_required_xml_attribute(&element, "source")?;
+                    let target = get_required_xml_attribute(&element, "target")?;
+                    graph.add_edge(id.as_deref(), &source, &target);
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+        Ok(graph.build())
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+pub mod test_gexf {
+    use super::*;
+    use crate::formats::test_formats::{assert_round_trip, get_sample_layout};
+
+    #[test]
+    fn test_gexf_round_trip() {
+        let (sample_data, positions) = get_sample_layout();
+        let content = Gexf::export(&sample_data.nodes, &sample_data.edges, &positions);
+        let imported = Gexf::import(&content);
+        assert!(imported.is_ok(), "{:?}", imported.err());
+        assert_round_trip(&sample_data, &positions, &imported.unwrap());
+    }
+}
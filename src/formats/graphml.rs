@@ -0,0 +1,167 @@
+use crate::entities::{Edge, GraphLayoutData, Node, NodePositionData};
+use crate::formats::{
+    RawGraph, get_required_xml_attribute, get_xml_attribute, positions_by_node_id,
+};
+use anyhow::anyhow;
+use quick_xml::Reader;
+use quick_xml::events::Event;
+use std::collections::HashMap;
+
+/// GraphML exporter/importer.
+/// The coordinates are stored on the `x` and `y` node data keys, the ring index and radius on `ring` and `radius`.
+#[derive(Debug)]
+pub struct GraphMl {}
+
+impl GraphMl {
+    /// Export the graph with the computed positions embedded.
+    pub fn export(nodes: &[Node], edges: &[Edge], positions: &[NodePositionData]) -> String {
+        let positions = positions_by_node_id(positions);
+        let mut output = String::new();
+        output.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        output.push_str("<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n");
+        output.push_str("  <key id=\"x\" for=\"node\" attr.name=\"x\" attr.type=\"float\"/>\n");
+        output.push_str("  <key id=\"y\" for=\"node\" attr.name=\"y\" attr.type=\"float\"/>\n");
+        output.push_str("  <key id=\"ring\" for=\"node\" attr.name=\"ring\" attr.type=\"int\"/>\n");
+        output.push_str(
+            "  <key id=\"radius\" for=\"node\" attr.name=\"radius\" attr.type=\"int\"/>\n",
+        );
+        output.push_str("  <graph id=\"G\" edgedefault=\"directed\">\n");
+        for node in nodes.iter() {
+            match positions.get(&node.id) {
+                Some(position) => {
+                    output.push_str(&format!("    <node id=\"{}\">\n", node.id));
+                    output.push_str(&format!("      <data key=\"x\">{}</data>\n", position.x));
+                    output.push_str(&format!("      <data key=\"y\">{}</data>\n", position.y));
+                    output.push_str(&format!(
+                        "      <data key=\"ring\">{}</data>\n",
+                        position.index
+                    ));
+                    output.push_str(&format!(
+                        "      <data key=\"radius\">{}</data>\n",
+                        position.radius
+                    ));
+                    output.push_str("    </node>\n");
+                }
+                None => {
+                    output.push_str(&format!("    <node id=\"{}\"/>\n", node.id));
+                }
+            }
+        }
+        for edge in edges.iter() {
+            output.push_str(&format!(
+                "    <edge id=\"{}\" source=\"{}\" target=\"{}\"/>\n",
+                edge.id, edge.source_id, edge.target_id
+            ));
+        }
+        output.push_str("  </graph>\n");
+        output.push_str("</graphml>\n");
+        output
+    }
+
+    /// Import a GraphML document. Coordinates are read from the node data keys named `x` and `y`.
+    pub fn import(content: &str) -> anyhow::Result<GraphLayoutData> {
+        let mut reader = Reader::from_str(content);
+        reader.config_mut().trim_text(true);
+        let mut graph = RawGraph::default();
+        // Key id -> attribute name
+        let mut keys: HashMap<String, String> = HashMap::new();
+        let mut current_node: Option<(String, Option<f32>, Option<f32>)> = None;
+        let mut current_data_key: Option<String> = None;
+        loop {
+            match reader.read_event()? {
+                Event::Start(element) | Event::Empty(element)
+                    if element.local_name().as_ref() == b"key" =>
+                {
+                    let id = get_required_xml_attribute(&element, "id")?;
+                    let name = get_xml_attribute(&element, "attr.name")?.unwrap_or(id.clone());
+                    keys.insert(id, name);
+                }
+                Event::Start(element) if element.local_name().as_ref() == b"node" => {
+                    let id = get_required_xml_attribute(&element, "id")?;
+                    current_node = Some((id, None, None));
+                }
+                Event::Empty(element) if element.local_name().as_ref() == b"node" => {
+                    let id = get_required_xml_attribute(&element, "id")?;
+                    graph.add_node(&id, None);
+                }
+                Event::End(element) if element.local_name().as_ref() == b"node" => {
+                    if let Some((id, x, y)) = current_node.take() {
+                        graph.add_node(&id, x.zip(y));
+                    }
+                }
+                Event::Start(element) if element.local_name().as_ref() == b"data" => {
+                    current_data_key = get_xml_attribute(&element, "key")?;
+                }
+                Event::End(element) if element.local_name().as_ref() == b"data" => {
+                    current_data_key = None;
+                }
+                Event::Text(text) => {
+                    if let (Some((_, x, y)), Some(key)) = (&mut current_node, &current_data_key) {
+                        let name = keys.get(key).unwrap_or(key);
+                        let value = || -> anyhow::Result<f32> {
+                            let value = text.decode()?;
+                            value
+                                .trim()
+                                .parse::<f32>()
+                                .map_err(|error| anyhow!("invalid `{}` value: {}", name, error))
+                        };
+                        match name.as_str() {
+                            "x" => *x = Some(value()?),
+                            "y" => *y = Some(value()?),
+                            _ => {}
+                        }
+                    }
+                }
+                Event::Start(element) | Event::Empty(element)
+                    if element.local_name().as_ref() == b"edge" =>
+                {
+                    let id = get_xml_attribute(&element, "id")?;
+                    let source = get_required_xml_attribute(&element, "source")?;
+                    let target = get_required_xml_attribute(&element, "target")?;
+                    graph.add_edge(id.as_deref(), &source, &target);
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+        Ok(graph.build())
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+pub mod test_graphml {
+    use super::*;
+    use crate::formats::test_formats::{assert_round_trip, get_sample_layout};
+
+    #[test]
+    fn test_graphml_round_trip() {
+        let (sample_data, positions) = get_sample_layout();
+        let content = GraphMl::export(&sample_data.nodes, &sample_data.edges, &positions);
+        let imported = GraphMl::import(&content);
+        assert!(imported.is_ok(), "{:?}", imported.err());
+        assert_round_trip(&sample_data, &positions, &imported.unwrap());
+    }
+
+    #[test]
+    fn test_graphml_import_string_ids() {
+        let content = r#"<?xml version="1.0" encoding="UTF-8"?>
+<graphml xmlns="http://graphml.graphdrawing.org/xmlns">
+  <key id="d0" for="node" attr.name="x" attr.type="double"/>
+  <key id="d1" for="node" attr.name="y" attr.type="double"/>
+  <graph edgedefault="undirected">
+    <node id="core"><data key="d0">1.5</data><data key="d1">-2</data></node>
+    <node id="edge"/>
+    <edge source="core" target="edge"/>
+  </graph>
+</graphml>"#;
+        let imported = GraphMl::import(content).unwrap();
+        assert_eq!(imported.nodes.len(), 2);
+        assert_eq!(imported.edges.len(), 1);
+        assert_eq!(imported.edges[0].source_id, 0);
+        assert_eq!(imported.edges[0].target_id, 1);
+        assert_eq!(imported.coordinates.len(), 1);
+        assert_eq!(imported.coordinates[0].x, 1.5);
+        assert_eq!(imported.coordinates[0].y, -2.0);
+        assert_eq!(imported.get_source_id(0), "core");
+    }
+}
@@ -0,0 +1,78 @@
+use crate::compound::NodeParent;
+use crate::entities::{Edge, GraphLayoutData, Node, NodeCoordinate, NodePositionData};
+use serde::{Deserialize, Serialize};
+
+/// Native JSON exporter/importer.
+/// Same structure as the sample data (`nodes`, `edges`) with the computed `positions`.
+#[derive(Debug)]
+pub struct Json {}
+
+#[derive(Debug, Serialize)]
+struct JsonExport<'a> {
+    nodes: &'a [Node],
+    edges: &'a [Edge],
+    positions: &'a [NodePositionData],
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonImport {
+    nodes: Vec<Node>,
+    #[serde(default)]
+    edges: Vec<Edge>,
+    #[serde(default)]
+    positions: Vec<NodeCoordinate>,
+    #[serde(default)]
+    parents: Vec<NodeParent>,
+}
+
+impl Json {
+    /// Export the graph with the computed positions.
+    pub fn export(
+        nodes: &[Node],
+        edges: &[Edge],
+        positions: &[NodePositionData],
+    ) -> anyhow::Result<String> {
+        Ok(serde_json::to_string_pretty(&JsonExport {
+            nodes,
+            edges,
+            positions,
+        })?)
+    }
+
+    /// Import a native JSON document. `positions` and `parents` are optional.
+    pub fn import(content: &str) -> anyhow::Result<GraphLayoutData> {
+        let data = serde_json::from_str::<JsonImport>(content)?;
+        Ok(Self::from_data(data))
+    }
+
+    /// Import from an already parsed JSON value. See `import`.
+    pub fn from_value(value: &serde_json::Value) -> anyhow::Result<GraphLayoutData> {
+        let data = JsonImport::deserialize(value)?;
+        Ok(Self::from_data(data))
+    }
+
+    fn from_data(data: JsonImport) -> GraphLayoutData {
+        GraphLayoutData {
+            nodes: data.nodes,
+            edges: data.edges,
+            coordinates: data.positions,
+            parents: data.parents,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+pub mod test_json {
+    use super::*;
+    use crate::formats::test_formats::{assert_round_trip, get_sample_layout};
+
+    #[test]
+    fn test_json_round_trip() {
+        let (sample_data, positions) = get_sample_layout();
+        let content = Json::export(&sample_data.nodes, &sample_data.edges, &positions).unwrap();
+        let imported = Json::import(&content);
+        assert!(imported.is_ok(), "{:?}", imported.err());
+        assert_round_trip(&sample_data, &positions, &imported.unwrap());
+    }
+}
@@ -0,0 +1,362 @@
+pub mod cytoscape;
+pub mod dot;
+pub mod gexf;
+pub mod graphml;
+pub mod json;
+pub mod svg;
+pub use cytoscape::Cytoscape;
+pub use dot::Dot;
+pub use gexf::Gexf;
+pub use graphml::GraphMl;
+pub use json::Json;
+pub use svg::{Svg, SvgColor, SvgOptions};
+
+use crate::compound::NodeParent;
+use crate::entities::{Edge, GraphLayoutData, Node, NodeCoordinate, NodePositionData};
+use anyhow::anyhow;
+use quick_xml::events::BytesStart;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::path::Path;
+use std::str::FromStr;
+
+/// The supported graph file formats
+/// Formats:
+/// - Json: native format. `{"nodes": [..], "edges": [..], "positions": [..]}`
+/// - Cytoscape: Cytoscape.js elements
+/// - GraphMl, Gexf, Dot: see the respective exporters
+/// - Svg: rendering of the layout, export only
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GraphFormat {
+    Json,
+    Cytoscape,
+    GraphMl,
+    Gexf,
+    Dot,
+    Svg,
+}
+
+impl GraphFormat {
+    /// Get the format based on the file extension.
+    /// `.json` is ambiguous between the native and Cytoscape.js formats so it is not resolved here.
+    pub fn from_extension(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "graphml" => Some(Self::GraphMl),
+            "gexf" => Some(Self::Gexf),
+            "dot" | "gv" => Some(Self::Dot),
+            "cyjs" => Some(Self::Cytoscape),
+            "svg" => Some(Self::Svg),
+            _ => None,
+        }
+    }
+
+    /// Detect the format based on the content.
+    pub fn detect(content: &str) -> Self {
+        let content = content.trim_start();
+        if content.starts_with('<') {
+            if content.contains("<gexf") {
+                Self::Gexf
+            } else if content.contains("<svg") {
+                Self::Svg
+            } else {
+                Self::GraphMl
+            }
+        } else if content.starts_with('[') {
+            Self::Cytoscape
+        } else if content.starts_with('{') {
+            match serde_json::from_str::<serde_json::Value>(content) {
+                Ok(value) if value.get("elements").is_some() => Self::Cytoscape,
+                _ => Self::Json,
+            }
+        } else {
+            Self::Dot
+        }
+    }
+
+    pub fn import(&self, content: &str) -> anyhow::Result<GraphLayoutData> {
+        match self {
+            Self::Json => Json::import(content),
+            Self::Cytoscape => Cytoscape::import(content),
+            Self::GraphMl => GraphMl::import(content),
+            Self::Gexf => Gexf::import(content),
+            Self::Dot => Dot::import(content),
+            Self::Svg => Err(anyhow!("svg is an export only format")),
+        }
+    }
+
+    pub fn export(
+        &self,
+        nodes: &[Node],
+        edges: &[Edge],
+        positions: &[NodePositionData],
+    ) -> anyhow::Result<String> {
+        match self {
+            Self::Json => Json::export(nodes, edges, positions),
+            Self::Cytoscape => Cytoscape::export(nodes, edges, positions),
+            Self::GraphMl => Ok(GraphMl::export(nodes, edges, positions)),
+            Self::Gexf => Ok(Gexf::export(nodes, edges, positions)),
+            Self::Dot => Ok(Dot::export(nodes, edges, positions)),
+            Self::Svg => Ok(Svg::export(nodes, edges, positions)),
+        }
+    }
+}
+
+impl FromStr for GraphFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "json" => Ok(Self::Json),
+            "cytoscape" | "cyjs" => Ok(Self::Cytoscape),
+            "graphml" => Ok(Self::GraphMl),
+            "gexf" => Ok(Self::Gexf),
+            "dot" | "gv" => Ok(Self::Dot),
+            "svg" => Ok(Self::Svg),
+            _ => Err(anyhow!("unknown graph format `{}`", value)),
+        }
+    }
+}
+
+impl Display for GraphFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let value = match self {
+            Self::Json => "json",
+            Self::Cytoscape => "cytoscape",
+            Self::GraphMl => "graphml",
+            Self::Gexf => "gexf",
+            Self::Dot => "dot",
+            Self::Svg => "svg",
+        };
+        write!(f, "{}", value)
+    }
+}
+
+/// Map the computed positions by node id.
+fn positions_by_node_id(positions: &[NodePositionData]) -> HashMap<u32, &NodePositionData> {
+    positions.iter().map(|item| (item.node_id, item)).collect()
+}
+
+/// Get the unescaped value of an XML attribute.
+fn get_xml_attribute(element: &BytesStart, name: &str) -> anyhow::Result<Option<String>> {
+    match element.try_get_attribute(name)? {
+        Some(attribute) => Ok(Some(attribute.unescape_value()?.into_owned())),
+        None => Ok(None),
+    }
+}
+
+/// Get the value of a required XML attribute.
+fn get_required_xml_attribute(element: &BytesStart, name: &str) -> anyhow::Result<String> {
+    get_xml_attribute(element, name)?.ok_or_else(|| {
+        anyhow!(
+            "missing attribute `{}` on <{}>",
+            name,
+            String::from_utf8_lossy(element.local_name().as_ref())
+        )
+    })
+}
+
+#[derive(Debug)]
+struct RawNode {
+    id: String,
+    position: Option<(f32, f32)>,
+}
+
+#[derive(Debug)]
+struct RawEdge {
+    id: Option<String>,
+    source: String,
+    target: String,
+}
+
+/// Collects the nodes and edges of an imported file using their original (string) ids.
+/// The ids are resolved to `u32` on `build`:
+/// - numeric ids are kept as is when every node id is numeric.
+/// - otherwise, nodes are numbered by order of appearance.
+#[derive(Debug, Default)]
+struct RawGraph {
+    nodes: Vec<RawNode>,
+    node_index: HashMap<String, usize>,
+    edges: Vec<RawEdge>,
+    /// Node and parent ids of the compound nodes
+    parents: Vec<(String, String)>,
+}
+
+impl RawGraph {
+    fn add_node(&mut self, id: &str, position: Option<(f32, f32)>) {
+        match self.node_index.get(id) {
+            Some(index) => {
+                if position.is_some() {
+                    self.nodes[*index].position = position;
+                }
+            }
+            None => {
+                self.node_index.insert(id.to_string(), self.nodes.len());
+                self.nodes.push(RawNode {
+                    id: id.to_string(),
+                    position,
+                });
+            }
+        }
+    }
+
+    fn add_edge(&mut self, id: Option<&str>, source: &str, target: &str) {
+        // Edges may reference nodes that are not declared (e.g. DOT).
+        self.add_node(source, None);
+        self.add_node(target, None);
+        self.edges.push(RawEdge {
+            id: id.map(|value| value.to_string()),
+            source: source.to_string(),
+            target: target.to_string(),
+        });
+    }
+
+    /// Parent of a node, e.g. the Cytoscape.js `parent` data. The parent is declared as a node.
+    fn add_parent(&mut self, id: &str, parent: &str) {
+        self.add_node(parent, None);
+        self.parents.push((id.to_string(), parent.to_string()));
+    }
+
+    fn build(self) -> GraphLayoutData {
+        let numeric_ids = self
+            .nodes
+            .iter()
+            .map(|node| node.id.parse::<u32>().ok())
+            .collect::<Option<Vec<u32>>>();
+        let (node_ids, source_ids): (Vec<u32>, HashMap<u32, String>) = match numeric_ids {
+            Some(ids) => (ids, HashMap::new()),
+            None => (
+                (0..self.nodes.len() as u32).collect(),
+                self.nodes
+                    .iter()
+                    .enumerate()
+                    .map(|(index, node)| (index as u32, node.id.to_owned()))
+                    .collect(),
+            ),
+        };
+        let resolve = |id: &str| -> u32 { node_ids[self.node_index[id]] };
+
+        let edge_ids = self
+            .edges
+            .iter()
+            .map(|edge| edge.id.as_ref().and_then(|id| id.parse::<u32>().ok()))
+            .collect::<Option<Vec<u32>>>();
+        let edge_ids: Vec<u32> = match edge_ids {
+            Some(ids) => ids,
+            None => (0..self.edges.len() as u32).collect(),
+        };
+
+        let nodes = node_ids.iter().map(|id| Node { id: *id }).collect();
+        let coordinates = self
+            .nodes
+            .iter()
+            .zip(node_ids.iter())
+            .filter_map(|(node, id)| {
+                node.position
+                    .map(|(x, y)| NodeCoordinate { node_id: *id, x, y })
+            })
+            .collect();
+        let edges = self
+            .edges
+            .iter()
+            .zip(edge_ids.iter())
+            .map(|(edge, id)| Edge {
+                id: *id,
+                source_id: resolve(&edge.source),
+                target_id: resolve(&edge.target),
+            })
+            .collect();
+        let parents = self
+            .parents
+            .iter()
+            .map(|(id, parent)| NodeParent::new(resolve(id), resolve(parent)))
+            .collect();
+        GraphLayoutData {
+            nodes,
+            edges,
+            coordinates,
+            source_ids,
+            parents,
+        }
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+pub mod test_formats {
+    use super::GraphFormat;
+    use crate::LayoutOptions;
+    use crate::cpu::CpuConcentric;
+    use crate::entities::{Edge, GraphLayoutData, Node, NodePositionData};
+    use crate::generators::GraphKind;
+
+    #[derive(Debug, Clone)]
+    pub struct SampleData {
+        pub nodes: Vec<Node>,
+        pub edges: Vec<Edge>,
+    }
+
+    /// Compute the layout of a generated telco graph which is used for the export/import round trips.
+    pub fn get_sample_layout() -> (SampleData, Vec<NodePositionData>) {
+        let graph = GraphKind::Telco {
+            nodes: 52,
+            fan_out: 3,
+        }
+        .generate(0);
+        let sample_data = SampleData {
+            nodes: graph.nodes,
+            edges: graph.edges,
+        };
+        let mut layout = CpuConcentric::new(
+            &sample_data.nodes,
+            &sample_data.edges,
+            &LayoutOptions::default(),
+        );
+        let positions = layout.get().unwrap();
+        (sample_data, positions)
+    }
+
+    #[test]
+    fn test_detect_format() {
+        let (sample_data, positions) = get_sample_layout();
+        for format in [
+            GraphFormat::Json,
+            GraphFormat::Cytoscape,
+            GraphFormat::GraphMl,
+            GraphFormat::Gexf,
+            GraphFormat::Dot,
+            GraphFormat::Svg,
+        ] {
+            let content = format
+                .export(&sample_data.nodes, &sample_data.edges, &positions)
+                .unwrap();
+            assert_eq!(GraphFormat::detect(&content), format);
+            assert_eq!(format.to_string().parse::<GraphFormat>().unwrap(), format);
+        }
+    }
+
+    /// Check that the imported graph matches the exported one, including the coordinates.
+    pub fn assert_round_trip(
+        sample_data: &SampleData,
+        positions: &[NodePositionData],
+        imported: &GraphLayoutData,
+    ) {
+        assert_eq!(sample_data.nodes.len(), imported.nodes.len());
+        assert_eq!(sample_data.edges.len(), imported.edges.len());
+        assert_eq!(positions.len(), imported.coordinates.len());
+        for (edge, imported_edge) in sample_data.edges.iter().zip(imported.edges.iter()) {
+            assert_eq!(edge.source_id, imported_edge.source_id);
+            assert_eq!(edge.target_id, imported_edge.target_id);
+        }
+        for position in positions.iter() {
+            let coordinate = imported
+                .coordinates
+                .iter()
+                .find(|item| item.node_id == position.node_id)
+                .expect("coordinate not found");
+            assert_eq!(position.x, coordinate.x);
+            assert_eq!(position.y, coordinate.y);
+        }
+    }
+}
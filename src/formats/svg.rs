@@ -0,0 +1,443 @@
+use crate::entities::{Edge, Node, NodePositionData};
+use crate::formats::positions_by_node_id;
+use crate::shape::RingGeometry;
+use quick_xml::escape::escape;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+
+/// Colours of the nodes, the Tableau 10 palette
+const PALETTE: [&str; 10] = [
+    "#4e79a7", "#f28e2b", "#e15759", "#76b7b2", "#59a14f", "#edc948", "#b07aa1", "#ff9da7",
+    "#9c755f", "#bab0ac",
+];
+/// Colour of the nodes without a group
+const NO_GROUP_COLOR: &str = "#9e9e9e";
+
+/// Colouring of the nodes
+/// Kinds:
+/// - Ring: one colour per ring (default)
+/// - Group: one colour per group of `SvgOptions::groups`
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SvgColor {
+    #[default]
+    Ring,
+    Group,
+}
+
+/// Options of the svg rendering.
+/// Parameters:
+/// - width, height: size of the canvas in pixels. Default: 1000 x 1000
+/// - padding: space kept around the drawing when fitted. Default: 20
+/// - fit: scale and centre the drawing to the canvas, otherwise 1 unit is 1 pixel around the layout centre. Default: true
+/// - node_size: diameter of the nodes in layout units. Default: 30
+/// - node_sizes: diameter per node id
+/// - color: see `SvgColor`
+/// - groups: group per node id, used by `SvgColor::Group`
+/// - ring_guides: draw a guide for each ring radius. Default: true
+/// - ring_geometry: shape of the ring guides, see `LayoutOptions::get_ring_geometry`. Default: circle
+/// - labels: draw the node labels. Default: false
+/// - node_labels: label per node id, the node id otherwise
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SvgOptions {
+    pub width: u32,
+    pub height: u32,
+    pub padding: f32,
+    pub fit: bool,
+    pub node_size: f32,
+    pub node_sizes: HashMap<u32, f32>,
+    pub color: SvgColor,
+    pub groups: HashMap<u32, u32>,
+    pub ring_guides: bool,
+    pub ring_geometry: RingGeometry,
+    pub labels: bool,
+    pub node_labels: HashMap<u32, String>,
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        Self {
+            width: 1000,
+            height: 1000,
+            padding: 20.0,
+            fit: true,
+            node_size: 30.0,
+            node_sizes: HashMap::new(),
+            color: SvgColor::Ring,
+            groups: HashMap::new(),
+            ring_guides: true,
+            ring_geometry: RingGeometry::default(),
+            labels: false,
+            node_labels: HashMap::new(),
+        }
+    }
+}
+
+impl SvgOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn size(mut self, width: u32, height: u32) -> Self {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    pub fn padding(mut self, padding: f32) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    pub fn fit(mut self, fit: bool) -> Self {
+        self.fit = fit;
+        self
+    }
+
+    pub fn node_size(mut self, node_size: f32) -> Self {
+        self.node_size = node_size;
+        self
+    }
+
+    pub fn node_sizes(mut self, node_sizes: HashMap<u32, f32>) -> Self {
+        self.node_sizes = node_sizes;
+        self
+    }
+
+    /// Colour the nodes by group
+    pub fn groups(mut self, groups: HashMap<u32, u32>) -> Self {
+        self.color = SvgColor::Group;
+        self.groups = groups;
+        self
+    }
+
+    pub fn ring_guides(mut self, ring_guides: bool) -> Self {
+        self.ring_guides = ring_guides;
+        self
+    }
+
+    pub fn ring_geometry(mut self, ring_geometry: RingGeometry) -> Self {
+        self.ring_geometry = ring_geometry;
+        self
+    }
+
+    pub fn labels(mut self, labels: bool) -> Self {
+        self.labels = labels;
+        self
+    }
+
+    /// Draw these labels instead of the node ids
+    pub fn node_labels(mut self, node_labels: HashMap<u32, String>) -> Self {
+        self.labels = true;
+        self.node_labels = node_labels;
+        self
+    }
+
+    fn get_node_radius(&self, node_id: u32) -> f32 {
+        self.node_sizes
+            .get(&node_id)
+            .copied()
+            .unwrap_or(self.node_size)
+            / 2.0
+    }
+
+    fn get_node_color(&self, position: &NodePositionData) -> &'static str {
+        match self.color {
+            SvgColor::Ring => PALETTE[position.index as usize % PALETTE.len()],
+            SvgColor::Group => match self.groups.get(&position.node_id) {
+                Some(group) => PALETTE[*group as usize % PALETTE.len()],
+                None => NO_GROUP_COLOR,
+            },
+        }
+    }
+}
+
+/// Layout coordinates to canvas pixels
+#[derive(Debug, Clone, Copy)]
+struct Transform {
+    scale: f32,
+    x: f32,
+    y: f32,
+}
+
+impl Transform {
+    fn apply(&self, x: f32, y: f32) -> (f32, f32) {
+        (x * self.scale + self.x, y * self.scale + self.y)
+    }
+}
+
+/// Standalone SVG renderer, export only.
+/// The nodes without a position and their edges are not drawn.
+#[derive(Debug)]
+pub struct Svg {}
+
+impl Svg {
+    /// Render the graph with the default `SvgOptions`.
+    pub fn export(nodes: &[Node], edges: &[Edge], positions: &[NodePositionData]) -> String {
+        Self::render(nodes, edges, positions, &SvgOptions::default())
+    }
+
+    pub fn render(
+        nodes: &[Node],
+        edges: &[Edge],
+        positions: &[NodePositionData],
+        options: &SvgOptions,
+    ) -> String {
+        let positions_map = positions_by_node_id(positions);
+        let (cx, cy) = positions
+            .first()
+            .map(|position| (position.cx, position.cy))
+            .unwrap_or_default();
+        let radiuses: BTreeSet<u32> = match options.ring_guides {
+            true => positions
+                .iter()
+                .map(|position| position.radius)
+                .filter(|radius| *radius > 0)
+                .collect(),
+            false => BTreeSet::new(),
+        };
+        let transform = Self::get_transform(positions, &radiuses, (cx, cy), options);
+
+        let mut output = String::new();
+        output.push_str(&format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n",
+            w = options.width,
+            h = options.height
+        ));
+        output.push_str("  <rect width=\"100%\" height=\"100%\" fill=\"#ffffff\"/>\n");
+        if !radiuses.is_empty() {
+            let (x, y) = transform.apply(cx, cy);
+            output.push_str(
+                "  <g class=\"ring-guides\" fill=\"none\" stroke=\"#d0d0d0\" stroke-dasharray=\"4 4\">\n",
+            );
+            for radius in radiuses.iter() {
+                let radius = *radius as f32 * transform.scale;
+                let (rx, ry) = options.ring_geometry.get_extent(radius);
+                output.push_str(&match options.ring_geometry {
+                    RingGeometry::Circle => format!(
+                        "    <circle cx=\"{:.2}\" cy=\"{:.2}\" r=\"{:.2}\"/>\n",
+                        x, y, radius
+                    ),
+                    RingGeometry::Ellipse { .. } => format!(
+                        "    <ellipse cx=\"{:.2}\" cy=\"{:.2}\" rx=\"{:.2}\" ry=\"{:.2}\"/>\n",
+                        x, y, rx, ry
+                    ),
+                    RingGeometry::RoundedRectangle { corner_radius, .. } => format!(
+                        "    <rect x=\"{:.2}\" y=\"{:.2}\" width=\"{:.2}\" height=\"{:.2}\" rx=\"{:.2}\"/>\n",
+                        x - rx,
+                        y - ry,
+                        2.0 * rx,
+                        2.0 * ry,
+                        corner_radius * radius
+                    ),
+                });
+            }
+            output.push_str("  </g>\n");
+        }
+        output.push_str("  <g class=\"edges\" stroke=\"#999999\" stroke-opacity=\"0.6\">\n");
+        for edge in edges.iter() {
+            if let (Some(source), Some(target)) = (
+                positions_map.get(&edge.source_id),
+                positions_map.get(&edge.target_id),
+            ) {
+                let (x1, y1) = transform.apply(source.x, source.y);
+                let (x2, y2) = transform.apply(target.x, target.y);
+                output.push_str(&format!(
+                    "    <line x1=\"{:.2}\" y1=\"{:.2}\" x2=\"{:.2}\" y2=\"{:.2}\"/>\n",
+                    x1, y1, x2, y2
+                ));
+            }
+        }
+        output.push_str("  </g>\n");
+        output.push_str("  <g class=\"nodes\" stroke=\"#ffffff\">\n");
+        for node in nodes.iter() {
+            if let Some(position) = positions_map.get(&node.id) {
+                let (x, y) = transform.apply(position.x, position.y);
+                output.push_str(&format!(
+                    "    <circle cx=\"{:.2}\" cy=\"{:.2}\" r=\"{:.2}\" fill=\"{}\"><title>{}</title></circle>\n",
+                    x,
+                    y,
+                    options.get_node_radius(node.id) * transform.scale,
+                    options.get_node_color(position),
+                    escape(Self::get_label(node.id, options).as_str())
+                ));
+            }
+        }
+        output.push_str("  </g>\n");
+        if options.labels {
+            output.push_str(
+                "  <g class=\"labels\" font-family=\"sans-serif\" text-anchor=\"middle\" dominant-baseline=\"central\">\n",
+            );
+            for node in nodes.iter() {
+                if let Some(position) = positions_map.get(&node.id) {
+                    let (x, y) = transform.apply(position.x, position.y);
+                    let font_size = (options.get_node_radius(node.id) * transform.scale).max(6.0);
+                    output.push_str(&format!(
+                        "    <text x=\"{:.2}\" y=\"{:.2}\" font-size=\"{:.2}\">{}</text>\n",
+                        x,
+                        y,
+                        font_size,
+                        escape(Self::get_label(node.id, options).as_str())
+                    ));
+                }
+            }
+            output.push_str("  </g>\n");
+        }
+        output.push_str("</svg>\n");
+        output
+    }
+
+    fn get_label(node_id: u32, options: &SvgOptions) -> String {
+        match options.node_labels.get(&node_id) {
+            Some(label) => label.to_owned(),
+            None => node_id.to_string(),
+        }
+    }
+
+    /// Fit the nodes and the ring guides to the canvas minus the padding, or centre the layout
+    fn get_transform(
+        positions: &[NodePositionData],
+        radiuses: &BTreeSet<u32>,
+        (cx, cy): (f32, f32),
+        options: &SvgOptions,
+    ) -> Transform {
+        let (width, height) = (options.width as f32, options.height as f32);
+        if !options.fit || positions.is_empty() {
+            return Transform {
+                scale: 1.0,
+                x: width / 2.0 - cx,
+                y: height / 2.0 - cy,
+            };
+        }
+        let mut bounds = [
+            f32::INFINITY,
+            f32::INFINITY,
+            f32::NEG_INFINITY,
+            f32::NEG_INFINITY,
+        ];
+        let mut extend = |x: f32, y: f32, (rx, ry): (f32, f32)| {
+            bounds = [
+                bounds[0].min(x - rx),
+                bounds[1].min(y - ry),
+                bounds[2].max(x + rx),
+                bounds[3].max(y + ry),
+            ];
+        };
+        for position in positions.iter() {
+            let radius = options.get_node_radius(position.node_id);
+            extend(position.x, position.y, (radius, radius));
+        }
+        if let Some(radius) = radiuses.last() {
+            extend(cx, cy, options.ring_geometry.get_extent(*radius as f32));
+        }
+        let [min_x, min_y, max_x, max_y] = bounds;
+        let available_width = (width - 2.0 * options.padding).max(1.0);
+        let available_height = (height - 2.0 * options.padding).max(1.0);
+        let scale = match (max_x - min_x, max_y - min_y) {
+            (drawing_width, drawing_height) if drawing_width > 0.0 && drawing_height > 0.0 => {
+                (available_width / drawing_width).min(available_height / drawing_height)
+            }
+            _ => 1.0,
+        };
+        Transform {
+            scale,
+            x: width / 2.0 - (min_x + max_x) / 2.0 * scale,
+            y: height / 2.0 - (min_y + max_y) / 2.0 * scale,
+        }
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+pub mod test_svg {
+    use super::*;
+    use crate::formats::GraphFormat;
+    use crate::formats::test_formats::get_sample_layout;
+
+    /// Values of an attribute in the rendered svg
+    fn get_values(content: &str, element: &str, attribute: &str) -> Vec<f32> {
+        content
+            .lines()
+            .filter(|line| line.trim_start().starts_with(&format!("<{} ", element)))
+            .filter_map(|line| {
+                let start = line.find(&format!(" {}=\"", attribute))? + attribute.len() + 3;
+                line[start..].split('"').next()?.parse::<f32>().ok()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_svg_render() {
+        let (sample_data, positions) = get_sample_layout();
+        let content = Svg::export(&sample_data.nodes, &sample_data.edges, &positions);
+        let rings = positions
+            .iter()
+            .map(|item| item.radius)
+            .filter(|radius| *radius > 0)
+            .collect::<BTreeSet<u32>>()
+            .len();
+        assert!(content.starts_with("<svg"));
+        assert_eq!(content.matches("<line ").count(), sample_data.edges.len());
+        assert_eq!(
+            content.matches("<circle ").count(),
+            sample_data.nodes.len() + rings
+        );
+        assert_eq!(content.matches("<text ").count(), 0);
+        // Fitted to the canvas minus the padding
+        for (attribute, element) in [("cx", "circle"), ("cy", "circle"), ("x1", "line")] {
+            let values = get_values(&content, element, attribute);
+            assert!(!values.is_empty());
+            assert!(values.iter().all(|value| (20.0..=980.0).contains(value)));
+        }
+        assert_eq!(GraphFormat::detect(&content), GraphFormat::Svg);
+        assert!(GraphFormat::Svg.import(&content).is_err());
+    }
+
+    #[test]
+    fn test_svg_options() {
+        let (sample_data, positions) = get_sample_layout();
+        let labels: HashMap<u32, String> = [(1, "core <1> & co".to_string())].into();
+        let groups: HashMap<u32, u32> = [(1, 1)].into();
+        let options = SvgOptions::new()
+            .size(400, 300)
+            .ring_guides(false)
+            .node_labels(labels)
+            .groups(groups);
+        let content = Svg::render(&sample_data.nodes, &sample_data.edges, &positions, &options);
+        assert!(content.contains("width=\"400\" height=\"300\""));
+        assert_eq!(content.matches("<circle ").count(), sample_data.nodes.len());
+        assert_eq!(content.matches("<text ").count(), sample_data.nodes.len());
+        assert!(content.contains(">core &lt;1&gt; &amp; co</text>"));
+        assert_eq!(content.matches(PALETTE[1]).count(), 1);
+        let values = get_values(&content, "circle", "cy");
+        assert!(values.iter().all(|value| (20.0..=280.0).contains(value)));
+
+        // Without fitting, the centre of the layout is the centre of the canvas
+        let options = SvgOptions::new().fit(false).ring_guides(false);
+        let content = Svg::render(&sample_data.nodes, &[], &positions[..1], &options);
+        assert!(content.contains("<circle cx=\"500.00\" cy=\"500.00\" r=\"15.00\""));
+
+        // Guides following the ring shape
+        let rings = positions
+            .iter()
+            .map(|position| position.radius)
+            .filter(|radius| *radius > 0)
+            .collect::<BTreeSet<u32>>()
+            .len();
+        let geometry = RingGeometry::Ellipse {
+            scale_x: 2.0,
+            scale_y: 1.0,
+        };
+        let options = SvgOptions::new().ring_geometry(geometry);
+        let content = Svg::render(&sample_data.nodes, &[], &positions, &options);
+        assert_eq!(content.matches("<ellipse ").count(), rings);
+        let geometry = RingGeometry::RoundedRectangle {
+            scale_x: 1.0,
+            scale_y: 1.0,
+            corner_radius: 0.0,
+        };
+        let options = SvgOptions::new().ring_geometry(geometry);
+        let content = Svg::render(&sample_data.nodes, &[], &positions, &options);
+        assert_eq!(content.matches("<rect ").count(), rings + 1);
+    }
+}
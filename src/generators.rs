@@ -0,0 +1,386 @@
+//! Deterministic synthetic graphs, e.g. the inputs of the tests and benchmarks.
+//! The same kind and seed always give the same graph, on every platform.
+
+use crate::entities::{Edge, GraphLayoutData, Node};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Seeded pseudo random numbers (SplitMix64).
+/// Not suited for cryptography, only for reproducible graphs.
+#[derive(Debug, Clone)]
+pub struct SeededRng {
+    state: u64,
+}
+
+impl SeededRng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut value = self.state;
+        value = (value ^ (value >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        value = (value ^ (value >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        value ^ (value >> 31)
+    }
+
+    /// Uniform number in `0..bound`, 0 when `bound` is 0
+    pub fn below(&mut self, bound: u32) -> u32 {
+        ((self.next_u64() as u128 * bound as u128) >> 64) as u32
+    }
+
+    /// Uniform number in `0..1`
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// True with the given probability, between 0 and 1
+    pub fn chance(&mut self, probability: f64) -> bool {
+        self.next_f64() < probability
+    }
+}
+
+/// Kind of generated graph. The nodes are numbered from 1, the edges from 1.
+/// Kinds:
+/// - FullMesh: every ordered pair of nodes is connected, `nodes * (nodes - 1)` edges as the `nodes_*_full_mesh` samples
+/// - ErdosRenyi: `edges` distinct random pairs of nodes, G(n, m), as the `nodes_*_random` samples
+/// - BarabasiAlbert: scale-free graph, each new node connects to `edges_per_node` nodes chosen by degree
+/// - Star: the first node connected to every other node
+/// - Ring: each node connected to the next one, the last one to the first one
+/// - Tree: `arity`-ary tree filled level by level from the root
+/// - Grid: rows of `columns` nodes, connected to their right and bottom neighbours
+/// - Telco: meshed core, aggregation nodes homed on two cores and access nodes with an optional redundant uplink.
+///   Each core serves `fan_out` aggregation nodes which serve `fan_out` access nodes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "kind", rename_all = "snake_case"))]
+pub enum GraphKind {
+    FullMesh { nodes: u32 },
+    ErdosRenyi { nodes: u32, edges: u32 },
+    BarabasiAlbert { nodes: u32, edges_per_node: u32 },
+    Star { nodes: u32 },
+    Ring { nodes: u32 },
+    Tree { nodes: u32, arity: u32 },
+    Grid { nodes: u32, columns: u32 },
+    Telco { nodes: u32, fan_out: u32 },
+}
+
+/// Edges under construction, numbered in insertion order
+#[derive(Debug, Default)]
+struct EdgeList {
+    edges: Vec<Edge>,
+}
+
+impl EdgeList {
+    fn push(&mut self, source_id: u32, target_id: u32) {
+        self.edges.push(Edge {
+            id: self.edges.len() as u32 + 1,
+            source_id,
+            target_id,
+        });
+    }
+}
+
+impl GraphKind {
+    pub fn get_total_nodes(&self) -> u32 {
+        match *self {
+            GraphKind::FullMesh { nodes }
+            | GraphKind::ErdosRenyi { nodes, .. }
+            | GraphKind::BarabasiAlbert { nodes, .. }
+            | GraphKind::Star { nodes }
+            | GraphKind::Ring { nodes }
+            | GraphKind::Tree { nodes, .. }
+            | GraphKind::Grid { nodes, .. }
+            | GraphKind::Telco { nodes, .. } => nodes,
+        }
+    }
+
+    /// Generate the graph. The seed only matters for the random kinds.
+    pub fn generate(&self, seed: u64) -> GraphLayoutData {
+        let total_nodes = self.get_total_nodes();
+        let mut rng = SeededRng::new(seed);
+        let mut edges = EdgeList::default();
+        match *self {
+            GraphKind::FullMesh { nodes } => {
+                for source in 1..=nodes {
+                    for target in (1..=nodes).filter(|target| *target != source) {
+                        edges.push(source, target);
+                    }
+                }
+            }
+            GraphKind::ErdosRenyi {
+                nodes,
+                edges: total,
+            } => Self::generate_erdos_renyi(nodes, total, &mut rng, &mut edges),
+            GraphKind::BarabasiAlbert {
+                nodes,
+                edges_per_node,
+            } => Self::generate_barabasi_albert(nodes, edges_per_node, &mut rng, &mut edges),
+            GraphKind::Star { nodes } => {
+                for target in 2..=nodes {
+                    edges.push(1, target);
+                }
+            }
+            GraphKind::Ring { nodes } => {
+                for source in 1..nodes {
+                    edges.push(source, source + 1);
+                }
+                if nodes > 2 {
+                    edges.push(nodes, 1);
+                }
+            }
+            GraphKind::Tree { nodes, arity } => {
+                let arity = arity.max(1);
+                for node in 2..=nodes {
+                    edges.push((node - 2) / arity + 1, node);
+                }
+            }
+            GraphKind::Grid { nodes, columns } => {
+                let columns = columns.max(1);
+                for index in 0..nodes {
+                    if (index + 1) % columns != 0 && index + 1 < nodes {
+                        edges.push(index + 1, index + 2);
+                    }
+                    if index + columns < nodes {
+                        edges.push(index + 1, index + columns + 1);
+                    }
+                }
+            }
+            GraphKind::Telco { nodes, fan_out } => {
+                Self::generate_telco(nodes, fan_out, &mut rng, &mut edges)
+            }
+        }
+        GraphLayoutData {
+            nodes: (1..=total_nodes).map(|id| Node { id }).collect(),
+            edges: edges.edges,
+            ..Default::default()
+        }
+    }
+
+    /// Distinct pairs without self loops, at most one edge per pair of nodes
+    fn generate_erdos_renyi(nodes: u32, total: u32, rng: &mut SeededRng, edges: &mut EdgeList) {
+        let max_edges = nodes as u64 * nodes.saturating_sub(1) as u64 / 2;
+        let total = (total as u64).min(max_edges);
+        let mut pairs: HashSet<(u32, u32)> = HashSet::with_capacity(total as usize);
+        while (pairs.len() as u64) < total {
+            let source = rng.below(nodes) + 1;
+            let target = rng.below(nodes) + 1;
+            if source != target && pairs.insert((source.min(target), source.max(target))) {
+                edges.push(source, target);
+            }
+        }
+    }
+
+    /// Starts from a full mesh of `edges_per_node + 1` nodes, the targets are picked from the
+    /// list of the edge ends so the probability follows the degree.
+    fn generate_barabasi_albert(
+        nodes: u32,
+        edges_per_node: u32,
+        rng: &mut SeededRng,
+        edges: &mut EdgeList,
+    ) {
+        let edges_per_node = edges_per_node.max(1);
+        let initial_nodes = (edges_per_node + 1).min(nodes);
+        let mut edge_ends: Vec<u32> = Vec::new();
+        for source in 1..=initial_nodes {
+            for target in (source + 1)..=initial_nodes {
+                edges.push(source, target);
+                edge_ends.extend([source, target]);
+            }
+        }
+        for source in (initial_nodes + 1)..=nodes {
+            let mut targets: Vec<u32> = Vec::with_capacity(edges_per_node as usize);
+            while (targets.len() as u32) < edges_per_node {
+                let target = edge_ends[rng.below(edge_ends.len() as u32) as usize];
+                if !targets.contains(&target) {
+                    targets.push(target);
+                }
+            }
+            for target in targets {
+                edges.push(source, target);
+                edge_ends.extend([source, target]);
+            }
+        }
+    }
+
+    fn generate_telco(nodes: u32, fan_out: u32, rng: &mut SeededRng, edges: &mut EdgeList) {
+        let fan_out = fan_out.max(1);
+        let per_core = 1 + fan_out + fan_out * fan_out;
+        let cores = nodes.div_ceil(per_core).max(2).min(nodes);
+        let aggregations = (cores * fan_out).min(nodes - cores);
+        for source in 1..=cores {
+            for target in (source + 1)..=cores {
+                edges.push(source, target);
+            }
+        }
+        for index in 0..aggregations {
+            let node = cores + 1 + index;
+            let core = index % cores;
+            edges.push(core + 1, node);
+            if cores > 1 {
+                let other = (core + 1 + rng.below(cores - 1)) % cores;
+                edges.push(other + 1, node);
+            }
+        }
+        // Without aggregation nodes the access nodes hang from the cores
+        let (first_uplink, uplinks) = match aggregations {
+            0 => (1, cores),
+            _ => (cores + 1, aggregations),
+        };
+        for index in 0..(nodes - cores - aggregations) {
+            let node = cores + aggregations + 1 + index;
+            let uplink = index % uplinks;
+            edges.push(first_uplink + uplink, node);
+            if uplinks > 1 && rng.chance(0.25) {
+                let other = (uplink + 1 + rng.below(uplinks - 1)) % uplinks;
+                edges.push(first_uplink + other, node);
+            }
+        }
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+pub mod test_generators {
+    use super::*;
+    use crate::Validation;
+
+    fn get_all_kinds(nodes: u32) -> Vec<GraphKind> {
+        vec![
+            GraphKind::FullMesh { nodes },
+            GraphKind::ErdosRenyi {
+                nodes,
+                edges: nodes * 4,
+            },
+            GraphKind::BarabasiAlbert {
+                nodes,
+                edges_per_node: 3,
+            },
+            GraphKind::Star { nodes },
+            GraphKind::Ring { nodes },
+            GraphKind::Tree { nodes, arity: 3 },
+            GraphKind::Grid { nodes, columns: 7 },
+            GraphKind::Telco { nodes, fan_out: 4 },
+        ]
+    }
+
+    #[test]
+    fn test_generators() {
+        for kind in get_all_kinds(50) {
+            let graph = kind.generate(7);
+            assert_eq!(graph.nodes.len(), 50, "{:?}", kind);
+            assert!(
+                Validation::validate(&graph.nodes, &graph.edges).is_ok(),
+                "{:?}",
+                kind
+            );
+            assert!(
+                graph
+                    .edges
+                    .iter()
+                    .all(|edge| edge.source_id != edge.target_id),
+                "{:?}",
+                kind
+            );
+            assert!(
+                graph
+                    .edges
+                    .iter()
+                    .enumerate()
+                    .all(|(index, edge)| edge.id == index as u32 + 1)
+            );
+            // Every node has at least one edge
+            let mut connected: HashSet<u32> = HashSet::new();
+            for edge in graph.edges.iter() {
+                connected.extend([edge.source_id, edge.target_id]);
+            }
+            assert_eq!(connected.len(), 50, "{:?}", kind);
+        }
+        let count = |kind: GraphKind| kind.generate(0).edges.len();
+        assert_eq!(count(GraphKind::FullMesh { nodes: 10 }), 90);
+        assert_eq!(
+            count(GraphKind::ErdosRenyi {
+                nodes: 1000,
+                edges: 4000
+            }),
+            4000
+        );
+        assert_eq!(
+            count(GraphKind::ErdosRenyi {
+                nodes: 4,
+                edges: 10
+            }),
+            6
+        );
+        assert_eq!(
+            count(GraphKind::BarabasiAlbert {
+                nodes: 100,
+                edges_per_node: 2
+            }),
+            3 + 97 * 2
+        );
+        assert_eq!(count(GraphKind::Star { nodes: 10 }), 9);
+        assert_eq!(count(GraphKind::Ring { nodes: 10 }), 10);
+        assert_eq!(
+            count(GraphKind::Tree {
+                nodes: 10,
+                arity: 2
+            }),
+            9
+        );
+        assert_eq!(
+            count(GraphKind::Grid {
+                nodes: 9,
+                columns: 3
+            }),
+            12
+        );
+        assert_eq!(count(GraphKind::Star { nodes: 0 }), 0);
+    }
+
+    #[test]
+    fn test_generators_seed() {
+        for kind in get_all_kinds(200) {
+            let graph = kind.generate(42);
+            let same = kind.generate(42);
+            assert_eq!(
+                format!("{:?}", graph.edges),
+                format!("{:?}", same.edges),
+                "{:?}",
+                kind
+            );
+        }
+        let kind = GraphKind::ErdosRenyi {
+            nodes: 200,
+            edges: 800,
+        };
+        assert_ne!(
+            format!("{:?}", kind.generate(1).edges),
+            format!("{:?}", kind.generate(2).edges)
+        );
+        // The sequence is part of the contract: a change breaks the reproducibility of the benchmarks
+        let mut rng = SeededRng::new(0);
+        assert_eq!(rng.next_u64(), 0xe220_a839_7b1d_cdaf);
+    }
+
+    #[test]
+    fn test_telco() {
+        let graph = GraphKind::Telco {
+            nodes: 52,
+            fan_out: 3,
+        }
+        .generate(0);
+        // 4 cores, 12 aggregation nodes homed on 2 cores, 36 access nodes
+        let degree = |id: u32| {
+            graph
+                .edges
+                .iter()
+                .filter(|edge| edge.source_id == id || edge.target_id == id)
+                .count()
+        };
+        assert_eq!(graph.nodes.len(), 52);
+        assert!(degree(1) > degree(52));
+        assert!((5..=16).all(|id| degree(id) >= 3));
+    }
+}
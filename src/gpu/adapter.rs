@@ -1,32 +1,259 @@
+use crate::gpu::GpuOptions;
+use crate::trace::event;
+use anyhow::anyhow;
+use futures_channel::oneshot;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
 use wgpu::{
-    Adapter, Device, DeviceDescriptor, Instance, InstanceDescriptor, Queue, RequestAdapterOptions,
+    Adapter, Buffer, BufferView, Device, DeviceDescriptor, Features, Instance, InstanceDescriptor,
+    Limits, MapMode, PollType, Queue, RequestAdapterOptions,
 };
-#[derive(Debug)]
+#[cfg(not(target_arch = "wasm32"))]
+use wgpu::{DeviceType, PowerPreference};
+
+/// Sender of a pending `map_read` or `wait_idle`, taken by the wgpu callback or by a poll error
+type Reply = Arc<Mutex<Option<oneshot::Sender<anyhow::Result<()>>>>>;
+
+#[derive(Debug, Clone)]
 pub struct GpuAdapter {
     pub adapter: Adapter,
     pub device: Device,
     pub queue: Queue,
     pub instance: Instance,
+    #[cfg(not(target_arch = "wasm32"))]
+    poller: Poller,
+}
+
+/// Thread polling the device for the pending replies, one per adapter and its clones.
+/// It stops once the last clone of the adapter is dropped.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone)]
+struct Poller {
+    replies: mpsc::Sender<Reply>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Poller {
+    fn new(device: &Device) -> anyhow::Result<Self> {
+        let (replies, receiver) = mpsc::channel::<Reply>();
+        let device = device.clone();
+        std::thread::Builder::new()
+            .name("gpu-poller".to_string())
+            .spawn(move || {
+                // The callbacks of the done work send the replies, a poll error goes to the reply
+                for reply in receiver {
+                    if let Err(error) = device.poll(PollType::wait_indefinitely()) {
+                        GpuAdapter::send(&reply, Err(anyhow!(error.to_string())));
+                    }
+                }
+            })?;
+        Ok(Self { replies })
+    }
 }
 
 impl GpuAdapter {
+    /// Request the default adapter. The buffer size limits are raised to the adapter maximum
+    /// so large graphs fit, the other limits are the wgpu defaults.
     pub async fn new() -> anyhow::Result<Self> {
-        let instance = Instance::new(&InstanceDescriptor::default());
-        let adapter = instance
-            .request_adapter(&RequestAdapterOptions::default())
-            .await?;
+        Self::with_options(&GpuOptions::default()).await
+    }
+
+    /// Request the default adapter with the given device limits, e.g. lower limits to test the splitting.
+    pub async fn with_limits(required_limits: Option<Limits>) -> anyhow::Result<Self> {
+        let options = GpuOptions {
+            required_limits,
+            ..GpuOptions::default()
+        };
+        Self::with_options(&options).await
+    }
+
+    /// Request the adapter and the device selected by the options, see `GpuOptions`.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "gpu.adapter",
+            skip_all,
+            fields(backends = ?options.backends, adapter_name = ?options.adapter_name)
+        )
+    )]
+    pub async fn with_options(options: &GpuOptions) -> anyhow::Result<Self> {
+        let instance = Instance::new(&InstanceDescriptor {
+            backends: options.backends,
+            ..InstanceDescriptor::default()
+        });
+        let adapter = Self::request_adapter(&instance, options).await?;
+        let required_limits = options.required_limits.clone().unwrap_or_else(|| {
+            let adapter_limits = adapter.limits();
+            Limits {
+                max_storage_buffer_binding_size: adapter_limits.max_storage_buffer_binding_size,
+                max_buffer_size: adapter_limits.max_buffer_size,
+                ..Limits::default()
+            }
+        });
         let (device, queue) = adapter
             .request_device(&DeviceDescriptor {
                 label: Some("concentrict-gpu-device"),
+                required_limits,
+                // Used by the stage timings when the adapter supports it, see `LayoutStats`
+                required_features: adapter.features() & Features::TIMESTAMP_QUERY,
                 ..Default::default()
             })
             .await?;
+        event!(
+            info,
+            adapter = %adapter.get_info().name,
+            backend = ?adapter.get_info().backend,
+            timestamps = device.features().contains(Features::TIMESTAMP_QUERY),
+            "gpu adapter selected"
+        );
 
         Ok(Self {
+            #[cfg(not(target_arch = "wasm32"))]
+            poller: Poller::new(&device)?,
             instance,
             adapter,
             device,
             queue,
         })
     }
+
+    /// Without a name filter wgpu picks the adapter, otherwise the best matching adapter of the backends.
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn request_adapter(instance: &Instance, options: &GpuOptions) -> anyhow::Result<Adapter> {
+        if options.adapter_name.is_none() {
+            return Self::request_default_adapter(instance, options).await;
+        }
+        let preferred_type = match options.power_preference {
+            PowerPreference::HighPerformance => Some(DeviceType::DiscreteGpu),
+            PowerPreference::LowPower => Some(DeviceType::IntegratedGpu),
+            PowerPreference::None => None,
+        };
+        let mut adapters: Vec<Adapter> = instance
+            .enumerate_adapters(options.backends)
+            .into_iter()
+            .filter(|adapter| options.is_matching(adapter))
+            .filter(|adapter| {
+                !options.force_fallback_adapter || adapter.get_info().device_type == DeviceType::Cpu
+            })
+            .collect();
+        adapters.sort_by_key(|adapter| Some(adapter.get_info().device_type) != preferred_type);
+        adapters.into_iter().next().ok_or_else(|| {
+            anyhow!(
+                "no gpu adapter matching {:?}",
+                options.adapter_name.as_deref().unwrap_or_default()
+            )
+        })
+    }
+
+    /// The adapters cannot be listed on wasm32, the adapter picked by the browser has to match the name filter.
+    #[cfg(target_arch = "wasm32")]
+    async fn request_adapter(instance: &Instance, options: &GpuOptions) -> anyhow::Result<Adapter> {
+        let adapter = Self::request_default_adapter(instance, options).await?;
+        if !options.is_matching(&adapter) {
+            return Err(anyhow!(
+                "gpu adapter {:?} does not match {:?}",
+                adapter.get_info().name,
+                options.adapter_name.as_deref().unwrap_or_default()
+            ));
+        }
+        Ok(adapter)
+    }
+
+    async fn request_default_adapter(
+        instance: &Instance,
+        options: &GpuOptions,
+    ) -> anyhow::Result<Adapter> {
+        Ok(instance
+            .request_adapter(&RequestAdapterOptions {
+                power_preference: options.power_preference,
+                force_fallback_adapter: options.force_fallback_adapter,
+                compatible_surface: None,
+            })
+            .await?)
+    }
+
+    /// Map the buffer for reading and resolve once the mapping is done.
+    /// The device is polled on the poller thread of the adapter so the caller's executor is not
+    /// blocked. On wasm32 the browser drives the mapping.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "gpu.map_read", level = "debug", skip_all, fields(bytes = buffer.size()))
+    )]
+    pub async fn map_read(&self, buffer: &Buffer) -> anyhow::Result<BufferView> {
+        let (tx, rx) = oneshot::channel();
+        let reply: Reply = Arc::new(Mutex::new(Some(tx)));
+        let callback = reply.clone();
+        buffer.map_async(MapMode::Read, .., move |result| {
+            Self::send(
+                &callback,
+                result.map_err(|error| anyhow!(error.to_string())),
+            );
+        });
+        self.poll(reply)?;
+
+        rx.await.map_err(|error| anyhow!(error.to_string()))??;
+        Ok(buffer.get_mapped_range(..))
+    }
+
+    /// Resolve once the submitted work is done, e.g. to time the dispatches on the host.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "gpu.wait_idle", level = "debug", skip_all)
+    )]
+    pub async fn wait_idle(&self) -> anyhow::Result<()> {
+        let (tx, rx) = oneshot::channel();
+        let reply: Reply = Arc::new(Mutex::new(Some(tx)));
+        let callback = reply.clone();
+        self.queue.on_submitted_work_done(move || {
+            Self::send(&callback, Ok(()));
+        });
+        self.poll(reply)?;
+        rx.await.map_err(|error| anyhow!(error.to_string()))?
+    }
+
+    /// Hand the reply to the poller thread, on wasm32 poll once and return the poll error
+    fn poll(&self, reply: Reply) -> anyhow::Result<()> {
+        #[cfg(not(target_arch = "wasm32"))]
+        self.poller
+            .replies
+            .send(reply)
+            .map_err(|_| anyhow!("the gpu poller thread stopped"))?;
+        #[cfg(target_arch = "wasm32")]
+        {
+            drop(reply);
+            self.device.poll(PollType::Poll)?;
+        }
+        Ok(())
+    }
+
+    /// The first of the callback and the poll error resolves the reply
+    fn send(reply: &Reply, result: anyhow::Result<()>) {
+        let sender = reply.lock().ok().and_then(|mut sender| sender.take());
+        if let Some(sender) = sender {
+            let _ = sender.send(result);
+        }
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+pub mod test_gpu_adapter {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_wait_idle() {
+        let adapter = GpuAdapter::new().await;
+        assert!(adapter.is_ok(), "{:?}", adapter.err());
+        let adapter = adapter.unwrap();
+        // The clones share the poller thread, the concurrent replies all resolve
+        let waits: Vec<_> = (0..8)
+            .map(|_| {
+                let adapter = adapter.clone();
+                tokio::spawn(async move { adapter.wait_idle().await })
+            })
+            .collect();
+        for wait in waits {
+            wait.await.unwrap().unwrap();
+        }
+    }
 }
@@ -1,48 +1,96 @@
-use crate::Timer;
 use crate::entities::{Edge, Node, NodePositionData};
+use crate::gpu::limits::{Dispatch, WORKGROUP_SIZE};
 use crate::gpu::node_positions::{NodePositions, NodePositionsResult};
 use crate::gpu::normalize::{Normalize, NormalizeResult};
-use crate::gpu::{NodeConnections, NodeConnectionsResult};
+use crate::gpu::{GpuAdapter, NodeConnections, NodeConnectionsResult};
+use crate::{
+    BoundingBox, ComputingConfig, LayoutOptions, LayoutStats, Levels, Pinning, Timer, Viewport,
+};
+#[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
-use tokio::time::Instant;
+use web_time::Instant;
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
-pub struct GpuConcentric {
+/// GPU computing of the layout. The nodes and edges are borrowed, not copied.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct GpuConcentric<'a> {
     pub timer: Timer,
-    pub nodes: Vec<Node>,
-    pub edges: Vec<Edge>,
+    pub stats: LayoutStats,
+    pub nodes: &'a [Node],
+    pub edges: &'a [Edge],
     pub node_connections: NodeConnectionsResult,
     pub normalized_values: NormalizeResult,
     pub node_positions: NodePositionsResult,
-    pub default_cx: Option<f32>,
-    pub default_cy: Option<f32>,
+    pub options: LayoutOptions,
+    /// Adapter shared by the stages. A new adapter is requested with `options.gpu` when not set.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub adapter: Option<GpuAdapter>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct GpuConcetricData {
     pub nodes: Vec<Node>,
     pub edges: Vec<Edge>,
     pub coordinates: Vec<NodePositionData>,
 }
 
-impl GpuConcentric {
-    pub fn new(nodes: &Vec<Node>, edges: &Vec<Edge>, cx: &Option<f32>, cy: &Option<f32>) -> Self {
+impl<'a> GpuConcentric<'a> {
+    pub fn new(nodes: &'a [Node], edges: &'a [Edge], options: &LayoutOptions) -> Self {
         Self {
-            nodes: nodes.to_owned(),
-            edges: edges.to_owned(),
-            default_cx: cx.to_owned(),
-            default_cy: cy.to_owned(),
+            nodes,
+            edges,
+            options: options.clone(),
             ..Default::default()
         }
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "gpu.layout",
+            skip_all,
+            fields(nodes = self.nodes.len(), edges = self.edges.len())
+        )
+    )]
     pub async fn get(&mut self) -> anyhow::Result<Vec<NodePositionData>> {
         let timer = Instant::now();
-        self.count_node_connections().await?;
-        self.normalize_node_connections().await?;
-        self.calculate_node_positions().await?;
+        self.options.validate()?;
+        Levels::validate(&self.options.levels, self.nodes)?;
+        Pinning::validate(&self.options.pinned, self.nodes)?;
+        let adapter = match &self.adapter {
+            Some(adapter) => adapter.clone(),
+            None => GpuAdapter::with_options(&self.options.gpu).await?,
+        };
+        let limits = adapter.device.limits();
+        let dispatch = Dispatch::new(self.nodes.len(), &limits)?;
+        self.stats = LayoutStats {
+            backend: ComputingConfig::Gpu,
+            threads: (dispatch.x * dispatch.y * WORKGROUP_SIZE) as usize,
+            nodes: self.nodes.len(),
+            edges: self.edges.len(),
+            gpu_timestamps: adapter
+                .device
+                .features()
+                .contains(wgpu::Features::TIMESTAMP_QUERY),
+            ..Default::default()
+        };
+        self.count_node_connections(&adapter).await?;
+        self.normalize_node_connections(&adapter).await?;
+        self.calculate_node_positions(&adapter).await?;
+        self.stats.fit_scale = Viewport::get_scale(&self.node_positions.gpu_data, &self.options);
+        Viewport::fit(&mut self.node_positions.gpu_data, &self.options)?;
+        // The pinned nodes keep their coordinates, they are not moved into the bounding box
+        Pinning::apply(
+            &mut self.node_positions.gpu_data,
+            &self.options,
+            self.stats.fit_scale.unwrap_or(1.0),
+        )?;
+        self.stats.bounding_box = BoundingBox::from_positions(&self.node_positions.gpu_data);
         let elapsed = timer.elapsed();
         let data = self.node_positions.gpu_data.to_owned();
+        self.stats.rings = LayoutStats::count_rings(&data);
+        self.stats.total_micros = elapsed.as_micros() as u64;
         self.timer = Timer {
             micros: Some(elapsed.as_micros()),
             millis: Some(elapsed.as_millis()),
@@ -52,30 +100,46 @@ impl GpuConcentric {
     }
 
     /// 1. Count the number of edges/paths per node
-    async fn count_node_connections(&mut self) -> anyhow::Result<()> {
-        let node_connections = NodeConnections::new(&self.nodes, &self.edges).await?;
-        self.node_connections = node_connections.execute().await?;
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "gpu.degree", skip_all)
+    )]
+    async fn count_node_connections(&mut self, adapter: &GpuAdapter) -> anyhow::Result<()> {
+        let node_connections = NodeConnections::new(adapter, self.nodes, self.edges);
+        (self.node_connections, self.stats.degree) = node_connections.execute_timed().await?;
         Ok(())
     }
 
-    /// 2. Normalize Node Connections
-    async fn normalize_node_connections(&mut self) -> anyhow::Result<()> {
-        let normalize = Normalize::new(&self.nodes, &self.edges, &self.node_connections).await?;
-        self.normalized_values = normalize.execute().await?;
+    /// 2. Normalize Node Connections, ordered from the centre to the outer rings
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "gpu.normalize", skip_all)
+    )]
+    async fn normalize_node_connections(&mut self, adapter: &GpuAdapter) -> anyhow::Result<()> {
+        let normalize = Normalize::new(adapter, self.nodes, self.edges, &self.node_connections);
+        let (mut result, normalize_timing, sort_timing) = normalize.execute_timed().await?;
+        self.options.order.apply(&mut result.gpu_data);
+        Levels::arrange(&mut result.gpu_data, &self.options)?;
+        self.stats.normalize = normalize_timing;
+        self.stats.sort = sort_timing;
+        self.normalized_values = result;
         Ok(())
     }
 
     /// 3. Calculate Node Positions (Ring, Angle, and Coordinates)
-    async fn calculate_node_positions(&mut self) -> anyhow::Result<()> {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "gpu.positions", skip_all)
+    )]
+    async fn calculate_node_positions(&mut self, adapter: &GpuAdapter) -> anyhow::Result<()> {
         let node_positions = NodePositions::new(
-            &self.nodes,
-            &self.edges,
-            self.normalized_values.clone(),
-            self.default_cx,
-            self.default_cy,
-        )
-        .await?;
-        self.node_positions = node_positions.execute().await?;
+            adapter,
+            self.nodes,
+            self.edges,
+            &self.normalized_values,
+            &self.options,
+        )?;
+        (self.node_positions, self.stats.positions) = node_positions.execute_timed().await?;
         Ok(())
     }
 }
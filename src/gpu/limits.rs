@@ -0,0 +1,207 @@
+use std::fmt::{Display, Formatter};
+use wgpu::Limits;
+
+/// Workgroup size of the compute shaders, see gpu/wgsl
+pub const WORKGROUP_SIZE: u32 = 64;
+
+/// The graph does not fit the limits of the gpu device
+/// Kinds:
+/// - TooManyWorkgroups: more invocations than a 2D dispatch of `max_compute_workgroups_per_dimension` allows
+/// - BufferTooLarge: a buffer is larger than `max_storage_buffer_binding_size` or `max_buffer_size`
+#[derive(Debug, Clone, PartialEq)]
+pub enum GpuLimitError {
+    TooManyWorkgroups {
+        invocations: u64,
+        max_workgroups_per_dimension: u32,
+    },
+    BufferTooLarge {
+        label: &'static str,
+        size: u64,
+        max_size: u64,
+    },
+}
+
+impl Display for GpuLimitError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GpuLimitError::TooManyWorkgroups {
+                invocations,
+                max_workgroups_per_dimension,
+            } => write!(
+                f,
+                "{} gpu invocations exceed the dispatch limit of {} workgroups per dimension",
+                invocations, max_workgroups_per_dimension
+            ),
+            GpuLimitError::BufferTooLarge {
+                label,
+                size,
+                max_size,
+            } => write!(
+                f,
+                "gpu buffer {} of {} bytes exceeds the device limit of {} bytes",
+                label, size, max_size
+            ),
+        }
+    }
+}
+
+impl std::error::Error for GpuLimitError {}
+
+/// Largest size of a buffer bound as storage
+pub fn get_max_storage_buffer_size(limits: &Limits) -> u64 {
+    (limits.max_storage_buffer_binding_size as u64).min(limits.max_buffer_size)
+}
+
+/// Check that a storage buffer of `size` bytes can be created and bound
+pub fn check_storage_buffer_size(
+    label: &'static str,
+    size: u64,
+    limits: &Limits,
+) -> Result<(), GpuLimitError> {
+    let max_size = get_max_storage_buffer_size(limits);
+    if size > max_size {
+        return Err(GpuLimitError::BufferTooLarge {
+            label,
+            size,
+            max_size,
+        });
+    }
+    Ok(())
+}
+
+/// Number of items of `item_size` bytes per storage buffer when a list is split across buffers
+pub fn get_items_per_buffer(item_size: usize, limits: &Limits) -> usize {
+    (get_max_storage_buffer_size(limits) as usize / item_size).max(1)
+}
+
+/// Number of workgroups to dispatch.
+/// Above `max_compute_workgroups_per_dimension` the workgroups are spread on a 2D grid,
+/// the shaders rebuild the index as `global_id.x + global_id.y * num_workgroups.x * WORKGROUP_SIZE`
+/// and skip the invocations past the end of the data.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Dispatch {
+    pub x: u32,
+    pub y: u32,
+}
+
+impl Dispatch {
+    pub fn new(invocations: usize, limits: &Limits) -> Result<Self, GpuLimitError> {
+        let max_per_dimension = limits.max_compute_workgroups_per_dimension as u64;
+        let workgroups = (invocations as u64).div_ceil(WORKGROUP_SIZE as u64).max(1);
+        if workgroups <= max_per_dimension {
+            return Ok(Self {
+                x: workgroups as u32,
+                y: 1,
+            });
+        }
+        let y = workgroups.div_ceil(max_per_dimension);
+        if y > max_per_dimension {
+            return Err(GpuLimitError::TooManyWorkgroups {
+                invocations: invocations as u64,
+                max_workgroups_per_dimension: limits.max_compute_workgroups_per_dimension,
+            });
+        }
+        Ok(Self {
+            x: max_per_dimension as u32,
+            y: y as u32,
+        })
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+pub mod test_gpu_limits {
+    use super::*;
+    use crate::LayoutOptions;
+    use crate::cpu::CpuConcentric;
+    use crate::entities::{Edge, Node};
+    use crate::gpu::{GpuAdapter, GpuConcentric};
+
+    fn get_sample_graph(total_nodes: u32, total_edges: u32) -> (Vec<Node>, Vec<Edge>) {
+        let nodes = (1..=total_nodes).map(|id| Node { id }).collect();
+        let edges = (0..total_edges)
+            .map(|id| Edge {
+                id,
+                source_id: id % 7 + 1,
+                target_id: (id * 13) % total_nodes + 1,
+            })
+            .collect();
+        (nodes, edges)
+    }
+
+    /// Limits small enough to split the edges in 3 buffers and to dispatch on 2 dimensions
+    fn get_low_limits() -> Limits {
+        Limits {
+            max_storage_buffer_binding_size: 8192,
+            max_compute_workgroups_per_dimension: 2,
+            ..Limits::default()
+        }
+    }
+
+    #[test]
+    fn test_dispatch() {
+        let limits = Limits::default();
+        assert_eq!(Dispatch::new(0, &limits).unwrap(), Dispatch { x: 1, y: 1 });
+        assert_eq!(
+            Dispatch::new(100, &limits).unwrap(),
+            Dispatch { x: 2, y: 1 }
+        );
+        // 5M nodes: 78125 workgroups > 65535
+        let dispatch = Dispatch::new(5_000_000, &limits).unwrap();
+        assert_eq!(dispatch, Dispatch { x: 65535, y: 2 });
+        assert!(dispatch.x as u64 * dispatch.y as u64 * WORKGROUP_SIZE as u64 >= 5_000_000);
+
+        let limits = Limits {
+            max_compute_workgroups_per_dimension: 4,
+            ..Limits::default()
+        };
+        assert_eq!(
+            Dispatch::new(300, &limits).unwrap(),
+            Dispatch { x: 4, y: 2 }
+        );
+        assert!(Dispatch::new(64 * 17, &limits).is_err());
+    }
+
+    #[test]
+    fn test_storage_buffer_size() {
+        let limits = Limits {
+            max_storage_buffer_binding_size: 1024,
+            ..Limits::default()
+        };
+        assert!(check_storage_buffer_size("test", 1024, &limits).is_ok());
+        assert!(check_storage_buffer_size("test", 1025, &limits).is_err());
+        assert_eq!(get_items_per_buffer(12, &limits), 85);
+    }
+
+    #[tokio::test]
+    async fn test_gpu_concentric_low_limits() {
+        let (nodes, edges) = get_sample_graph(140, 2000);
+        let adapter = GpuAdapter::with_limits(Some(get_low_limits())).await;
+        assert!(adapter.is_ok(), "{:?}", adapter.err());
+        let mut layout = GpuConcentric::new(&nodes, &edges, &LayoutOptions::default());
+        layout.adapter = adapter.ok();
+        let result = layout.get().await;
+        assert!(result.is_ok(), "{:?}", result.err());
+        let expected = CpuConcentric::new(&nodes, &edges, &LayoutOptions::default())
+            .get()
+            .unwrap();
+        let result = result.unwrap();
+        assert_eq!(result.len(), expected.len());
+        for (gpu, cpu) in result.iter().zip(expected.iter()) {
+            assert_eq!((gpu.node_id, gpu.index), (cpu.node_id, cpu.index));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_gpu_concentric_over_limits() {
+        let (nodes, edges) = get_sample_graph(250, 10);
+        let adapter = GpuAdapter::with_limits(Some(get_low_limits())).await;
+        assert!(adapter.is_ok(), "{:?}", adapter.err());
+        let mut layout = GpuConcentric::new(&nodes, &edges, &LayoutOptions::default());
+        layout.adapter = adapter.ok();
+        let error = layout.get().await.unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<GpuLimitError>(),
+            Some(GpuLimitError::BufferTooLarge { .. })
+        ));
+    }
+}
@@ -1,8 +1,14 @@
 pub mod adapter;
 pub mod concentric;
+pub mod limits;
 pub mod node_connections;
 pub mod node_positions;
+pub mod options;
+pub mod timestamps;
 pub use adapter::GpuAdapter;
 pub use concentric::GpuConcentric;
+pub use limits::GpuLimitError;
 pub use node_connections::{NodeConnections, NodeConnectionsResult};
+pub use options::GpuOptions;
+pub use wgpu::{Backends, Limits, PowerPreference};
 pub mod normalize;
@@ -1,78 +1,117 @@
 use crate::{
+    StageTiming,
     entities::{Edge, Node},
     gpu::GpuAdapter,
+    gpu::limits::{Dispatch, check_storage_buffer_size, get_items_per_buffer},
+    gpu::timestamps::GpuTimestamps,
+    trace::event,
 };
-use anyhow::anyhow;
 use bytemuck::{Pod, Zeroable};
+#[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
+use web_time::Instant;
 use wgpu::{
-    BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor, BindGroupLayoutEntry,
-    BindingType, Buffer, BufferBindingType, BufferUsages, BufferView, ComputePassDescriptor,
-    ComputePipelineDescriptor, PipelineCompilationOptions, PipelineLayoutDescriptor, ShaderStages,
-    include_wgsl,
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingType, Buffer, BufferBindingType, BufferUsages,
+    ComputePassDescriptor, ComputePipelineDescriptor, PipelineCompilationOptions,
+    PipelineLayoutDescriptor, ShaderStages, include_wgsl,
     util::{BufferInitDescriptor, DeviceExt},
-    wgt::{BufferDescriptor, CommandEncoderDescriptor, PollType},
+    wgt::{BufferDescriptor, CommandEncoderDescriptor},
 };
 
-#[derive(Debug, Copy, Clone, Pod, Zeroable, Serialize, Deserialize)]
+#[derive(Debug, Copy, Clone, Pod, Zeroable)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[repr(C)]
 pub struct GpuNodeConnectionValue {
     pub node_id: u32,
     pub total: u32,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct NodeConnectionsResult {
     pub gpu_data: Vec<GpuNodeConnectionValue>,
     pub max_degree: u32,
     pub min_degree: u32,
 }
 #[derive(Debug)]
-pub struct NodeConnections {
-    pub adapter: GpuAdapter,
-    pub nodes: Vec<Node>,
-    pub edges: Vec<Edge>,
+pub struct NodeConnections<'a> {
+    pub adapter: &'a GpuAdapter,
+    pub nodes: &'a [Node],
+    pub edges: &'a [Edge],
 }
 
 #[derive(Debug)]
 pub struct BufferData {
     nodes_buffer: Buffer,
-    edges_buffer: Buffer,
+    edges_buffers: Vec<Buffer>,
     inner_min_max_buffer: Buffer,
     inner_result_buffer: Buffer,
     outer_result_buffer: Buffer,
     outer_min_max_buffer: Buffer,
 }
 
-impl NodeConnections {
-    pub async fn new(nodes: &Vec<Node>, edges: &Vec<Edge>) -> anyhow::Result<Self> {
-        let adapter = GpuAdapter::new().await?;
-        Ok(Self {
+impl<'a> NodeConnections<'a> {
+    pub fn new(adapter: &'a GpuAdapter, nodes: &'a [Node], edges: &'a [Edge]) -> Self {
+        Self {
             adapter,
-            nodes: nodes.to_owned(),
-            edges: edges.to_owned(),
-        })
+            nodes,
+            edges,
+        }
     }
 
-    pub async fn get_buffer_data(&self) -> BufferData {
+    /// The edges are split in buffers fitting the storage binding size limit, one pass per buffer.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "gpu.upload",
+            level = "debug",
+            skip_all,
+            fields(stage = "node_connections")
+        )
+    )]
+    pub async fn get_buffer_data(&self) -> anyhow::Result<BufferData> {
         let device = &self.adapter.device;
+        let limits = device.limits();
+        let result_size = (std::mem::size_of::<GpuNodeConnectionValue>() * self.nodes.len()) as u64;
+        check_storage_buffer_size(
+            "node-connections-nodes-data",
+            size_of_val(self.nodes) as u64,
+            &limits,
+        )?;
+        check_storage_buffer_size("node-connections-result", result_size, &limits)?;
+
         let nodes_buffer = device.create_buffer_init(&BufferInitDescriptor {
             label: Some("node-connections-nodes-data"),
-            contents: bytemuck::cast_slice(&self.nodes),
-            usage: BufferUsages::COPY_DST | BufferUsages::STORAGE,
-        });
-        let edges_buffer = device.create_buffer_init(&BufferInitDescriptor {
-            label: Some("node-connections-edges-data"),
-            contents: bytemuck::cast_slice(&self.edges),
+            contents: bytemuck::cast_slice(self.nodes),
             usage: BufferUsages::COPY_DST | BufferUsages::STORAGE,
         });
+        // A binding cannot be empty, a graph without edges gets an edge matching no node
+        let no_edges = [Edge {
+            id: 0,
+            source_id: u32::MAX,
+            target_id: u32::MAX,
+        }];
+        let edges = if self.edges.is_empty() {
+            &no_edges[..]
+        } else {
+            self.edges
+        };
+        let edges_buffers = edges
+            .chunks(get_items_per_buffer(std::mem::size_of::<Edge>(), &limits))
+            .map(|chunk| {
+                device.create_buffer_init(&BufferInitDescriptor {
+                    label: Some("node-connections-edges-data"),
+                    contents: bytemuck::cast_slice(chunk),
+                    usage: BufferUsages::COPY_DST | BufferUsages::STORAGE,
+                })
+            })
+            .collect();
         let inner_min_max_buffer = device.create_buffer_init(&BufferInitDescriptor {
             label: Some("node-connections-inner-min-max-data"),
             contents: bytemuck::cast_slice(&[0u32; 2]),
             usage: BufferUsages::COPY_SRC | BufferUsages::STORAGE,
         });
-        let result_size = (std::mem::size_of::<GpuNodeConnectionValue>() * self.nodes.len()) as u64;
-
         let inner_result_buffer = device.create_buffer(&BufferDescriptor {
             label: Some("node-connections-innert-result"),
             size: result_size,
@@ -91,42 +130,27 @@ impl NodeConnections {
             usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
             mapped_at_creation: false,
         });
-        BufferData {
+        Ok(BufferData {
             nodes_buffer,
-            edges_buffer,
+            edges_buffers,
             inner_min_max_buffer,
             inner_result_buffer,
             outer_result_buffer,
             outer_min_max_buffer,
-        }
+        })
     }
 
-    async fn get_buffer_view(&self, buffer_data: &Buffer) -> anyhow::Result<BufferView> {
-        let device = &self.adapter.device;
-        let (tx, rx) = crossbeam::channel::bounded(1);
-        buffer_data.map_async(wgpu::MapMode::Read, .., move |result| {
-            tx.send(result)
-                .expect("unable to send node connection result")
-        });
-        device.poll(PollType::wait_indefinitely())?;
-
-        match rx.recv() {
-            Ok(received) => {
-                if let Err(error) = received {
-                    return Err(anyhow!(error.to_string()));
-                }
-            }
-            Err(error) => {
-                return Err(anyhow!(error.to_string()));
-            }
-        }
-        let data = buffer_data.get_mapped_range(..);
-        Ok(data)
+    pub async fn execute(&self) -> anyhow::Result<NodeConnectionsResult> {
+        Ok(self.execute_timed().await?.0)
     }
 
-    pub async fn execute(&self) -> anyhow::Result<NodeConnectionsResult> {
-        let buffer_data = self.get_buffer_data().await;
+    /// Execute and measure the upload, dispatch and readback, see `StageTiming`
+    pub async fn execute_timed(&self) -> anyhow::Result<(NodeConnectionsResult, StageTiming)> {
+        let timer = Instant::now();
+        let buffer_data = self.get_buffer_data().await?;
+        let upload = timer.elapsed();
         let device = &self.adapter.device;
+        let dispatch = Dispatch::new(self.nodes.len(), &device.limits())?;
         let data_bg_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
             label: Some("node-connections-data-bg-layout"),
             entries: &[
@@ -177,31 +201,40 @@ impl NodeConnections {
             bind_group_layouts: &[&data_bg_layout],
             push_constant_ranges: &[],
         });
-        let data_bg_group = device.create_bind_group(&BindGroupDescriptor {
-            label: Some("nodes-connections-bg-group"),
-            layout: &data_bg_layout,
-            entries: &[
-                BindGroupEntry {
-                    binding: 0,
-                    resource: buffer_data.nodes_buffer.as_entire_binding(),
-                },
-                BindGroupEntry {
-                    binding: 1,
-                    resource: buffer_data.edges_buffer.as_entire_binding(),
-                },
-                BindGroupEntry {
-                    binding: 2,
-                    resource: buffer_data.inner_result_buffer.as_entire_binding(),
-                },
-                BindGroupEntry {
-                    binding: 3,
-                    resource: buffer_data.inner_min_max_buffer.as_entire_binding(),
-                },
-            ],
-        });
+        let data_bg_groups: Vec<BindGroup> = buffer_data
+            .edges_buffers
+            .iter()
+            .map(|edges_buffer| {
+                device.create_bind_group(&BindGroupDescriptor {
+                    label: Some("nodes-connections-bg-group"),
+                    layout: &data_bg_layout,
+                    entries: &[
+                        BindGroupEntry {
+                            binding: 0,
+                            resource: buffer_data.nodes_buffer.as_entire_binding(),
+                        },
+                        BindGroupEntry {
+                            binding: 1,
+                            resource: edges_buffer.as_entire_binding(),
+                        },
+                        BindGroupEntry {
+                            binding: 2,
+                            resource: buffer_data.inner_result_buffer.as_entire_binding(),
+                        },
+                        BindGroupEntry {
+                            binding: 3,
+                            resource: buffer_data.inner_min_max_buffer.as_entire_binding(),
+                        },
+                    ],
+                })
+            })
+            .collect();
         let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
             label: Some("node-connections-encoder"),
         });
+        let timestamps = GpuTimestamps::new(self.adapter, "node-connections-timestamps", 2);
+        let last_pass = data_bg_groups.len() + 1;
+        let mut pass = 0;
         for entry_point in &["get_connections", "get_min", "get_max"] {
             let compute_pipeline_label =
                 format!("node-connections-{}-compute-pipeline", entry_point);
@@ -210,20 +243,30 @@ impl NodeConnections {
                 layout: Some(&data_pipeline_layout),
                 // layout: None,
                 module: &device.create_shader_module(include_wgsl!("wgsl/connections.wgsl")),
-                entry_point: Some(&entry_point),
+                entry_point: Some(entry_point),
                 compilation_options: PipelineCompilationOptions::default(),
                 cache: Default::default(),
             });
-            {
+            // The connections are accumulated over the edge buffers, min and max need a single pass
+            let data_bg_groups = if *entry_point == "get_connections" {
+                &data_bg_groups[..]
+            } else {
+                &data_bg_groups[..1]
+            };
+            for data_bg_group in data_bg_groups {
                 let compute_pass_label = format!("node-connections-{}-compute-pass", entry_point);
                 let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
                     label: Some(&compute_pass_label),
-                    ..Default::default()
+                    timestamp_writes: GpuTimestamps::pass_writes(
+                        timestamps.as_ref(),
+                        (pass == 0).then_some(0),
+                        (pass == last_pass).then_some(1),
+                    ),
                 });
-                let num_dispatchers = self.nodes.len().div_ceil(64) as u32 + 10;
-                compute_pass.set_bind_group(0, &data_bg_group, &[]);
+                compute_pass.set_bind_group(0, data_bg_group, &[]);
                 compute_pass.set_pipeline(&compute_pipeline);
-                compute_pass.dispatch_workgroups(num_dispatchers, 1, 1);
+                compute_pass.dispatch_workgroups(dispatch.x, dispatch.y, 1);
+                pass += 1;
             }
         }
         encoder.copy_buffer_to_buffer(
@@ -240,15 +283,31 @@ impl NodeConnections {
             0,
             buffer_data.inner_min_max_buffer.size(),
         );
+        if let Some(timestamps) = &timestamps {
+            timestamps.resolve(&mut encoder);
+        }
+        let dispatch_timer = Instant::now();
+        event!(
+            debug,
+            passes = last_pass + 1,
+            edge_buffers = buffer_data.edges_buffers.len(),
+            workgroups = ?(dispatch.x, dispatch.y),
+            "node connections submitted"
+        );
         self.adapter.queue.submit([encoder.finish()]);
+        self.adapter.wait_idle().await?;
+        let mut dispatch_micros = dispatch_timer.elapsed().as_micros() as u64;
+        let readback_timer = Instant::now();
         let result: NodeConnectionsResult = {
             let buffered_data = self
-                .get_buffer_view(&buffer_data.outer_result_buffer)
+                .adapter
+                .map_read(&buffer_data.outer_result_buffer)
                 .await?;
             let gpu_data: &[GpuNodeConnectionValue] = bytemuck::cast_slice(&buffered_data);
 
             let buffered_data = self
-                .get_buffer_view(&buffer_data.outer_min_max_buffer)
+                .adapter
+                .map_read(&buffer_data.outer_min_max_buffer)
                 .await?;
             let min_max: &[u32] = bytemuck::cast_slice(&buffered_data);
             NodeConnectionsResult {
@@ -258,32 +317,36 @@ impl NodeConnections {
             }
         };
         buffer_data.outer_result_buffer.unmap();
-        Ok(result)
+        buffer_data.outer_min_max_buffer.unmap();
+        let readback = readback_timer.elapsed();
+        if let Some(timestamps) = &timestamps {
+            dispatch_micros =
+                GpuTimestamps::get_micros(&timestamps.read_nanos(self.adapter).await?, 0, 1);
+        }
+        let timing = StageTiming {
+            micros: timer.elapsed().as_micros() as u64,
+            upload_micros: Some(upload.as_micros() as u64),
+            dispatch_micros: Some(dispatch_micros),
+            readback_micros: Some(readback.as_micros() as u64),
+        };
+        Ok((result, timing))
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "serde", not(target_arch = "wasm32")))]
 pub mod test_gpu_node_connections {
     use super::*;
+    use crate::generators::GraphKind;
     use crate::gpu::node_connections::NodeConnections;
-    use serde::Deserialize;
 
     #[tokio::test]
     async fn test_node_connections() {
-        #[derive(Debug, Clone, Deserialize)]
-        struct SampleData {
-            nodes: Vec<Node>,
-            edges: Vec<Edge>,
-        }
-
-        let reader = std::fs::File::options()
-            .read(true)
-            .open("storage/sample-data/nodes_100_full_mesh.json")
-            .unwrap();
-        let sample_data = serde_json::from_reader::<_, SampleData>(reader).unwrap();
-        let node_connections = NodeConnections::new(&sample_data.nodes, &sample_data.edges).await;
-        assert!(node_connections.is_ok(), "{:?}", node_connections.err());
-        let node_connections = node_connections.unwrap();
+        let sample_data = GraphKind::FullMesh { nodes: 100 }.generate(0);
+        let adapter = GpuAdapter::new().await;
+        assert!(adapter.is_ok(), "{:?}", adapter.err());
+        let adapter = adapter.unwrap();
+        let node_connections =
+            NodeConnections::new(&adapter, &sample_data.nodes, &sample_data.edges);
         let result = node_connections.execute().await;
         assert!(result.is_ok(), "{:?}", result.err());
         let result = result.unwrap();
@@ -1,68 +1,144 @@
-use anyhow::anyhow;
+use bytemuck::{Pod, Zeroable};
+#[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 use wgpu::{
     BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor, BindGroupLayoutEntry,
-    BindingType, Buffer, BufferBindingType, BufferDescriptor, BufferUsages, BufferView,
+    BindingType, Buffer, BufferBindingType, BufferDescriptor, BufferUsages,
     CommandEncoderDescriptor, ComputePassDescriptor, ComputePipelineDescriptor,
     PipelineCompilationOptions, PipelineLayoutDescriptor, ShaderStages, include_wgsl,
     util::{BufferInitDescriptor, DeviceExt},
-    wgt::PollType,
 };
 
 use crate::{
+    LayoutMode, LayoutOptions, Levels, RingGeometry, StageTiming,
     entities::{Edge, Node, NodePositionData, RingCapacity},
+    gpu::limits::{Dispatch, check_storage_buffer_size},
+    gpu::timestamps::GpuTimestamps,
     gpu::{GpuAdapter, normalize::NormalizeResult},
+    trace::event,
 };
+use web_time::Instant;
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+/// `NodePositionData` as written by the kernels, `pinned` is 0 or 1
+#[derive(Debug, Copy, Clone, Default, Pod, Zeroable)]
+#[repr(C)]
+pub struct GpuNodePositionData {
+    pub index: u32,
+    pub radius: u32,
+    pub angle_degree: f32,
+    pub angle_radian: f32,
+    pub cx: f32,
+    pub cy: f32,
+    pub x: f32,
+    pub y: f32,
+    pub node_id: u32,
+    pub cz: f32,
+    pub z: f32,
+    pub polar_degree: f32,
+    pub azimuth_degree: f32,
+    pub pinned: u32,
+}
+
+impl From<GpuNodePositionData> for NodePositionData {
+    fn from(value: GpuNodePositionData) -> Self {
+        NodePositionData {
+            index: value.index,
+            radius: value.radius,
+            angle_degree: value.angle_degree,
+            angle_radian: value.angle_radian,
+            cx: value.cx,
+            cy: value.cy,
+            x: value.x,
+            y: value.y,
+            node_id: value.node_id,
+            cz: value.cz,
+            z: value.z,
+            polar_degree: value.polar_degree,
+            azimuth_degree: value.azimuth_degree,
+            pinned: value.pinned != 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct NodePositionsResult {
     pub gpu_data: Vec<NodePositionData>,
 }
 #[derive(Debug)]
-pub struct NodePositions {
-    pub adapter: GpuAdapter,
-    pub normalize_result: NormalizeResult,
+pub struct NodePositions<'a> {
+    pub adapter: &'a GpuAdapter,
+    pub normalize_result: &'a NormalizeResult,
     pub ring_capacity: Vec<RingCapacity>,
     pub cx: f32,
     pub cy: f32,
-    pub nodes: Vec<Node>,
-    pub edges: Vec<Edge>,
+    pub cz: f32,
+    pub start_angle: f32,
+    pub ring_offsets: Vec<f32>,
+    pub geometry: RingGeometry,
+    pub mode: LayoutMode,
+    pub min_node_spacing: f32,
+    pub nodes: &'a [Node],
+    pub edges: &'a [Edge],
 }
 
 #[derive(Debug)]
 pub struct BufferData {
     pub normalize_gpu_data_buffer: Buffer,
     pub ring_capacity_buffer: Buffer,
+    pub ring_offsets_buffer: Buffer,
     pub inner_result_buffer: Buffer,
-    pub cx_cy_buffer: Buffer,
+    pub settings_buffer: Buffer,
     pub outer_result_buffer: Buffer,
 }
 
-impl NodePositions {
-    pub async fn new(
-        nodes: &Vec<Node>,
-        edges: &Vec<Edge>,
-        normalize_result: NormalizeResult,
-        cx: Option<f32>,
-        cy: Option<f32>,
+impl<'a> NodePositions<'a> {
+    pub fn new(
+        adapter: &'a GpuAdapter,
+        nodes: &'a [Node],
+        edges: &'a [Edge],
+        normalize_result: &'a NormalizeResult,
+        options: &LayoutOptions,
     ) -> anyhow::Result<Self> {
-        let adapter = GpuAdapter::new().await?;
-        let ring_capacity = RingCapacity::generate(nodes.len() as u32, Some(20));
+        let ring_capacity = Levels::get_ring_capacity(&normalize_result.gpu_data, options)?;
+        let ring_offsets = options.stagger.get_offsets(&ring_capacity);
         Ok(Self {
             adapter,
             ring_capacity,
+            ring_offsets,
+            geometry: options.get_ring_geometry(),
+            mode: options.mode,
+            min_node_spacing: options.min_node_spacing,
             normalize_result,
-            cx: cx.unwrap_or(0.0),
-            cy: cy.unwrap_or(0.0),
-            nodes: nodes.to_owned(),
-            edges: edges.to_owned(),
+            cx: options.cx,
+            cy: options.cy,
+            cz: options.cz,
+            start_angle: options.start_angle,
+            nodes,
+            edges,
         })
     }
 
-    pub async fn get_buffer_data(&self) -> BufferData {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "gpu.upload",
+            level = "debug",
+            skip_all,
+            fields(stage = "node_positions")
+        )
+    )]
+    pub async fn get_buffer_data(&self) -> anyhow::Result<BufferData> {
         let device = &self.adapter.device;
+        let limits = device.limits();
         let total_nodes = self.nodes.len();
-        let result_size = (std::mem::size_of::<NodePositionData>() * total_nodes) as u64;
+        let result_size = (std::mem::size_of::<GpuNodePositionData>() * total_nodes) as u64;
+        check_storage_buffer_size(
+            "ring-normalize-gpu-data",
+            size_of_val(self.normalize_result.gpu_data.as_slice()) as u64,
+            &limits,
+        )?;
+        check_storage_buffer_size("ring-result", result_size, &limits)?;
         let normalize_gpu_data_buffer = device.create_buffer_init(&BufferInitDescriptor {
             label: Some("ring-normalize-gpu-data"),
             contents: bytemuck::cast_slice(&self.normalize_result.gpu_data),
@@ -73,9 +149,20 @@ impl NodePositions {
             contents: bytemuck::cast_slice(&self.ring_capacity),
             usage: BufferUsages::COPY_SRC | BufferUsages::STORAGE,
         });
-        let cx_cy_buffer = device.create_buffer_init(&BufferInitDescriptor {
-            label: Some("ring-cx-cy-data"),
-            contents: bytemuck::cast_slice(&[self.cx, self.cy]),
+        let ring_offsets_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("ring-offsets-data"),
+            contents: bytemuck::cast_slice(&self.ring_offsets),
+            usage: BufferUsages::COPY_SRC | BufferUsages::STORAGE,
+        });
+        let settings_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("ring-settings-data"),
+            contents: bytemuck::cast_slice(&[
+                [self.cx, self.cy, self.start_angle, self.cz],
+                match self.mode {
+                    LayoutMode::Rings | LayoutMode::Shells => self.geometry.get_settings(),
+                    LayoutMode::Spiral => [self.min_node_spacing, 0.0, 0.0, 0.0],
+                },
+            ]),
             usage: BufferUsages::COPY_SRC | BufferUsages::STORAGE,
         });
         let inner_result_buffer = device.create_buffer(&BufferDescriptor {
@@ -91,41 +178,37 @@ impl NodePositions {
             mapped_at_creation: false,
         });
 
-        BufferData {
+        Ok(BufferData {
             normalize_gpu_data_buffer,
-            cx_cy_buffer,
+            settings_buffer,
             ring_capacity_buffer,
+            ring_offsets_buffer,
             inner_result_buffer,
             outer_result_buffer,
-        }
+        })
     }
 
-    async fn get_buffer_view(&self, buffer_data: &Buffer) -> anyhow::Result<BufferView> {
-        let device = &self.adapter.device;
-        let (tx, rx) = crossbeam::channel::bounded(1);
-        buffer_data.map_async(wgpu::MapMode::Read, .., move |result| {
-            tx.send(result).expect("unable to send ring result")
-        });
-        device.poll(PollType::wait_indefinitely())?;
-
-        match rx.recv() {
-            Ok(received) => {
-                if let Err(error) = received {
-                    return Err(anyhow!(error.to_string()));
-                }
-            }
-            Err(error) => {
-                return Err(anyhow!(error.to_string()));
-            }
-        }
-        let data = buffer_data.get_mapped_range(..);
-        Ok(data)
+    pub async fn execute(&self) -> anyhow::Result<NodePositionsResult> {
+        Ok(self.execute_timed().await?.0)
     }
 
-    pub async fn execute(&self) -> anyhow::Result<NodePositionsResult> {
+    /// Execute and measure the upload, dispatch and readback, see `StageTiming`
+    pub async fn execute_timed(&self) -> anyhow::Result<(NodePositionsResult, StageTiming)> {
+        let timer = Instant::now();
         let device = &self.adapter.device;
         let queue = &self.adapter.queue;
-        let buffer_data = self.get_buffer_data().await;
+        let buffer_data = self.get_buffer_data().await?;
+        let upload = timer.elapsed();
+        // One invocation per ring or shell, or per node along the spiral
+        let (invocations, shader) = match self.mode {
+            LayoutMode::Rings => (
+                self.ring_capacity.len(),
+                include_wgsl!("wgsl/positions.wgsl"),
+            ),
+            LayoutMode::Shells => (self.ring_capacity.len(), include_wgsl!("wgsl/shells.wgsl")),
+            LayoutMode::Spiral => (self.nodes.len(), include_wgsl!("wgsl/spiral.wgsl")),
+        };
+        let dispatch = Dispatch::new(invocations, &device.limits())?;
         let data_bg_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
             label: Some("ring-data-bg-layout"),
             entries: &[
@@ -170,6 +253,16 @@ impl NodePositions {
                     },
                     count: None,
                 },
+                BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
             ],
         });
         let data_bg = device.create_bind_group(&BindGroupDescriptor {
@@ -186,12 +279,16 @@ impl NodePositions {
                 },
                 BindGroupEntry {
                     binding: 2,
-                    resource: buffer_data.cx_cy_buffer.as_entire_binding(),
+                    resource: buffer_data.settings_buffer.as_entire_binding(),
                 },
                 BindGroupEntry {
                     binding: 3,
                     resource: buffer_data.inner_result_buffer.as_entire_binding(),
                 },
+                BindGroupEntry {
+                    binding: 4,
+                    resource: buffer_data.ring_offsets_buffer.as_entire_binding(),
+                },
             ],
         });
         let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
@@ -205,20 +302,20 @@ impl NodePositions {
         let compute_pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
             label: Some("ring-compute-pipeline"),
             layout: Some(&pipeline_layout),
-            module: &device.create_shader_module(include_wgsl!("wgsl/positions.wgsl")),
+            module: &device.create_shader_module(shader),
             entry_point: Some("main"),
             compilation_options: PipelineCompilationOptions::default(),
             cache: None,
         });
+        let timestamps = GpuTimestamps::new(self.adapter, "ring-timestamps", 2);
         {
             let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
                 label: Some("ring-compute-pass"),
-                ..Default::default()
+                timestamp_writes: GpuTimestamps::pass_writes(timestamps.as_ref(), Some(0), Some(1)),
             });
-            let num_dispatchers = self.ring_capacity.len().div_ceil(64) as u32;
             compute_pass.set_pipeline(&compute_pipeline);
             compute_pass.set_bind_group(0, &data_bg, &[]);
-            compute_pass.dispatch_workgroups(num_dispatchers, 1, 1);
+            compute_pass.dispatch_workgroups(dispatch.x, dispatch.y, 1);
         }
         encoder.copy_buffer_to_buffer(
             &buffer_data.inner_result_buffer,
@@ -227,54 +324,80 @@ impl NodePositions {
             0,
             buffer_data.outer_result_buffer.size(),
         );
+        if let Some(timestamps) = &timestamps {
+            timestamps.resolve(&mut encoder);
+        }
+        let dispatch_timer = Instant::now();
+        event!(
+            debug,
+            invocations,
+            workgroups = ?(dispatch.x, dispatch.y),
+            "node positions submitted"
+        );
         queue.submit([encoder.finish()]);
+        self.adapter.wait_idle().await?;
+        let mut dispatch_micros = dispatch_timer.elapsed().as_micros() as u64;
+        let readback_timer = Instant::now();
         let result = {
             let outer_result_buffer = self
-                .get_buffer_view(&buffer_data.outer_result_buffer)
+                .adapter
+                .map_read(&buffer_data.outer_result_buffer)
                 .await?;
-            let gpu_data: &[NodePositionData] = bytemuck::cast_slice(&outer_result_buffer);
+            let gpu_data: &[GpuNodePositionData] = bytemuck::cast_slice(&outer_result_buffer);
             NodePositionsResult {
-                gpu_data: gpu_data.to_vec(),
+                gpu_data: gpu_data.iter().map(|item| (*item).into()).collect(),
             }
         };
         buffer_data.outer_result_buffer.unmap();
-        Ok(result)
+        let readback = readback_timer.elapsed();
+        if let Some(timestamps) = &timestamps {
+            dispatch_micros =
+                GpuTimestamps::get_micros(&timestamps.read_nanos(self.adapter).await?, 0, 1);
+        }
+        let timing = StageTiming {
+            micros: timer.elapsed().as_micros() as u64,
+            upload_micros: Some(upload.as_micros() as u64),
+            dispatch_micros: Some(dispatch_micros),
+            readback_micros: Some(readback.as_micros() as u64),
+        };
+        Ok((result, timing))
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "serde", not(target_arch = "wasm32")))]
 pub mod test_gpu_node_positions {
     use super::*;
-    use serde::Deserialize;
+    use crate::generators::GraphKind;
+    use crate::gpu::{NodeConnections, normalize::Normalize};
 
     #[tokio::test]
     async fn test_node_positions() {
-        #[derive(Debug, Clone, Deserialize)]
-        struct SampleData {
-            nodes: Vec<Node>,
-            edges: Vec<Edge>,
-        }
-        let normalize_reader = std::fs::File::options()
-            .read(true)
-            .open("storage/gpu-normalize.json")
-            .unwrap();
-        let sample_data_reader = std::fs::File::options()
-            .read(true)
-            .open("storage/sample-data/nodes_100_full_mesh.json")
-            .unwrap();
-        let normalize_data =
-            serde_json::from_reader::<_, NormalizeResult>(normalize_reader).unwrap();
-        let sample_data = serde_json::from_reader::<_, SampleData>(sample_data_reader).unwrap();
+        let sample_data = GraphKind::FullMesh { nodes: 100 }.generate(0);
+        let adapter = GpuAdapter::new().await;
+        assert!(adapter.is_ok(), "{:?}", adapter.err());
+        let adapter = adapter.unwrap();
+        let node_connections_data =
+            NodeConnections::new(&adapter, &sample_data.nodes, &sample_data.edges)
+                .execute()
+                .await
+                .unwrap();
+        let normalize_data = Normalize::new(
+            &adapter,
+            &sample_data.nodes,
+            &sample_data.edges,
+            &node_connections_data,
+        )
+        .execute()
+        .await
+        .unwrap();
         let positions = NodePositions::new(
+            &adapter,
             &sample_data.nodes,
             &sample_data.edges,
-            normalize_data,
-            None,
-            None,
+            &normalize_data,
+            &LayoutOptions::default(),
         )
-        .await;
-        assert!(positions.is_ok(), "{:?}", positions.err());
-        let positions = positions.unwrap();
+        .unwrap();
         let result = positions.execute().await;
         assert!(result.is_ok(), "{:?}", result.err());
         let result = result.unwrap();
@@ -1,8 +1,14 @@
 use crate::{
+    StageTiming,
     entities::{Edge, Node, NormalizeValue},
+    gpu::limits::{Dispatch, check_storage_buffer_size},
+    gpu::timestamps::GpuTimestamps,
     gpu::{GpuAdapter, NodeConnectionsResult, node_connections::GpuNodeConnectionValue},
+    trace::event,
 };
+#[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
+use web_time::Instant;
 use wgpu::{
     BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor, BindGroupLayoutEntry,
     BindingType, Buffer, BufferBindingType, BufferDescriptor, BufferUsages,
@@ -11,7 +17,8 @@ use wgpu::{
     util::DeviceExt,
 };
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct NormalizeResult {
     pub gpu_data: Vec<NormalizeValue>,
 }
@@ -26,34 +33,51 @@ pub struct BufferData {
 }
 
 #[derive(Debug)]
-pub struct Normalize {
-    pub adapter: GpuAdapter,
-    pub nodes: Vec<Node>,
-    pub edges: Vec<Edge>,
-    pub node_connections: NodeConnectionsResult,
+pub struct Normalize<'a> {
+    pub adapter: &'a GpuAdapter,
+    pub nodes: &'a [Node],
+    pub edges: &'a [Edge],
+    pub node_connections: &'a NodeConnectionsResult,
 }
 
-impl Normalize {
-    pub async fn new(
-        nodes: &Vec<Node>,
-        edge: &Vec<Edge>,
-        node_connections: &NodeConnectionsResult,
-    ) -> anyhow::Result<Self> {
-        let adapter = GpuAdapter::new().await?;
-        Ok(Self {
+impl<'a> Normalize<'a> {
+    pub fn new(
+        adapter: &'a GpuAdapter,
+        nodes: &'a [Node],
+        edges: &'a [Edge],
+        node_connections: &'a NodeConnectionsResult,
+    ) -> Self {
+        Self {
             adapter,
-            nodes: nodes.to_owned(),
-            edges: edge.to_owned(),
-            node_connections: node_connections.to_owned(),
-        })
+            nodes,
+            edges,
+            node_connections,
+        }
     }
 
     pub async fn get_gpu_node_connections_data(&self) -> &Vec<GpuNodeConnectionValue> {
         &self.node_connections.gpu_data
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "gpu.upload",
+            level = "debug",
+            skip_all,
+            fields(stage = "normalize")
+        )
+    )]
     pub async fn get_buffer_data(&self) -> anyhow::Result<BufferData> {
         let device = &self.adapter.device;
+        let limits = device.limits();
+        let result_size = self.nodes.len() as u64 * std::mem::size_of::<NormalizeValue>() as u64;
+        check_storage_buffer_size(
+            "normalize-node-connections",
+            size_of_val(self.node_connections.gpu_data.as_slice()) as u64,
+            &limits,
+        )?;
+        check_storage_buffer_size("normalize-result", result_size, &limits)?;
 
         let min_max: &[u32; 2] = &[
             self.node_connections.min_degree,
@@ -76,7 +100,6 @@ impl Normalize {
             contents: bytemuck::bytes_of(&0u32),
             usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
         });
-        let result_size = self.nodes.len() as u64 * std::mem::size_of::<NormalizeValue>() as u64;
         let inner_result_buffer = device.create_buffer(&BufferDescriptor {
             label: Some("normalize-inner-result"),
             size: result_size,
@@ -100,8 +123,18 @@ impl Normalize {
     }
 
     pub async fn execute(&self) -> anyhow::Result<NormalizeResult> {
+        Ok(self.execute_timed().await?.0)
+    }
+
+    /// Execute and measure the normalize and the sort passes, see `StageTiming`.
+    /// The upload is part of the normalize timing, the readback of the sorted values part of the sort timing.
+    pub async fn execute_timed(
+        &self,
+    ) -> anyhow::Result<(NormalizeResult, StageTiming, StageTiming)> {
+        let timer = Instant::now();
         let device = &self.adapter.device;
         let buffer_data = self.get_buffer_data().await?;
+        let upload = timer.elapsed();
         let data_bg_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
             label: Some("normalize-data-bg-layout"),
             entries: &[
@@ -174,10 +207,11 @@ impl Normalize {
             bind_group_layouts: &[&data_bg_layout],
             ..Default::default()
         });
-        let num_dispatchers = (self.node_connections.gpu_data.len().div_ceil(64) + 10) as u32;
+        let dispatch = Dispatch::new(self.node_connections.gpu_data.len(), &device.limits())?;
         let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
             label: Some("normalize-encoder"),
         });
+        let timestamps = GpuTimestamps::new(self.adapter, "normalize-timestamps", 4);
         let normalize_compute_pipeline =
             device.create_compute_pipeline(&ComputePipelineDescriptor {
                 label: Some("normalize-compute-pipeline"),
@@ -191,15 +225,20 @@ impl Normalize {
         {
             let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
                 label: Some("normalize-compute-pass"),
-                ..Default::default()
+                timestamp_writes: GpuTimestamps::pass_writes(timestamps.as_ref(), Some(0), Some(1)),
             });
-            let num_dispatchers = (self.node_connections.gpu_data.len().div_ceil(64) + 10) as u32;
             compute_pass.set_pipeline(&normalize_compute_pipeline);
             compute_pass.set_bind_group(0, &data_bg, &[]);
-            compute_pass.dispatch_workgroups(num_dispatchers, 1, 1);
+            compute_pass.dispatch_workgroups(dispatch.x, dispatch.y, 1);
         }
+        let dispatch_timer = Instant::now();
+        event!(debug, workgroups = ?(dispatch.x, dispatch.y), "normalize submitted");
         self.adapter.queue.submit([encoder.finish()]);
+        self.adapter.wait_idle().await?;
+        let mut normalize_dispatch_micros = dispatch_timer.elapsed().as_micros() as u64;
+        let normalize_micros = timer.elapsed().as_micros() as u64;
 
+        let timer = Instant::now();
         let sort_compute_pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
             label: Some("normalize-sort-compute-pipeline"),
             layout: Some(&data_pipeline_layout),
@@ -208,19 +247,26 @@ impl Normalize {
             compilation_options: PipelineCompilationOptions::default(),
             cache: None,
         });
-        for i in 0..(self.node_connections.gpu_data.len() + 10) {
+        let last_pass = self.node_connections.gpu_data.len() + 9;
+        event!(debug, passes = last_pass + 1, "sort submitted");
+        let dispatch_timer = Instant::now();
+        for i in 0..=last_pass {
             let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
                 label: Some("normalize-encoder"),
             });
             let even_odd = if i % 2 == 0 {
-                0 as u32 //Odd
+                0_u32 //Odd
             } else {
-                1 as u32 //Even
+                1_u32 //Even
             };
             {
                 let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
                     label: Some("normalize-compute-pass"),
-                    ..Default::default()
+                    timestamp_writes: GpuTimestamps::pass_writes(
+                        timestamps.as_ref(),
+                        (i == 0).then_some(2),
+                        (i == last_pass).then_some(3),
+                    ),
                 });
                 self.adapter.queue.write_buffer(
                     &buffer_data.sort_toggle_buffer,
@@ -229,7 +275,7 @@ impl Normalize {
                 );
                 compute_pass.set_pipeline(&sort_compute_pipeline);
                 compute_pass.set_bind_group(0, &data_bg, &[]);
-                compute_pass.dispatch_workgroups(num_dispatchers, 1, 1);
+                compute_pass.dispatch_workgroups(dispatch.x, dispatch.y, 1);
             }
             encoder.copy_buffer_to_buffer(
                 &buffer_data.inner_result_buffer,
@@ -238,66 +284,71 @@ impl Normalize {
                 0,
                 buffer_data.inner_result_buffer.size(),
             );
+            if i == last_pass
+                && let Some(timestamps) = &timestamps
+            {
+                timestamps.resolve(&mut encoder);
+            }
             self.adapter.queue.submit([encoder.finish()]);
         }
+        self.adapter.wait_idle().await?;
+        let mut sort_dispatch_micros = dispatch_timer.elapsed().as_micros() as u64;
+        let readback_timer = Instant::now();
         let result = {
-            let (tx, rx) = crossbeam::channel::bounded(1);
-
-            buffer_data
-                .outer_result_buffer
-                .map_async(wgpu::MapMode::Read, .., move |result| {
-                    tx.send(result).expect("normalize unable to send result")
-                });
-
-            device
-                .poll(wgpu::wgt::PollType::wait_indefinitely())
-                .expect("unable to wait for device in normalize");
-
-            rx.recv()
-                .expect("unable to receive buffer in normalize")
-                .expect("unuable to read received buffer in normalize");
-            let buffer_result = buffer_data.outer_result_buffer.get_mapped_range(..);
+            let buffer_result = self
+                .adapter
+                .map_read(&buffer_data.outer_result_buffer)
+                .await?;
             let gpu_data: &[NormalizeValue] = bytemuck::cast_slice(&buffer_result);
             let gpu_data = gpu_data.to_vec();
             NormalizeResult { gpu_data }
         };
         buffer_data.outer_result_buffer.unmap();
-        Ok(result)
+        let readback = readback_timer.elapsed();
+        if let Some(timestamps) = &timestamps {
+            let nanos = timestamps.read_nanos(self.adapter).await?;
+            normalize_dispatch_micros = GpuTimestamps::get_micros(&nanos, 0, 1);
+            sort_dispatch_micros = GpuTimestamps::get_micros(&nanos, 2, 3);
+        }
+        let normalize_timing = StageTiming {
+            micros: normalize_micros,
+            upload_micros: Some(upload.as_micros() as u64),
+            dispatch_micros: Some(normalize_dispatch_micros),
+            readback_micros: None,
+        };
+        let sort_timing = StageTiming {
+            micros: timer.elapsed().as_micros() as u64,
+            upload_micros: None,
+            dispatch_micros: Some(sort_dispatch_micros),
+            readback_micros: Some(readback.as_micros() as u64),
+        };
+        Ok((result, normalize_timing, sort_timing))
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "serde", not(target_arch = "wasm32")))]
 pub mod test_gpu_normalize {
     use super::*;
-    use crate::gpu::{NodeConnectionsResult, normalize::Normalize};
-    use serde::Deserialize;
+    use crate::generators::GraphKind;
+    use crate::gpu::{NodeConnections, normalize::Normalize};
 
     #[tokio::test]
     async fn test_normalize() {
-        #[derive(Debug, Clone, Deserialize)]
-        struct SampleData {
-            nodes: Vec<Node>,
-            edges: Vec<Edge>,
-        }
-        let node_connections_reader = std::fs::File::options()
-            .read(true)
-            .open("storage/gpu-node-connections.json")
-            .unwrap();
-        let sample_data_reader = std::fs::File::options()
-            .read(true)
-            .open("storage/sample-data/nodes_100_full_mesh.json")
-            .unwrap();
+        let sample_data = GraphKind::FullMesh { nodes: 100 }.generate(0);
+        let adapter = GpuAdapter::new().await;
+        assert!(adapter.is_ok(), "{:?}", adapter.err());
+        let adapter = adapter.unwrap();
         let node_connections_data =
-            serde_json::from_reader::<_, NodeConnectionsResult>(node_connections_reader).unwrap();
-        let sample_data = serde_json::from_reader::<_, SampleData>(sample_data_reader).unwrap();
+            NodeConnections::new(&adapter, &sample_data.nodes, &sample_data.edges)
+                .execute()
+                .await
+                .unwrap();
         let normalize = Normalize::new(
+            &adapter,
             &sample_data.nodes,
             &sample_data.edges,
             &node_connections_data,
-        )
-        .await;
-        assert!(normalize.is_ok(), "{:?}", normalize.err());
-        let normalize = normalize.unwrap();
+        );
         let result = normalize.execute().await;
         assert!(result.is_ok(), "{:?}", result.err());
         let result = result.unwrap();
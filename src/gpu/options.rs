@@ -0,0 +1,137 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use wgpu::{Adapter, Backends, Limits, PowerPreference};
+
+/// Selection of the gpu adapter and device.
+/// Missing fields take their default value when deserialized.
+/// Parameters:
+/// - backends: graphics APIs to look for adapters, e.g. `Backends::GL` for llvmpipe on a CPU only CI. Default: all
+/// - power_preference: `HighPerformance` for the discrete gpu, `LowPower` for the integrated one. Default: none
+/// - force_fallback_adapter: use the software adapter of the backends. Default: false
+/// - required_limits: device limits. Default: wgpu defaults with the buffer sizes of the adapter
+/// - adapter_name: only use an adapter whose name contains this text, case insensitive. Default: any
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct GpuOptions {
+    pub backends: Backends,
+    pub power_preference: PowerPreference,
+    pub force_fallback_adapter: bool,
+    pub required_limits: Option<Limits>,
+    pub adapter_name: Option<String>,
+}
+
+impl Default for GpuOptions {
+    fn default() -> Self {
+        Self {
+            backends: Backends::all(),
+            power_preference: PowerPreference::None,
+            force_fallback_adapter: false,
+            required_limits: None,
+            adapter_name: None,
+        }
+    }
+}
+
+impl GpuOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Default options overridden by the wgpu environment variables, see `with_env`
+    pub fn from_env() -> Self {
+        Self::default().with_env()
+    }
+
+    /// Override the options with the wgpu environment variables when they are set:
+    /// `WGPU_BACKEND` (e.g. `vulkan,gl`), `WGPU_POWER_PREF` (`low` or `high`) and `WGPU_ADAPTER_NAME`
+    pub fn with_env(mut self) -> Self {
+        self.backends = self.backends.with_env();
+        if let Some(power_preference) = PowerPreference::from_env() {
+            self.power_preference = power_preference;
+        }
+        if let Ok(adapter_name) = std::env::var("WGPU_ADAPTER_NAME") {
+            self.adapter_name = Some(adapter_name);
+        }
+        self
+    }
+
+    pub fn backends(mut self, backends: Backends) -> Self {
+        self.backends = backends;
+        self
+    }
+
+    pub fn power_preference(mut self, power_preference: PowerPreference) -> Self {
+        self.power_preference = power_preference;
+        self
+    }
+
+    pub fn force_fallback_adapter(mut self, force_fallback_adapter: bool) -> Self {
+        self.force_fallback_adapter = force_fallback_adapter;
+        self
+    }
+
+    pub fn required_limits(mut self, required_limits: Limits) -> Self {
+        self.required_limits = Some(required_limits);
+        self
+    }
+
+    pub fn adapter_name(mut self, adapter_name: &str) -> Self {
+        self.adapter_name = Some(adapter_name.to_string());
+        self
+    }
+
+    /// Whether the adapter passes the `adapter_name` filter
+    pub fn is_matching(&self, adapter: &Adapter) -> bool {
+        match &self.adapter_name {
+            Some(name) => adapter
+                .get_info()
+                .name
+                .to_lowercase()
+                .contains(&name.to_lowercase()),
+            None => true,
+        }
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+pub mod test_gpu_options {
+    use super::*;
+    use crate::gpu::GpuAdapter;
+
+    #[tokio::test]
+    async fn test_adapter_name() {
+        let adapter = GpuAdapter::new().await;
+        assert!(adapter.is_ok(), "{:?}", adapter.err());
+        let name = adapter.unwrap().adapter.get_info().name;
+
+        let options = GpuOptions::new().adapter_name(&name.to_uppercase());
+        let adapter = GpuAdapter::with_options(&options).await;
+        assert!(adapter.is_ok(), "{:?}", adapter.err());
+        assert_eq!(adapter.unwrap().adapter.get_info().name, name);
+
+        let options = GpuOptions::new().adapter_name("no such adapter");
+        assert!(GpuAdapter::with_options(&options).await.is_err());
+    }
+
+    #[cfg(feature = "formats")]
+    #[test]
+    fn test_gpu_options_from_toml() {
+        let toml = r#"
+            [gpu]
+            backends = "VULKAN | GL"
+            power_preference = "high-performance"
+            adapter_name = "llvmpipe"
+        "#;
+        let options = crate::LayoutOptions::from_toml(toml).unwrap();
+        assert_eq!(
+            options.gpu,
+            GpuOptions::new()
+                .backends(Backends::VULKAN | Backends::GL)
+                .power_preference(PowerPreference::HighPerformance)
+                .adapter_name("llvmpipe")
+        );
+        let json = serde_json::to_string(&options).unwrap();
+        assert_eq!(crate::LayoutOptions::from_json(&json).unwrap(), options);
+    }
+}
@@ -0,0 +1,95 @@
+use crate::gpu::GpuAdapter;
+use wgpu::{
+    Buffer, BufferDescriptor, BufferUsages, CommandEncoder, ComputePassTimestampWrites, Features,
+    QUERY_SIZE, QuerySet, QuerySetDescriptor, QueryType,
+};
+
+/// Timestamps written by the gpu at the beginning or the end of compute passes.
+/// Only available when the device has `Features::TIMESTAMP_QUERY`.
+#[derive(Debug)]
+pub struct GpuTimestamps {
+    query_set: QuerySet,
+    resolve_buffer: Buffer,
+    read_buffer: Buffer,
+    count: u32,
+}
+
+impl GpuTimestamps {
+    /// A set of `count` timestamps, None when the device does not support them
+    pub fn new(adapter: &GpuAdapter, label: &str, count: u32) -> Option<Self> {
+        let device = &adapter.device;
+        if !device.features().contains(Features::TIMESTAMP_QUERY) {
+            return None;
+        }
+        let size = count as u64 * QUERY_SIZE as u64;
+        Some(Self {
+            query_set: device.create_query_set(&QuerySetDescriptor {
+                label: Some(label),
+                ty: QueryType::Timestamp,
+                count,
+            }),
+            resolve_buffer: device.create_buffer(&BufferDescriptor {
+                label: Some(label),
+                size,
+                usage: BufferUsages::QUERY_RESOLVE | BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            }),
+            read_buffer: device.create_buffer(&BufferDescriptor {
+                label: Some(label),
+                size,
+                usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+                mapped_at_creation: false,
+            }),
+            count,
+        })
+    }
+
+    /// Timestamp writes of a compute pass
+    /// Parameters:
+    /// - beginning: index of the timestamp written before the pass
+    /// - end: index of the timestamp written after the pass
+    pub fn pass_writes(
+        timestamps: Option<&Self>,
+        beginning: Option<u32>,
+        end: Option<u32>,
+    ) -> Option<ComputePassTimestampWrites<'_>> {
+        let timestamps = timestamps?;
+        if beginning.is_none() && end.is_none() {
+            return None;
+        }
+        Some(ComputePassTimestampWrites {
+            query_set: &timestamps.query_set,
+            beginning_of_pass_write_index: beginning,
+            end_of_pass_write_index: end,
+        })
+    }
+
+    /// Copy the timestamps to a readable buffer, after the last pass
+    pub fn resolve(&self, encoder: &mut CommandEncoder) {
+        encoder.resolve_query_set(&self.query_set, 0..self.count, &self.resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(
+            &self.resolve_buffer,
+            0,
+            &self.read_buffer,
+            0,
+            self.resolve_buffer.size(),
+        );
+    }
+
+    /// Timestamps in nanoseconds, once the resolving commands are done
+    pub async fn read_nanos(&self, adapter: &GpuAdapter) -> anyhow::Result<Vec<f64>> {
+        let period = adapter.queue.get_timestamp_period() as f64;
+        let nanos = {
+            let buffer = adapter.map_read(&self.read_buffer).await?;
+            let ticks: &[u64] = bytemuck::cast_slice(&buffer);
+            ticks.iter().map(|tick| *tick as f64 * period).collect()
+        };
+        self.read_buffer.unmap();
+        Ok(nanos)
+    }
+
+    /// Microseconds between two timestamps
+    pub fn get_micros(nanos: &[f64], beginning: usize, end: usize) -> u64 {
+        ((nanos[end] - nanos[beginning]).max(0.0) / 1000.0) as u64
+    }
+}
@@ -4,11 +4,15 @@ struct NodeValue {
 }
 
 struct Edge {
+    id: u32,
     source_node: u32,
     target_node: u32
 }
 
+const WORKGROUP_SIZE: u32 = 64;
+
 @group(0) @binding(0) var<storage, read> nodes: array<u32>;
+// One chunk of the edges, the totals are accumulated over the chunks
 @group(0) @binding(1) var<storage, read> edges: array<Edge>;
 @group(0) @binding(2) var<storage, read_write> connections: array<NodeValue>;
 @group(0) @binding(3) var<storage, read_write> min_max: array<u32,2>;
@@ -17,11 +21,12 @@ struct Edge {
 @workgroup_size(64)
 fn get_connections(
     @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
 ){
-    let wg_size: u32 = 64;
-    let index = global_id.x;
-
-    let nodes_length = arrayLength(&nodes);
+    let index = global_id.x + global_id.y * num_workgroups.x * WORKGROUP_SIZE;
+    if (index >= arrayLength(&nodes)) {
+        return;
+    }
 
     let node: u32 = nodes[index];
     let total_edges = arrayLength(&edges);
@@ -32,16 +37,19 @@ fn get_connections(
             total_connections += 1;
         }
     }
-    connections[index] = NodeValue(node,total_connections);
+    connections[index] = NodeValue(node, connections[index].total + total_connections);
 }
 
 @compute
 @workgroup_size(64)
 fn get_min(
     @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
 ){
-    let wg_size: u32 = 64;
-    let index = global_id.x;
+    let index = global_id.x + global_id.y * num_workgroups.x * WORKGROUP_SIZE;
+    if (index >= arrayLength(&connections)) {
+        return;
+    }
     let connection = connections[index];
     let total = connection.total;
 
@@ -60,9 +68,12 @@ fn get_min(
 @workgroup_size(64)
 fn get_max(
     @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
 ){
-    let wg_size: u32 = 64;
-    let index = global_id.x;
+    let index = global_id.x + global_id.y * num_workgroups.x * WORKGROUP_SIZE;
+    if (index >= arrayLength(&connections)) {
+        return;
+    }
     let connection = connections[index];
     let total = connection.total;
 
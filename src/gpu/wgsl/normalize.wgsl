@@ -11,28 +11,38 @@ struct NodeConnectionValue {
 @group(0) @binding(1) var<storage, read> min_max_degree: array<u32,2>;
 @group(0) @binding(2) var<storage, read_write> normalized_values: array<NormalizedValue>;
 @group(0) @binding(3) var<uniform> sort_toggle: u32;
+const WORKGROUP_SIZE: u32 = 64;
 
 @compute
 @workgroup_size(64,1,1)
 fn main(
-    @builtin(global_invocation_id) global_invocation_id: vec3<u32>
+    @builtin(global_invocation_id) global_invocation_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>
 ){
     let min_degree = f32(min_max_degree[0]);
     let max_degree = f32(min_max_degree[1]);
-    let index = global_invocation_id.x;
+    let index = global_invocation_id.x + global_invocation_id.y * num_workgroups.x * WORKGROUP_SIZE;
+    if(index >= arrayLength(&node_connections)){
+        return;
+    }
     let item = node_connections[index];
     let node_id = item.node_id;
     let total = item.total;
-    let normalized_value: f32 = (f32(total) - min_degree) / (max_degree - min_degree);
+    // Same degree for every node: 0 as the cpu does instead of NaN
+    var normalized_value: f32 = 0.0;
+    if(max_degree > min_degree){
+        normalized_value = (f32(total) - min_degree) / (max_degree - min_degree);
+    }
     normalized_values[index] = NormalizedValue(node_id,normalized_value);
 }
 
 @compute
 @workgroup_size(64)
 fn sort(
-    @builtin(global_invocation_id) global_invocation_id: vec3<u32>
+    @builtin(global_invocation_id) global_invocation_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>
 ){
-    let left_index = global_invocation_id.x;
+    let left_index = global_invocation_id.x + global_invocation_id.y * num_workgroups.x * WORKGROUP_SIZE;
     let right_index = left_index + 1;
     let total_values = arrayLength(&normalized_values);
     if(right_index < total_values){
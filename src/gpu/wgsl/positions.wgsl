@@ -20,23 +20,104 @@ struct RingData {
     cy: f32,
     x: f32,
     y: f32,
-    node_id: u32
+    node_id: u32,
+    cz: f32,
+    z: f32,
+    polar_degree: f32,
+    azimuth_degree: f32,
+    pinned: u32
+}
+struct Settings {
+    // cx, cy, start_angle (degrees), cz
+    center: vec4<f32>,
+    // kind (0 circle, 1 ellipse, 2 rounded rectangle), scale_x, scale_y, corner_radius, see `RingGeometry`
+    shape: vec4<f32>,
 }
 @group(0) @binding(0) var<storage,read> normalize_data: array<NormalizeValue>;
 @group(0) @binding(1) var<storage,read> ring_capacity: array<RingCapacity>;
-@group(0) @binding(2) var<storage,read> cx_cy: vec2<f32>;
+@group(0) @binding(2) var<storage,read> settings: Settings;
 @group(0) @binding(3) var<storage,read_write> result: array<RingData>;
+// Offset in degrees added to the start angle of each ring, see `RingStagger`
+@group(0) @binding(4) var<storage,read> ring_offsets: array<f32>;
 const PI: f32 = radians(180.0); //3.141592653589793;
+const WORKGROUP_SIZE: u32 = 64;
+const ELLIPSE_SEGMENTS: u32 = 128;
+
+fn ellipse_point(a: f32, b: f32, angle: f32) -> vec2<f32> {
+    return vec2<f32>(a * cos(angle), b * sin(angle));
+}
+
+// Point at a fraction of the length of a polygon of ELLIPSE_SEGMENTS along the ellipse
+fn get_ellipse_point(a: f32, b: f32, fraction: f32) -> vec2<f32> {
+    let step = 2.0 * PI / f32(ELLIPSE_SEGMENTS);
+    var total: f32 = 0.0;
+    for(var i: u32 = 0; i < ELLIPSE_SEGMENTS; i++) {
+        total += distance(ellipse_point(a, b, f32(i) * step), ellipse_point(a, b, f32(i + 1) * step));
+    }
+    var remaining = fraction * total;
+    for(var i: u32 = 0; i < ELLIPSE_SEGMENTS; i++) {
+        let length = distance(ellipse_point(a, b, f32(i) * step), ellipse_point(a, b, f32(i + 1) * step));
+        if(remaining < length) {
+            return ellipse_point(a, b, (f32(i) + remaining / length) * step);
+        }
+        remaining -= length;
+    }
+    return ellipse_point(a, b, 0.0);
+}
+
+// First quadrant of a rounded rectangle, from the middle of the right side to the middle of the top side
+fn get_first_quadrant_point(half_width: f32, half_height: f32, corner: f32, length: f32) -> vec2<f32> {
+    let side = half_height - corner;
+    let arc = PI * corner / 2.0;
+    let top = half_width - corner;
+    if(length < side) {
+        return vec2<f32>(half_width, length);
+    }
+    let on_arc = length - side;
+    if(on_arc < arc) {
+        let angle = on_arc / corner;
+        return vec2<f32>(top + corner * cos(angle), side + corner * sin(angle));
+    }
+    return vec2<f32>(top - (on_arc - arc), half_height);
+}
+
+// Point at a fraction of the perimeter, the quadrants mirror the first one
+fn get_rounded_rectangle_point(half_width: f32, half_height: f32, corner: f32, fraction: f32) -> vec2<f32> {
+    let quadrant = (half_height - corner) + PI * corner / 2.0 + (half_width - corner);
+    let total = fraction * 4.0 * quadrant;
+    let index = min(u32(total / quadrant), 3u);
+    let length = total - f32(index) * quadrant;
+    if(index == 0u) {
+        return get_first_quadrant_point(half_width, half_height, corner, length);
+    }
+    if(index == 1u) {
+        return get_first_quadrant_point(half_width, half_height, corner, quadrant - length) * vec2<f32>(-1.0, 1.0);
+    }
+    if(index == 2u) {
+        return -get_first_quadrant_point(half_width, half_height, corner, length);
+    }
+    return get_first_quadrant_point(half_width, half_height, corner, quadrant - length) * vec2<f32>(1.0, -1.0);
+}
 
 @compute
 @workgroup_size(64)
 fn main(
-    @builtin(global_invocation_id) global_invocation_id: vec3<u32>
+    @builtin(global_invocation_id) global_invocation_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>
 ){
-    let index = global_invocation_id.x;
+    let index = global_invocation_id.x + global_invocation_id.y * num_workgroups.x * WORKGROUP_SIZE;
+    if(index >= arrayLength(&ring_capacity)){
+        return;
+    }
     let capacity = ring_capacity[index];
-    let cx = cx_cy.x;
-    let cy = cx_cy.y;
+    let cx = settings.center.x;
+    let cy = settings.center.y;
+    let start_angle = settings.center.z + ring_offsets[index];
+    let shape = u32(settings.shape.x);
+    let radius = f32(capacity.radius);
+    let half_width = radius * settings.shape.y;
+    let half_height = radius * settings.shape.z;
+    let corner = radius * settings.shape.w;
     let ring_index = capacity.index;
     let ring_radius = capacity.radius;
     let start_index = capacity.range[0];
@@ -50,12 +131,25 @@ fn main(
     }
     let step_angle = 360.0 / f32(total_nodes);
     for(var i = start_index; i < end_index; i++) {
-        let angle_degree = f32(node_index) * step_angle;
-        let angle_radian = angle_degree * (PI / 180.0 );
+        let angle = start_angle + f32(node_index) * step_angle;
+        var angle_degree = angle - 360.0 * floor(angle / 360.0);
+        var angle_radian = angle_degree * (PI / 180.0 );
+        var offset = vec2<f32>(radius * cos(angle_radian), radius * sin(angle_radian));
+        if(shape != 0u && ring_radius > 0u) {
+            if(shape == 1u) {
+                offset = get_ellipse_point(half_width, half_height, angle_degree / 360.0);
+            } else {
+                offset = get_rounded_rectangle_point(half_width, half_height, corner, angle_degree / 360.0);
+            }
+            // Angle of the node seen from the centre
+            let polar = degrees(atan2(offset.y, offset.x));
+            angle_degree = polar - 360.0 * floor(polar / 360.0);
+            angle_radian = angle_degree * (PI / 180.0 );
+        }
         let normalize_node = normalize_data[i];
         let node_id = normalize_node.node_id;
-        let x = cx + f32(ring_radius) * cos(angle_radian);
-        let y = cy + f32(ring_radius) * sin(angle_radian);
+        let x = cx + offset.x;
+        let y = cy + offset.y;
         result[i] = RingData(
             index,
             ring_radius,
@@ -65,7 +159,12 @@ fn main(
             cy,
             x,
             y,
-            node_id);
+            node_id,
+            settings.center.w,
+            settings.center.w,
+            90.0,
+            angle_degree,
+            0u);
         node_index++;
     }
 
@@ -0,0 +1,90 @@
+struct RingCapacity {
+    index: u32,
+    max_nodes: u32,
+    radius: u32,
+    range: array<u32,2>,
+}
+struct NormalizeValue {
+    node_id: u32,
+    value: f32
+}
+struct RingData {
+    index: u32,
+    radius: u32,
+    angle_degree: f32,
+    angle_radian: f32,
+    cx: f32,
+    cy: f32,
+    x: f32,
+    y: f32,
+    node_id: u32,
+    cz: f32,
+    z: f32,
+    polar_degree: f32,
+    azimuth_degree: f32,
+    pinned: u32
+}
+struct Settings {
+    // cx, cy, start_angle (degrees), cz
+    center: vec4<f32>,
+    // unused
+    shape: vec4<f32>,
+}
+@group(0) @binding(0) var<storage,read> normalize_data: array<NormalizeValue>;
+@group(0) @binding(1) var<storage,read> ring_capacity: array<RingCapacity>;
+@group(0) @binding(2) var<storage,read> settings: Settings;
+@group(0) @binding(3) var<storage,read_write> result: array<RingData>;
+const PI: f32 = radians(180.0);
+const WORKGROUP_SIZE: u32 = 64;
+// Golden angle minus 137.5 degrees, see `LayoutMode::get_spiral_angle`
+const GOLDEN_ANGLE_FRACTION: f32 = 0.00776405;
+
+// One shell per invocation, the nodes follow a Fibonacci sphere
+@compute
+@workgroup_size(64)
+fn main(
+    @builtin(global_invocation_id) global_invocation_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>
+){
+    let index = global_invocation_id.x + global_invocation_id.y * num_workgroups.x * WORKGROUP_SIZE;
+    if(index >= arrayLength(&ring_capacity)){
+        return;
+    }
+    let capacity = ring_capacity[index];
+    let cx = settings.center.x;
+    let cy = settings.center.y;
+    let cz = settings.center.w;
+    let radius = f32(capacity.radius);
+    let start_index = capacity.range[0];
+    let end_index = min(capacity.range[1], arrayLength(&normalize_data));
+    if(end_index <= start_index) {
+        return;
+    }
+    let total_nodes = f32(end_index - start_index);
+    for(var i = start_index; i < end_index; i++) {
+        let node_index = i - start_index;
+        let height = clamp(1.0 - 2.0 * (f32(node_index) + 0.5) / total_nodes, -1.0, 1.0);
+        let polar_radian = acos(height);
+        let whole = f32((node_index % 360u) * 137u % 360u);
+        let half = f32(node_index % 720u) * 0.5;
+        let angle = settings.center.z + whole + half + f32(node_index) * GOLDEN_ANGLE_FRACTION;
+        let azimuth_degree = angle - 360.0 * floor(angle / 360.0);
+        let azimuth_radian = azimuth_degree * (PI / 180.0);
+        let distance = radius * sin(polar_radian);
+        result[i] = RingData(
+            capacity.index,
+            capacity.radius,
+            azimuth_degree,
+            azimuth_radian,
+            cx,
+            cy,
+            cx + distance * cos(azimuth_radian),
+            cy + distance * sin(azimuth_radian),
+            normalize_data[i].node_id,
+            cz,
+            cz + radius * height,
+            polar_radian * (180.0 / PI),
+            azimuth_degree,
+            0u);
+    }
+}
@@ -0,0 +1,69 @@
+struct NormalizeValue {
+    node_id: u32,
+    value: f32
+}
+struct RingData {
+    index: u32,
+    radius: u32,
+    angle_degree: f32,
+    angle_radian: f32,
+    cx: f32,
+    cy: f32,
+    x: f32,
+    y: f32,
+    node_id: u32,
+    cz: f32,
+    z: f32,
+    polar_degree: f32,
+    azimuth_degree: f32,
+    pinned: u32
+}
+struct Settings {
+    // cx, cy, start_angle (degrees), cz
+    center: vec4<f32>,
+    // min_node_spacing, unused
+    spiral: vec4<f32>,
+}
+@group(0) @binding(0) var<storage,read> normalize_data: array<NormalizeValue>;
+@group(0) @binding(2) var<storage,read> settings: Settings;
+@group(0) @binding(3) var<storage,read_write> result: array<RingData>;
+const PI: f32 = radians(180.0);
+const WORKGROUP_SIZE: u32 = 64;
+// Golden angle minus 137.5 degrees, see `LayoutMode::get_spiral_angle`
+const GOLDEN_ANGLE_FRACTION: f32 = 0.00776405;
+
+// One node per invocation, the node `index` of the sorted order turns by `index` golden angles
+@compute
+@workgroup_size(64)
+fn main(
+    @builtin(global_invocation_id) global_invocation_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>
+){
+    let index = global_invocation_id.x + global_invocation_id.y * num_workgroups.x * WORKGROUP_SIZE;
+    if(index >= arrayLength(&result) || index >= arrayLength(&normalize_data)){
+        return;
+    }
+    let cx = settings.center.x;
+    let cy = settings.center.y;
+    let whole = f32((index % 360u) * 137u % 360u);
+    let half = f32(index % 720u) * 0.5;
+    let angle = settings.center.z + whole + half + f32(index) * GOLDEN_ANGLE_FRACTION;
+    let angle_degree = angle - 360.0 * floor(angle / 360.0);
+    let angle_radian = angle_degree * (PI / 180.0);
+    let radius = settings.spiral.x * sqrt(f32(index));
+    result[index] = RingData(
+        0u,
+        u32(round(radius)),
+        angle_degree,
+        angle_radian,
+        cx,
+        cy,
+        cx + radius * cos(angle_radian),
+        cy + radius * sin(angle_radian),
+        normalize_data[index].node_id,
+        settings.center.w,
+        settings.center.w,
+        90.0,
+        angle_degree,
+        0u);
+}
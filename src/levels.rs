@@ -0,0 +1,269 @@
+//! Rings chosen by the caller, e.g. the core, aggregation and access tiers of a network.
+//! The other nodes keep the rings computed from their degree.
+
+use crate::entities::{Node, NormalizeValue, RingCapacity};
+use crate::{LayoutMode, LayoutOptions};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Ring level of a node given by the caller, see `LayoutOptions::levels`. The ring 0 is the centre.
+/// At most `Levels::MAX_LEVEL`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct NodeLevel {
+    pub node_id: u32,
+    pub level: u32,
+}
+
+impl NodeLevel {
+    pub fn new(node_id: u32, level: u32) -> Self {
+        Self { node_id, level }
+    }
+}
+
+#[derive(Debug)]
+pub struct Levels {}
+
+impl Levels {
+    /// Highest level accepted by `validate`
+    pub const MAX_LEVEL: u32 = 1024;
+
+    /// The nodes with a level are in the graph and have a single level, at most `MAX_LEVEL`
+    pub fn validate(levels: &[NodeLevel], nodes: &[Node]) -> anyhow::Result<()> {
+        if levels.is_empty() {
+            return Ok(());
+        }
+        let node_ids: HashSet<u32> = nodes.iter().map(|node| node.id).collect();
+        let mut level_ids = HashSet::with_capacity(levels.len());
+        for item in levels {
+            if !node_ids.contains(&item.node_id) {
+                anyhow::bail!(
+                    "node {} of level {} is not in the graph",
+                    item.node_id,
+                    item.level
+                );
+            }
+            if item.level > Self::MAX_LEVEL {
+                anyhow::bail!(
+                    "node {} has the level {}, the maximum is {}",
+                    item.node_id,
+                    item.level,
+                    Self::MAX_LEVEL
+                );
+            }
+            if !level_ids.insert(item.node_id) {
+                anyhow::bail!("node {} has more than one level", item.node_id);
+            }
+        }
+        Ok(())
+    }
+
+    /// Reorder the sorted values ring by ring, see `get_ring_capacity`. The order within a ring is kept.
+    /// Without levels, or in `LayoutMode::Spiral`, the values are left as is.
+    pub fn arrange(
+        values: &mut Vec<NormalizeValue>,
+        options: &LayoutOptions,
+    ) -> anyhow::Result<()> {
+        if options.levels.is_empty() || options.mode == LayoutMode::Spiral {
+            return Ok(());
+        }
+        let (order, _) = Self::get_rings(values, options)?;
+        *values = order.iter().map(|index| values[*index]).collect();
+        Ok(())
+    }
+
+    /// Rings holding the values arranged by `arrange`.
+    /// Each level is placed on the ring of its index, continued on the next rings when it exceeds
+    /// the ring capacity, and pushes the next levels outwards. The nodes without level fill the other
+    /// rings from the centre in the sorted order. Once they are all placed the next levels take the
+    /// next rings, a level never leaves an empty ring before it.
+    pub fn get_ring_capacity(
+        values: &[NormalizeValue],
+        options: &LayoutOptions,
+    ) -> anyhow::Result<Vec<RingCapacity>> {
+        match options.levels.is_empty() || options.mode == LayoutMode::Spiral {
+            true => options.get_ring_capacity(values.len() as u32),
+            false => Ok(Self::get_rings(values, options)?.1),
+        }
+    }
+
+    /// Indexes of the values in ring order and the rings
+    fn get_rings(
+        values: &[NormalizeValue],
+        options: &LayoutOptions,
+    ) -> anyhow::Result<(Vec<usize>, Vec<RingCapacity>)> {
+        let levels: HashMap<u32, u32> = options
+            .levels
+            .iter()
+            .map(|item| (item.node_id, item.level))
+            .collect();
+        let mut groups: BTreeMap<u32, Vec<usize>> = BTreeMap::new();
+        let mut auto: Vec<usize> = Vec::with_capacity(values.len());
+        for (index, value) in values.iter().enumerate() {
+            match levels.get(&value.node_id) {
+                Some(level) => groups.entry(*level).or_default().push(index),
+                None => auto.push(index),
+            }
+        }
+        let geometry = options.get_ring_geometry();
+        let get_max_nodes = |ring_index: u32, radius: u32| match (ring_index, options.mode) {
+            (0, _) => 1,
+            (_, LayoutMode::Shells) => {
+                RingCapacity::get_max_shell_nodes(radius, options.min_node_spacing)
+            }
+            _ => geometry.get_max_nodes(radius, options.min_node_spacing),
+        };
+        let mut groups = groups
+            .into_iter()
+            .map(|(level, group)| (level, group.into_iter()));
+        let mut group = groups.next();
+        let mut auto = auto.into_iter();
+        let mut order: Vec<usize> = Vec::with_capacity(values.len());
+        let mut ring_capacity: Vec<RingCapacity> = Vec::new();
+        let mut ring_index: u32 = 0;
+        while order.len() < values.len() {
+            let radius = RingCapacity::get_ring_radius(options.step_radius, ring_index);
+            let max_nodes = get_max_nodes(ring_index, radius);
+            RingCapacity::check_max_nodes(max_nodes, radius)?;
+            let start = order.len() as u32;
+            match group.as_mut() {
+                Some((level, nodes)) if *level <= ring_index || auto.len() == 0 => {
+                    order.extend(nodes.by_ref().take(max_nodes as usize));
+                    if nodes.len() == 0 {
+                        group = groups.next();
+                    }
+                }
+                _ => order.extend(auto.by_ref().take(max_nodes as usize)),
+            }
+            ring_capacity.push(RingCapacity {
+                index: ring_index,
+                max_nodes,
+                radius,
+                range: [start, order.len() as u32],
+            });
+            ring_index += 1;
+        }
+        Ok((order, ring_capacity))
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+pub mod test_levels {
+    use super::*;
+    use crate::ComputingConfig;
+    use crate::ConcentricLayout;
+    use crate::entities::{Edge, NodePositionData};
+
+    /// Tree of a core, 4 aggregation nodes and 6 access nodes per aggregation node
+    fn get_tiers() -> (Vec<Node>, Vec<Edge>, Vec<NodeLevel>) {
+        let nodes: Vec<Node> = (1..=29).map(|id| Node { id }).collect();
+        let mut edges = Vec::new();
+        for aggregation in 2..=5 {
+            edges.push((1, aggregation));
+            for access in 0..6 {
+                edges.push((aggregation, 6 + (aggregation - 2) * 6 + access));
+            }
+        }
+        let edges = edges
+            .into_iter()
+            .enumerate()
+            .map(|(id, (source_id, target_id))| Edge {
+                id: id as u32,
+                source_id,
+                target_id,
+            })
+            .collect();
+        let levels = (1..=29)
+            .map(|id| match id {
+                1 => NodeLevel::new(id, 0),
+                2..=5 => NodeLevel::new(id, 1),
+                _ => NodeLevel::new(id, 3),
+            })
+            .collect();
+        (nodes, edges, levels)
+    }
+
+    fn get_ring(positions: &[NodePositionData], node_id: u32) -> u32 {
+        positions
+            .iter()
+            .find(|item| item.node_id == node_id)
+            .unwrap()
+            .index
+    }
+
+    #[tokio::test]
+    async fn test_levels() {
+        let (nodes, edges, levels) = get_tiers();
+        let options = LayoutOptions::new()
+            .backend(ComputingConfig::Cpu(2))
+            .levels(levels.clone());
+        let mut layout = ConcentricLayout::new(&nodes, &edges, options.clone());
+        let positions = layout.execute().await.unwrap();
+        assert_eq!(positions.len(), nodes.len());
+        // The aggregation nodes have the highest degree but stay on the ring 1
+        assert_eq!(get_ring(&positions, 1), 0);
+        for node_id in 2..=5 {
+            assert_eq!(get_ring(&positions, node_id), 1);
+        }
+        // The access nodes of the level 3 start on the ring 2, the next one, and fill the ring 3
+        for node_id in 6..=29 {
+            assert!((2..=3).contains(&get_ring(&positions, node_id)));
+        }
+        assert!(positions.iter().any(|item| item.index == 2));
+
+        // Levels exceeding the ring capacity continue on the next rings: 3 nodes fit on the ring 1
+        // and 14 on the ring 3
+        let mut layout =
+            ConcentricLayout::new(&nodes, &edges, options.clone().min_node_spacing(80.0));
+        let positions = layout.execute().await.unwrap();
+        let count = |index: u32| positions.iter().filter(|item| item.index == index).count();
+        assert_eq!(
+            [count(0), count(1), count(2), count(3), count(4)],
+            [1, 3, 1, 14, 10]
+        );
+
+        // Mixed with the nodes without level, which fill the other rings in degree order
+        let options = options.levels(vec![NodeLevel::new(29, 0), NodeLevel::new(2, 3)]);
+        let mut layout = ConcentricLayout::new(&nodes, &edges, options.clone());
+        let positions = layout.execute().await.unwrap();
+        assert_eq!(get_ring(&positions, 29), 0);
+        assert_eq!(get_ring(&positions, 2), 3);
+        assert_eq!(get_ring(&positions, 3), 1);
+        assert!(positions.iter().filter(|item| item.index == 3).count() == 1);
+
+        #[cfg(feature = "gpu")]
+        {
+            let options = options.clone().backend(ComputingConfig::Gpu);
+            let mut layout = ConcentricLayout::new(&nodes, &edges, options);
+            let gpu = layout.execute().await.unwrap();
+            assert_eq!(gpu.len(), positions.len());
+            for item in positions.iter() {
+                assert_eq!(get_ring(&gpu, item.node_id), item.index);
+            }
+        }
+
+        // A level far past the last ring takes the next ring
+        let highest = options
+            .clone()
+            .levels(vec![NodeLevel::new(29, Levels::MAX_LEVEL)]);
+        let mut layout = ConcentricLayout::new(&nodes, &edges, highest);
+        let positions = layout.execute().await.unwrap();
+        let last = positions.iter().map(|item| item.index).max().unwrap();
+        assert_eq!(get_ring(&positions, 29), last);
+        assert_eq!(
+            positions.iter().filter(|item| item.index == last).count(),
+            1
+        );
+        assert!(last < 10);
+
+        let huge = options
+            .clone()
+            .levels(vec![NodeLevel::new(1, 4_000_000_000)]);
+        let mut layout = ConcentricLayout::new(&nodes, &edges, huge);
+        assert!(layout.execute().await.is_err());
+        let options = options.levels(vec![NodeLevel::new(42, 1)]);
+        let mut layout = ConcentricLayout::new(&nodes, &edges, options);
+        assert!(layout.execute().await.is_err());
+    }
+}
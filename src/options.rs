@@ -0,0 +1,536 @@
+use crate::BoundingBox;
+use crate::ComputingConfig;
+use crate::RingShape;
+use crate::entities::{NormalizeValue, RingCapacity};
+use crate::generators::SeededRng;
+#[cfg(feature = "gpu")]
+use crate::gpu::GpuOptions;
+use crate::levels::NodeLevel;
+use crate::pinning::PinnedNode;
+use crate::shape::RingGeometry;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Order of the nodes from the centre to the outer rings
+/// Kinds:
+/// - DegreeDescending: highest degree at the centre (default)
+/// - DegreeAscending: lowest degree at the centre
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum RingOrder {
+    #[default]
+    DegreeDescending,
+    DegreeAscending,
+}
+
+impl RingOrder {
+    /// Reorder the normalized values, sorted by descending degree, from the centre to the outer rings.
+    pub fn apply(&self, values: &mut [NormalizeValue]) {
+        if *self == RingOrder::DegreeAscending {
+            values.reverse();
+        }
+    }
+}
+
+/// Golden angle in degrees, 360 * (2 - phi)
+pub const GOLDEN_ANGLE: f32 = 137.507_77;
+/// Golden angle minus 137.5 degrees, to turn large node indexes without losing the f32 precision
+const GOLDEN_ANGLE_FRACTION: f32 = 0.007_764_05;
+
+/// Placement of the nodes
+/// Kinds:
+/// - Rings: concentric rings, see `RingCapacity` (default)
+/// - Shells: 3D, spherical shells with the ring radiuses. The nodes of a shell follow a Fibonacci
+///   sphere and the capacity its surface area, see `RingCapacity::generate_shells`. The stagger
+///   and the shape do not apply.
+/// - Spiral: Fermat spiral turning by the golden angle (phyllotaxis), without ring boundaries.
+///   The node `k` of the sorted order is at `min_node_spacing * sqrt(k)` from the centre, so the
+///   density is uniform and no two nodes are closer than the spacing. The nodes are all on the
+///   ring 0, `radius` is their distance to the centre. The stagger and the shape do not apply.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum LayoutMode {
+    #[default]
+    Rings,
+    Shells,
+    Spiral,
+}
+
+impl LayoutMode {
+    /// Angle in degrees of the node `index` on the spiral, `index` times the golden angle.
+    /// The whole and the half degrees are reduced exactly, as in `spiral.wgsl`.
+    pub fn get_spiral_angle(index: u32) -> f32 {
+        let whole = ((index % 360) * 137 % 360) as f32;
+        let half = (index % 720) as f32 * 0.5;
+        (whole + half + index as f32 * GOLDEN_ANGLE_FRACTION).rem_euclid(360_f32)
+    }
+}
+
+/// Angular offset added to the start angle of each ring, so the first nodes of the rings do not
+/// line up on a spoke.
+/// Kinds:
+/// - None: every ring starts at the start angle (default)
+/// - HalfStep: the odd rings are turned by half of their angle between two nodes
+/// - GoldenAngle: the ring `i` is turned by `i` times the golden angle
+/// - Random: each ring is turned by a seeded random part of its angle between two nodes
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum RingStagger {
+    #[default]
+    None,
+    HalfStep,
+    GoldenAngle,
+    Random {
+        seed: u64,
+    },
+}
+
+impl RingStagger {
+    /// Offset in degrees of each ring, in the order of the ring capacities
+    pub fn get_offsets(&self, ring_capacity: &[RingCapacity]) -> Vec<f32> {
+        let mut rng = match self {
+            RingStagger::Random { seed } => Some(SeededRng::new(*seed)),
+            _ => None,
+        };
+        ring_capacity
+            .iter()
+            .map(|capacity| {
+                let total_nodes = capacity.range[1].saturating_sub(capacity.range[0]).max(1);
+                let step_angle = 360_f32 / total_nodes as f32;
+                match (self, rng.as_mut()) {
+                    (RingStagger::HalfStep, _) if capacity.index % 2 == 1 => step_angle / 2.0,
+                    (RingStagger::GoldenAngle, _) => {
+                        (capacity.index as f32 * GOLDEN_ANGLE).rem_euclid(360_f32)
+                    }
+                    (RingStagger::Random { .. }, Some(rng)) => rng.next_f64() as f32 * step_angle,
+                    _ => 0.0,
+                }
+            })
+            .collect()
+    }
+}
+
+/// What to do with the input before computing
+/// Kinds:
+/// - Strict: reject empty graphs, duplicated node ids and edges to unknown nodes (default)
+/// - Skip: no validation, the input is trusted
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum ValidationPolicy {
+    #[default]
+    Strict,
+    Skip,
+}
+
+/// Options of the layout shared by the cpu and gpu computing.
+/// Missing fields take their default value when deserialized.
+/// Parameters:
+/// - backend: cpu, gpu or auto computing. Default: cpu with automatic number of threads
+/// - gpu_min_nodes: smallest graph computed on the gpu by the `auto` backend. Default: 20000
+/// - gpu: adapter selection when no adapter is shared, see `GpuOptions`. Requires the `gpu` feature.
+/// - cx, cy: centre of the rings. Default: 0, 0
+/// - cz: depth of the centre, used by `LayoutMode::Shells`. Default: 0
+/// - start_angle: angle in degrees where the first node of each ring is placed. Default: 0
+/// - mode: rings or spiral, see `LayoutMode`
+/// - stagger: offset added to the start angle per ring, see `RingStagger`
+/// - shape: circles, ellipses or rounded rectangles, see `RingShape`
+/// - step_radius: radius added per ring, positive. Default: 20
+/// - min_node_spacing: minimum distance between two nodes of a ring, defines the ring capacity. Finite and
+///   positive, see `validate`. Default: 40
+/// - order: see `RingOrder`
+/// - validation: see `ValidationPolicy`
+/// - bounding_box: rectangle the finished layout is moved into, as the Cytoscape.js `boundingBox`. Default: none, the
+///   coordinates are left as computed around cx, cy
+/// - padding: space kept free inside each side of the bounding box. Default: 30
+/// - fit: scale the layout uniformly to fill the bounding box, otherwise it is only centred in it. Default: true
+/// - levels: rings of the nodes chosen by the caller, the other nodes fill the other rings, see `Levels`.
+///   Not used by `LayoutMode::Spiral`. Default: none
+/// - pinned: nodes kept at a position given by the caller, the others are placed around them, see `Pinning`.
+///   The coordinates are final, the pinned nodes stay there when the layout moves into the bounding box.
+///   Default: none
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct LayoutOptions {
+    pub backend: ComputingConfig,
+    pub gpu_min_nodes: usize,
+    #[cfg(feature = "gpu")]
+    pub gpu: GpuOptions,
+    pub cx: f32,
+    pub cy: f32,
+    pub cz: f32,
+    pub start_angle: f32,
+    pub mode: LayoutMode,
+    pub stagger: RingStagger,
+    pub shape: RingShape,
+    pub step_radius: u32,
+    pub min_node_spacing: f32,
+    pub order: RingOrder,
+    pub validation: ValidationPolicy,
+    pub bounding_box: Option<BoundingBox>,
+    pub padding: f32,
+    pub fit: bool,
+    pub levels: Vec<NodeLevel>,
+    pub pinned: Vec<PinnedNode>,
+}
+
+impl Default for LayoutOptions {
+    fn default() -> Self {
+        Self {
+            backend: ComputingConfig::default(),
+            gpu_min_nodes: 20_000,
+            #[cfg(feature = "gpu")]
+            gpu: GpuOptions::default(),
+            cx: 0.0,
+            cy: 0.0,
+            cz: 0.0,
+            start_angle: 0.0,
+            mode: LayoutMode::default(),
+            stagger: RingStagger::default(),
+            shape: RingShape::default(),
+            step_radius: 20,
+            min_node_spacing: 40.0,
+            order: RingOrder::default(),
+            validation: ValidationPolicy::default(),
+            bounding_box: None,
+            padding: 30.0,
+            fit: true,
+            levels: Vec::new(),
+            pinned: Vec::new(),
+        }
+    }
+}
+
+impl LayoutOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn backend(mut self, backend: ComputingConfig) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    pub fn gpu_min_nodes(mut self, gpu_min_nodes: usize) -> Self {
+        self.gpu_min_nodes = gpu_min_nodes;
+        self
+    }
+
+    #[cfg(feature = "gpu")]
+    pub fn gpu(mut self, gpu: GpuOptions) -> Self {
+        self.gpu = gpu;
+        self
+    }
+
+    pub fn center(mut self, cx: f32, cy: f32) -> Self {
+        self.cx = cx;
+        self.cy = cy;
+        self
+    }
+
+    pub fn cz(mut self, cz: f32) -> Self {
+        self.cz = cz;
+        self
+    }
+
+    pub fn start_angle(mut self, start_angle: f32) -> Self {
+        self.start_angle = start_angle;
+        self
+    }
+
+    pub fn mode(mut self, mode: LayoutMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn stagger(mut self, stagger: RingStagger) -> Self {
+        self.stagger = stagger;
+        self
+    }
+
+    pub fn shape(mut self, shape: RingShape) -> Self {
+        self.shape = shape;
+        self
+    }
+
+    pub fn step_radius(mut self, step_radius: u32) -> Self {
+        self.step_radius = step_radius;
+        self
+    }
+
+    pub fn min_node_spacing(mut self, min_node_spacing: f32) -> Self {
+        self.min_node_spacing = min_node_spacing;
+        self
+    }
+
+    pub fn order(mut self, order: RingOrder) -> Self {
+        self.order = order;
+        self
+    }
+
+    pub fn validation(mut self, validation: ValidationPolicy) -> Self {
+        self.validation = validation;
+        self
+    }
+
+    pub fn bounding_box(mut self, bounding_box: BoundingBox) -> Self {
+        self.bounding_box = Some(bounding_box);
+        self
+    }
+
+    pub fn padding(mut self, padding: f32) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    pub fn fit(mut self, fit: bool) -> Self {
+        self.fit = fit;
+        self
+    }
+
+    pub fn levels(mut self, levels: Vec<NodeLevel>) -> Self {
+        self.levels = levels;
+        self
+    }
+
+    pub fn pinned(mut self, pinned: Vec<PinnedNode>) -> Self {
+        self.pinned = pinned;
+        self
+    }
+
+    /// The node spacing is finite and positive and the rings grow by a positive step
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if !self.min_node_spacing.is_finite() || self.min_node_spacing <= 0.0 {
+            anyhow::bail!(
+                "min_node_spacing must be finite and positive, got {}",
+                self.min_node_spacing
+            );
+        }
+        if self.step_radius == 0 {
+            anyhow::bail!("step_radius must be positive");
+        }
+        Ok(())
+    }
+
+    /// Shape of the rings resolved against the bounding box
+    pub fn get_ring_geometry(&self) -> RingGeometry {
+        self.shape.get_geometry(self.bounding_box)
+    }
+
+    /// Rings holding the nodes, the capacity follows the shape perimeter, or the shell surface
+    pub fn get_ring_capacity(&self, total_nodes: u32) -> anyhow::Result<Vec<RingCapacity>> {
+        match self.mode {
+            LayoutMode::Shells => RingCapacity::generate_shells(
+                total_nodes,
+                Some(self.step_radius),
+                self.min_node_spacing,
+            ),
+            _ => RingCapacity::generate_with_geometry(
+                total_nodes,
+                Some(self.step_radius),
+                self.min_node_spacing,
+                &self.get_ring_geometry(),
+            ),
+        }
+    }
+
+    /// Load the options from a JSON document
+    #[cfg(feature = "formats")]
+    pub fn from_json(content: &str) -> anyhow::Result<Self> {
+        Ok(serde_json::from_str(content)?)
+    }
+
+    /// Load the options from a TOML document
+    #[cfg(feature = "formats")]
+    pub fn from_toml(content: &str) -> anyhow::Result<Self> {
+        Ok(toml::from_str(content)?)
+    }
+
+    /// Load the options from a `.json` or `.toml` file, detected from the extension
+    #[cfg(feature = "formats")]
+    pub fn from_file(path: &std::path::Path) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        match path.extension().and_then(|value| value.to_str()) {
+            Some("toml") => Self::from_toml(&content),
+            _ => Self::from_json(&content),
+        }
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+pub mod test_layout_options {
+    use super::*;
+    use crate::cpu::CpuConcentric;
+    use crate::entities::{Edge, Node, NodePositionData};
+    use crate::generators::GraphKind;
+    use crate::{ComputingConfig, ConcentricLayout};
+
+    /// Angles of the nodes of each ring, sorted, as the order inside a ring may differ per backend
+    pub fn get_ring_angles(positions: &[NodePositionData]) -> Vec<Vec<f32>> {
+        let mut rings: Vec<Vec<f32>> = Vec::new();
+        for item in positions {
+            let index = item.index as usize;
+            if rings.len() <= index {
+                rings.resize(index + 1, Vec::new());
+            }
+            rings[index].push(item.angle_degree);
+        }
+        for ring in rings.iter_mut() {
+            ring.sort_by(f32::total_cmp);
+        }
+        rings
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(LayoutOptions::new().validate().is_ok());
+        for min_node_spacing in [0.0, -5.0, f32::NAN, f32::INFINITY] {
+            let options = LayoutOptions::new().min_node_spacing(min_node_spacing);
+            assert!(options.validate().is_err(), "{}", min_node_spacing);
+        }
+        assert!(LayoutOptions::new().step_radius(0).validate().is_err());
+
+        // Checked before computing, a huge spacing fails instead of looping
+        let nodes: Vec<Node> = (1..=20).map(|id| Node { id }).collect();
+        let edges: Vec<Edge> = (2..=20)
+            .map(|id| Edge {
+                id,
+                source_id: 1,
+                target_id: id,
+            })
+            .collect();
+        for min_node_spacing in [0.0, -5.0, 1e30] {
+            let options = LayoutOptions::new().min_node_spacing(min_node_spacing);
+            assert!(CpuConcentric::new(&nodes, &edges, &options).get().is_err());
+        }
+    }
+
+    #[cfg(feature = "formats")]
+    #[test]
+    fn test_layout_options() {
+        let options = LayoutOptions::new()
+            .backend(ComputingConfig::Cpu(2))
+            .center(10.0, -5.0)
+            .start_angle(90.0)
+            .order(RingOrder::DegreeAscending);
+        let json = serde_json::to_string(&options).unwrap();
+        assert_eq!(LayoutOptions::from_json(&json).unwrap(), options);
+
+        let toml = r#"
+            backend = { cpu = 4 }
+            cx = 10.0
+            order = "degree_ascending"
+            validation = "skip"
+        "#;
+        let options = LayoutOptions::from_toml(toml).unwrap();
+        assert_eq!(options.backend, ComputingConfig::Cpu(4));
+        assert_eq!(options.cx, 10.0);
+        assert_eq!(options.cy, 0.0);
+        assert_eq!(options.step_radius, 20);
+        assert_eq!(options.order, RingOrder::DegreeAscending);
+        assert_eq!(options.validation, ValidationPolicy::Skip);
+
+        let options = LayoutOptions::from_json(r#"{ "backend": { "cpu": 8 } }"#).unwrap();
+        assert_eq!(options.backend, ComputingConfig::Cpu(8));
+
+        let options = LayoutOptions::from_toml("backend = \"auto\"\ngpu_min_nodes = 1000").unwrap();
+        assert_eq!(options.backend, ComputingConfig::Auto);
+        assert_eq!(options.gpu_min_nodes, 1000);
+
+        let options = LayoutOptions::from_toml("stagger = { random = { seed = 7 } }").unwrap();
+        assert_eq!(options.stagger, RingStagger::Random { seed: 7 });
+        let options = LayoutOptions::from_json(r#"{ "stagger": "golden_angle" }"#).unwrap();
+        assert_eq!(options.stagger, RingStagger::GoldenAngle);
+
+        let toml = r#"pinned = [{ node_id = 2, position = { ring = { index = 1, angle_degree = 90.0 } } }]"#;
+        let options = LayoutOptions::from_toml(toml).unwrap();
+        assert_eq!(options.pinned, vec![PinnedNode::ring(2, 1, 90.0)]);
+        let options =
+            LayoutOptions::from_json(r#"{ "levels": [{ "node_id": 3, "level": 2 }] }"#).unwrap();
+        assert_eq!(options.levels, vec![NodeLevel::new(3, 2)]);
+    }
+
+    #[test]
+    fn test_stagger() {
+        let graph = GraphKind::Tree {
+            nodes: 100,
+            arity: 3,
+        }
+        .generate(0);
+        let execute = |stagger: RingStagger| {
+            let options = LayoutOptions::new()
+                .backend(ComputingConfig::Cpu(2))
+                .stagger(stagger);
+            let mut layout = ConcentricLayout::new(&graph.nodes, &graph.edges, options);
+            get_ring_angles(&layout.execute_blocking().unwrap())
+        };
+        let none = execute(RingStagger::None);
+        assert!(none.len() > 3);
+        assert!(none.iter().all(|ring| ring[0] == 0.0));
+
+        let half_step = execute(RingStagger::HalfStep);
+        let step_angle = 360.0 / half_step[1].len() as f32;
+        assert!((half_step[1][0] - step_angle / 2.0).abs() < 1e-3);
+        assert_eq!(half_step[2], none[2]);
+
+        let golden_angle = execute(RingStagger::GoldenAngle);
+        let offset = (2.0 * GOLDEN_ANGLE).rem_euclid(360.0);
+        assert!(
+            golden_angle[2]
+                .iter()
+                .any(|angle| (angle - offset).abs() < 1e-3)
+        );
+
+        let random = execute(RingStagger::Random { seed: 1 });
+        assert_eq!(random, execute(RingStagger::Random { seed: 1 }));
+        assert_ne!(random, execute(RingStagger::Random { seed: 2 }));
+        assert_ne!(random, none);
+
+        #[cfg(feature = "gpu")]
+        for stagger in [
+            RingStagger::HalfStep,
+            RingStagger::GoldenAngle,
+            RingStagger::Random { seed: 1 },
+        ] {
+            let expected = execute(stagger);
+            let options = LayoutOptions::new()
+                .backend(ComputingConfig::Gpu)
+                .stagger(stagger);
+            let mut layout = ConcentricLayout::new(&graph.nodes, &graph.edges, options);
+            let rings = get_ring_angles(&layout.execute_blocking().unwrap());
+            assert_eq!(rings.len(), expected.len());
+            for (ring, expected) in rings.iter().zip(expected.iter()) {
+                assert_eq!(ring.len(), expected.len());
+                for (angle, expected) in ring.iter().zip(expected.iter()) {
+                    assert!(
+                        (angle - expected).abs() < 1e-2,
+                        "{:?}: {} {}",
+                        stagger,
+                        angle,
+                        expected
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_spiral_angle() {
+        // The angles keep their precision on large indexes
+        for index in [1_u32, 1_000, 99_999, 1_000_000, 30_000_000] {
+            let expected = (index as f64 * 137.507_764_050_037_85).rem_euclid(360.0);
+            let angle = LayoutMode::get_spiral_angle(index) as f64;
+            let difference = (angle - expected).abs();
+            assert!(
+                difference.min(360.0 - difference) < 0.05,
+                "{} {}",
+                index,
+                angle
+            );
+        }
+    }
+}
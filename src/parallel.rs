@@ -0,0 +1,49 @@
+//! Iterators used by the cpu computing.
+//! Rayon parallel iterators with the `parallel` feature, otherwise sequential iterators with the same method names.
+
+#[cfg(feature = "parallel")]
+pub mod prelude {
+    pub use rayon::current_num_threads;
+    pub use rayon::prelude::*;
+}
+
+#[cfg(not(feature = "parallel"))]
+pub mod prelude {
+    use std::cmp::Ordering;
+
+    /// Sequential computing runs on the current thread
+    pub fn current_num_threads() -> usize {
+        1
+    }
+
+    pub trait IntoParallelRefIterator<'data> {
+        type Iter: Iterator<Item = Self::Item>;
+        type Item: 'data;
+
+        fn par_iter(&'data self) -> Self::Iter;
+    }
+
+    impl<'data, T: 'data> IntoParallelRefIterator<'data> for [T] {
+        type Iter = std::slice::Iter<'data, T>;
+        type Item = &'data T;
+
+        fn par_iter(&'data self) -> Self::Iter {
+            self.iter()
+        }
+    }
+
+    pub trait ParallelSliceMut<T> {
+        fn par_sort_by<F>(&mut self, compare: F)
+        where
+            F: Fn(&T, &T) -> Ordering + Sync;
+    }
+
+    impl<T> ParallelSliceMut<T> for [T] {
+        fn par_sort_by<F>(&mut self, compare: F)
+        where
+            F: Fn(&T, &T) -> Ordering + Sync,
+        {
+            self.sort_by(compare)
+        }
+    }
+}
@@ -0,0 +1,328 @@
+//! Nodes placed by the caller, e.g. hand-placed core routers. The other nodes are laid out around them.
+
+use crate::entities::{Node, NodePositionData, RingCapacity};
+use crate::shape::RingGeometry;
+use crate::{LayoutMode, LayoutOptions};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Position a node is pinned to.
+/// Kinds:
+/// - Coordinate: exact `x`, `y` (and `cz`), the node takes the nearest slot of the layout
+/// - Ring: ring `index` at `angle_degree`, the angle follows the ring shape as the start angle does.
+///   The ring 0 is the centre. Not available in `LayoutMode::Spiral`, which has no rings.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum PinnedPosition {
+    Coordinate { x: f32, y: f32 },
+    Ring { index: u32, angle_degree: f32 },
+}
+
+/// Node kept at a position given by the caller, see `LayoutOptions::pinned`
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PinnedNode {
+    pub node_id: u32,
+    pub position: PinnedPosition,
+}
+
+impl PinnedNode {
+    pub fn coordinate(node_id: u32, x: f32, y: f32) -> Self {
+        Self {
+            node_id,
+            position: PinnedPosition::Coordinate { x, y },
+        }
+    }
+
+    pub fn ring(node_id: u32, index: u32, angle_degree: f32) -> Self {
+        Self {
+            node_id,
+            position: PinnedPosition::Ring {
+                index,
+                angle_degree,
+            },
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Pinning {}
+
+impl Pinning {
+    /// The pinned nodes are in the graph, pinned once and at finite positions
+    pub fn validate(pinned: &[PinnedNode], nodes: &[Node]) -> anyhow::Result<()> {
+        if pinned.is_empty() {
+            return Ok(());
+        }
+        let node_ids: HashSet<u32> = nodes.iter().map(|node| node.id).collect();
+        let mut pinned_ids = HashSet::with_capacity(pinned.len());
+        for item in pinned {
+            if !node_ids.contains(&item.node_id) {
+                anyhow::bail!("pinned node {} is not in the graph", item.node_id);
+            }
+            if !pinned_ids.insert(item.node_id) {
+                anyhow::bail!("node {} is pinned more than once", item.node_id);
+            }
+            let finite = match item.position {
+                PinnedPosition::Coordinate { x, y } => x.is_finite() && y.is_finite(),
+                PinnedPosition::Ring { angle_degree, .. } => angle_degree.is_finite(),
+            };
+            if !finite {
+                anyhow::bail!("pinned node {} has a non finite position", item.node_id);
+            }
+        }
+        Ok(())
+    }
+
+    /// Move the `options.pinned` nodes to their positions, marked with `pinned`.
+    /// The positions are in the slot order of the layout (the sorted order), after `Viewport::fit`:
+    /// the coordinates are final and the rings are those of the fitted layout, `scale` times the
+    /// computed ones (see `Viewport::get_scale`). Each pinned node takes
+    /// the nearest free slot of its ring (of the layout for a coordinate), the other nodes fill the
+    /// remaining slots in order. A pinned node finding no free slot, on a ring full of pinned nodes,
+    /// takes none and the last slot stays empty.
+    /// The pinned nodes are searched among all the slots, a pin costs the number of nodes.
+    pub fn apply(
+        positions: &mut Vec<NodePositionData>,
+        options: &LayoutOptions,
+        scale: f32,
+    ) -> anyhow::Result<()> {
+        if options.pinned.is_empty() {
+            return Ok(());
+        }
+        let geometry = match options.mode {
+            LayoutMode::Shells => RingGeometry::Circle,
+            _ => options.get_ring_geometry(),
+        };
+        // Centre of the rings, moved by the bounding box
+        let (cx, cy) = positions
+            .first()
+            .map_or((options.cx, options.cy), |slot| (slot.cx, slot.cy));
+        let mut reserved: Vec<Option<NodePositionData>> = vec![None; positions.len()];
+        let mut unplaced = Vec::new();
+        for item in options.pinned.iter() {
+            let ring = match item.position {
+                PinnedPosition::Ring { .. } if options.mode == LayoutMode::Spiral => {
+                    anyhow::bail!(
+                        "node {} is pinned to a ring, the spiral mode has no rings",
+                        item.node_id
+                    );
+                }
+                PinnedPosition::Ring { index, .. } => Some(index),
+                PinnedPosition::Coordinate { .. } => None,
+            };
+            let candidates = || {
+                positions
+                    .iter()
+                    .enumerate()
+                    .filter(move |(_, slot)| ring.is_none_or(|index| slot.index == index))
+            };
+            let (x, y, angle_degree) = match item.position {
+                PinnedPosition::Coordinate { x, y } => {
+                    let angle_degree = match cx == x && cy == y {
+                        true => 0.0,
+                        false => (y - cy).atan2(x - cx).to_degrees().rem_euclid(360_f32),
+                    };
+                    (x, y, angle_degree)
+                }
+                PinnedPosition::Ring {
+                    index,
+                    angle_degree,
+                } => {
+                    let Some((_, slot)) = candidates().next() else {
+                        anyhow::bail!(
+                            "node {} is pinned to the ring {}, the layout has {} rings",
+                            item.node_id,
+                            index,
+                            positions
+                                .iter()
+                                .map(|slot| slot.index + 1)
+                                .max()
+                                .unwrap_or(0)
+                        );
+                    };
+                    // The fitted radius is rounded, the ring radius is scaled instead
+                    let radius = RingCapacity::get_ring_radius(options.step_radius, slot.index);
+                    let (dx, dy, angle_degree) =
+                        geometry.get_position(radius as f32 * scale, angle_degree);
+                    (cx + dx, cy + dy, angle_degree.rem_euclid(360_f32))
+                }
+            };
+            let distance = |slot: &NodePositionData| {
+                (slot.x - x).powi(2) + (slot.y - y).powi(2) + (slot.z - options.cz).powi(2)
+            };
+            let nearest = |free: bool| {
+                candidates()
+                    .filter(|(slot_index, _)| !free || reserved[*slot_index].is_none())
+                    .min_by(|(_, a), (_, b)| distance(a).total_cmp(&distance(b)))
+                    .map(|(slot_index, slot)| (slot_index, *slot))
+            };
+            let (slot_index, slot) = match nearest(true) {
+                Some((slot_index, slot)) => (Some(slot_index), slot),
+                None => (
+                    None,
+                    nearest(false).map(|(_, slot)| slot).unwrap_or_default(),
+                ),
+            };
+            let position = NodePositionData {
+                index: ring.unwrap_or(slot.index),
+                angle_degree,
+                angle_radian: angle_degree.to_radians(),
+                cx,
+                cy,
+                x,
+                y,
+                node_id: item.node_id,
+                cz: options.cz,
+                z: options.cz,
+                polar_degree: 90.0,
+                azimuth_degree: angle_degree,
+                pinned: true,
+                ..slot
+            };
+            match slot_index {
+                Some(slot_index) => reserved[slot_index] = Some(position),
+                None => unplaced.push(position),
+            }
+        }
+        let pinned_ids: HashSet<u32> = options.pinned.iter().map(|item| item.node_id).collect();
+        let mut free_ids = positions
+            .iter()
+            .map(|item| item.node_id)
+            .filter(|node_id| !pinned_ids.contains(node_id))
+            .collect::<Vec<u32>>()
+            .into_iter();
+        let mut result: Vec<NodePositionData> = positions
+            .iter()
+            .zip(reserved)
+            .filter_map(|(slot, pinned)| match pinned {
+                Some(pinned) => Some(pinned),
+                None => free_ids
+                    .next()
+                    .map(|node_id| NodePositionData { node_id, ..*slot }),
+            })
+            .collect();
+        result.extend(unplaced);
+        result.sort_by_key(|item| item.index);
+        *positions = result;
+        Ok(())
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+pub mod test_pinning {
+    use super::*;
+    use crate::BoundingBox;
+    use crate::cpu::CpuConcentric;
+    use crate::entities::Edge;
+
+    /// Star of a hub and its leaves, the hub is at the centre and the leaves on the ring 1
+    fn get_star(leaves: u32) -> (Vec<Node>, Vec<Edge>) {
+        let nodes = (1..=leaves + 1).map(|id| Node { id }).collect();
+        let edges = (2..=leaves + 1)
+            .map(|id| Edge {
+                id,
+                source_id: 1,
+                target_id: id,
+            })
+            .collect();
+        (nodes, edges)
+    }
+
+    #[tokio::test]
+    async fn test_pinning() {
+        // 9 slots on the ring 1 of radius 120, every 40 degrees
+        let (nodes, edges) = get_star(9);
+        let options = LayoutOptions::new().step_radius(100).pinned(vec![
+            PinnedNode::ring(4, 1, 90.0),
+            PinnedNode::coordinate(7, 500.0, 400.0),
+        ]);
+        let result = CpuConcentric::new(&nodes, &edges, &options).get().unwrap();
+        assert_eq!(result.len(), nodes.len());
+        let get = |node_id: u32| *result.iter().find(|item| item.node_id == node_id).unwrap();
+
+        let ring = get(4);
+        assert_eq!((ring.index, ring.radius, ring.pinned), (1, 120, true));
+        assert!(ring.x.abs() < 1e-3 && (ring.y - 120.0).abs() < 1e-3);
+        assert_eq!(ring.angle_degree, 90.0);
+        let coordinate = get(7);
+        assert_eq!((coordinate.x, coordinate.y), (500.0, 400.0));
+        assert_eq!((coordinate.index, coordinate.pinned), (1, true));
+        assert_eq!((get(1).index, get(1).pinned), (0, false));
+
+        // The other leaves skip the slots at 80 and 40 degrees, nearest to the pinned nodes
+        let mut angles: Vec<f32> = result
+            .iter()
+            .filter(|item| item.index == 1 && !item.pinned)
+            .map(|item| item.angle_degree)
+            .collect();
+        angles.sort_by(f32::total_cmp);
+        let expected = [0.0, 120.0, 160.0, 200.0, 240.0, 280.0, 320.0];
+        assert_eq!(angles.len(), expected.len());
+        for (angle, expected) in angles.iter().zip(expected) {
+            assert!((angle - expected).abs() < 0.01, "{:?}", angles);
+        }
+
+        #[cfg(feature = "gpu")]
+        {
+            let mut layout = crate::gpu::GpuConcentric::new(&nodes, &edges, &options);
+            let gpu = layout.get().await.unwrap();
+            assert_eq!(gpu.len(), result.len());
+            for (gpu, cpu) in gpu.iter().zip(result.iter()) {
+                assert_eq!(
+                    (gpu.node_id, gpu.index, gpu.pinned),
+                    (cpu.node_id, cpu.index, cpu.pinned)
+                );
+                assert!((gpu.x - cpu.x).abs() < 0.01 && (gpu.y - cpu.y).abs() < 0.01);
+            }
+        }
+
+        // The pinned nodes stay put when the layout is fitted into a bounding box, a ring pin
+        // follows the fitted ring
+        let fitted = options
+            .clone()
+            .bounding_box(BoundingBox::new(1000.0, 1000.0, 400.0, 400.0))
+            .pinned(vec![
+                PinnedNode::coordinate(7, 100.0, 50.0),
+                PinnedNode::ring(4, 1, 90.0),
+            ]);
+        let mut layout = CpuConcentric::new(&nodes, &edges, &fitted);
+        let result = layout.get().unwrap();
+        let ring_radius = RingCapacity::get_ring_radius(options.step_radius, 1) as f32;
+        let scale = layout.stats.fit_scale.unwrap();
+        let get = |node_id: u32| *result.iter().find(|item| item.node_id == node_id).unwrap();
+        assert_eq!((get(7).x, get(7).y, get(7).pinned), (100.0, 50.0, true));
+        let (hub, ring) = (get(1), get(4));
+        assert!(hub.x > 1000.0 && hub.y > 1000.0);
+        assert!((ring.x - hub.x).abs() < 1e-3);
+        assert!((ring.y - hub.y - ring_radius * scale).abs() < 1e-3);
+        for item in result.iter().filter(|item| !item.pinned) {
+            assert!((1000.0..=1400.0).contains(&item.x) && (1000.0..=1400.0).contains(&item.y));
+        }
+
+        // In a small bounding box the rounded radius is far from the fitted ring
+        let small = fitted
+            .clone()
+            .bounding_box(BoundingBox::new(0.0, 0.0, 10.0, 10.0))
+            .pinned(vec![PinnedNode::ring(4, 1, 90.0)]);
+        let mut layout = CpuConcentric::new(&nodes, &edges, &small);
+        let result = layout.get().unwrap();
+        let scale = layout.stats.fit_scale.unwrap();
+        assert!(scale < 0.1);
+        let get = |node_id: u32| *result.iter().find(|item| item.node_id == node_id).unwrap();
+        let (hub, ring) = (get(1), get(4));
+        assert!((ring.x - hub.x).abs() < 1e-3);
+        assert!((ring.y - hub.y - ring_radius * scale).abs() < 1e-3);
+
+        let options = options.pinned(vec![PinnedNode::ring(4, 5, 0.0)]);
+        assert!(CpuConcentric::new(&nodes, &edges, &options).get().is_err());
+        let options = options.pinned(vec![PinnedNode::coordinate(42, 0.0, 0.0)]);
+        assert!(CpuConcentric::new(&nodes, &edges, &options).get().is_err());
+        let options = options
+            .mode(LayoutMode::Spiral)
+            .pinned(vec![PinnedNode::ring(4, 1, 0.0)]);
+        assert!(CpuConcentric::new(&nodes, &edges, &options).get().is_err());
+    }
+}
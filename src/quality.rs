@@ -0,0 +1,506 @@
+//! Objective metrics of a computed layout, e.g. to compare option sets and backends.
+
+use crate::LayoutOptions;
+use crate::entities::{Edge, NodePositionData};
+use crate::parallel::prelude::*;
+use crate::shape::RingGeometry;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Default node diameter, the default node width of Cytoscape.js
+pub const DEFAULT_NODE_SIZE: f32 = 30.0;
+
+/// Options of the quality metrics.
+/// Parameters:
+/// - node_size: diameter of the nodes without an entry in `node_sizes`. Default: 30
+/// - node_sizes: diameter per node id
+/// - min_node_spacing: spacing used by the layout, defines the ring capacities. Default: 40
+/// - ring_geometry: shape of the rings used by the layout, defines the ring capacities. Default: circle
+/// - fit_scale: factor of the fit into the bounding box of the layout, scales `min_node_spacing` to
+///   the fitted rings, see `LayoutStats::fit_scale`. Default: 1
+#[derive(Debug, Clone, PartialEq)]
+pub struct QualityOptions {
+    pub node_size: f32,
+    pub node_sizes: HashMap<u32, f32>,
+    pub min_node_spacing: f32,
+    pub ring_geometry: RingGeometry,
+    pub fit_scale: f32,
+}
+
+impl Default for QualityOptions {
+    fn default() -> Self {
+        Self {
+            node_size: DEFAULT_NODE_SIZE,
+            node_sizes: HashMap::new(),
+            min_node_spacing: LayoutOptions::default().min_node_spacing,
+            ring_geometry: RingGeometry::default(),
+            fit_scale: 1.0,
+        }
+    }
+}
+
+impl QualityOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Options matching the spacing and the ring shape of a layout
+    pub fn from_layout(options: &LayoutOptions) -> Self {
+        Self::new()
+            .min_node_spacing(options.min_node_spacing)
+            .ring_geometry(options.get_ring_geometry())
+    }
+
+    pub fn node_size(mut self, node_size: f32) -> Self {
+        self.node_size = node_size;
+        self
+    }
+
+    pub fn node_sizes(mut self, node_sizes: HashMap<u32, f32>) -> Self {
+        self.node_sizes = node_sizes;
+        self
+    }
+
+    pub fn min_node_spacing(mut self, min_node_spacing: f32) -> Self {
+        self.min_node_spacing = min_node_spacing;
+        self
+    }
+
+    pub fn ring_geometry(mut self, ring_geometry: RingGeometry) -> Self {
+        self.ring_geometry = ring_geometry;
+        self
+    }
+
+    pub fn fit_scale(mut self, fit_scale: f32) -> Self {
+        self.fit_scale = fit_scale;
+        self
+    }
+
+    fn get_node_radius(&self, node_id: u32) -> f32 {
+        self.node_sizes
+            .get(&node_id)
+            .copied()
+            .unwrap_or(self.node_size)
+            / 2.0
+    }
+}
+
+/// Lengths of the edges
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct EdgeLengthStats {
+    pub min: f32,
+    pub max: f32,
+    pub mean: f32,
+    pub std_dev: f32,
+}
+
+/// Quality metrics of a layout. Each pair of connected nodes is counted once, self loops are ignored.
+/// Parameters:
+/// - nodes, edges: positioned nodes and connected pairs of nodes
+/// - overlaps: pairs of nodes whose discs intersect, see `QualityOptions`
+/// - min_neighbour_distance, avg_neighbour_distance: distance of each node to its nearest node
+/// - edge_length: see `EdgeLengthStats`
+/// - crossings: pairs of edges crossing each other, edges sharing a node never cross
+/// - min_angular_resolution, avg_angular_resolution: smallest angle in degrees between the edges of a node,
+///   the minimum and the mean over the nodes with at least 2 edges
+/// - ring_occupancy: positioned nodes per ring place, over the used rings
+///
+/// The metrics without any node or edge to measure are 0.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LayoutQuality {
+    pub nodes: usize,
+    pub edges: usize,
+    pub overlaps: usize,
+    pub min_neighbour_distance: f32,
+    pub avg_neighbour_distance: f32,
+    pub edge_length: EdgeLengthStats,
+    pub crossings: usize,
+    pub min_angular_resolution: f32,
+    pub avg_angular_resolution: f32,
+    pub ring_occupancy: f32,
+}
+
+/// Point of the x sorted scans
+#[derive(Debug, Clone, Copy)]
+struct Point {
+    x: f64,
+    y: f64,
+    radius: f64,
+}
+
+/// Edge of the crossing test, from the left end `a` to the right end `b`
+#[derive(Debug, Clone, Copy)]
+struct Segment {
+    a: (f64, f64),
+    b: (f64, f64),
+    min_y: f64,
+    max_y: f64,
+    nodes: (usize, usize),
+}
+
+impl LayoutQuality {
+    pub fn compute(
+        positions: &[NodePositionData],
+        edges: &[Edge],
+        options: &QualityOptions,
+    ) -> LayoutQuality {
+        let indexes: HashMap<u32, usize> = positions
+            .iter()
+            .enumerate()
+            .map(|(index, item)| (item.node_id, index))
+            .collect();
+        let mut pairs: Vec<(usize, usize)> = edges
+            .iter()
+            .filter_map(|edge| {
+                let source = *indexes.get(&edge.source_id)?;
+                let target = *indexes.get(&edge.target_id)?;
+                (source != target).then_some((source.min(target), source.max(target)))
+            })
+            .collect();
+        pairs.par_sort_by(|a, b| a.cmp(b));
+        pairs.dedup();
+
+        let mut points: Vec<Point> = positions
+            .iter()
+            .map(|item| Point {
+                x: item.x as f64,
+                y: item.y as f64,
+                radius: options.get_node_radius(item.node_id) as f64,
+            })
+            .collect();
+        points.par_sort_by(|a, b| a.x.total_cmp(&b.x));
+        let (min_neighbour_distance, avg_neighbour_distance) =
+            Self::get_neighbour_distances(&points);
+        let (min_angular_resolution, avg_angular_resolution) =
+            Self::get_angular_resolution(positions, &pairs);
+        LayoutQuality {
+            nodes: positions.len(),
+            edges: pairs.len(),
+            overlaps: Self::count_overlaps(&points),
+            min_neighbour_distance,
+            avg_neighbour_distance,
+            edge_length: Self::get_edge_lengths(positions, &pairs),
+            crossings: Self::count_pairwise_crossings(positions, &pairs),
+            min_angular_resolution,
+            avg_angular_resolution,
+            ring_occupancy: Self::get_ring_occupancy(positions, options),
+        }
+    }
+
+    /// Scan of the x sorted points, stopped once the x distance exceeds the largest possible overlap
+    fn count_overlaps(points: &[Point]) -> usize {
+        let max_radius = points.iter().map(|point| point.radius).fold(0.0, f64::max);
+        let indexes: Vec<usize> = (0..points.len()).collect();
+        indexes
+            .par_iter()
+            .map(|index| {
+                let point = &points[*index];
+                points[index + 1..]
+                    .iter()
+                    .take_while(|other| other.x - point.x < point.radius + max_radius)
+                    .filter(|other| {
+                        (other.x - point.x).hypot(other.y - point.y) < point.radius + other.radius
+                    })
+                    .count()
+            })
+            .sum()
+    }
+
+    /// Nearest node of each node, scanning the x sorted points in both directions
+    fn get_neighbour_distances(points: &[Point]) -> (f32, f32) {
+        if points.len() < 2 {
+            return (0.0, 0.0);
+        }
+        let indexes: Vec<usize> = (0..points.len()).collect();
+        let distances: Vec<f64> = indexes
+            .par_iter()
+            .map(|index| {
+                let point = &points[*index];
+                let mut nearest = f64::INFINITY;
+                for other in points[index + 1..].iter() {
+                    if other.x - point.x >= nearest {
+                        break;
+                    }
+                    nearest = nearest.min((other.x - point.x).hypot(other.y - point.y));
+                }
+                for other in points[..*index].iter().rev() {
+                    if point.x - other.x >= nearest {
+                        break;
+                    }
+                    nearest = nearest.min((other.x - point.x).hypot(other.y - point.y));
+                }
+                nearest
+            })
+            .collect();
+        let min = distances.iter().copied().fold(f64::INFINITY, f64::min);
+        let avg = distances.iter().sum::<f64>() / distances.len() as f64;
+        (min as f32, avg as f32)
+    }
+
+    fn get_edge_lengths(
+        positions: &[NodePositionData],
+        pairs: &[(usize, usize)],
+    ) -> EdgeLengthStats {
+        if pairs.is_empty() {
+            return EdgeLengthStats::default();
+        }
+        let lengths: Vec<f64> = pairs
+            .par_iter()
+            .map(|(source, target)| {
+                let (source, target) = (&positions[*source], &positions[*target]);
+                ((target.x - source.x) as f64).hypot((target.y - source.y) as f64)
+            })
+            .collect();
+        let mean = lengths.iter().sum::<f64>() / lengths.len() as f64;
+        let variance = lengths
+            .iter()
+            .map(|length| (length - mean).powi(2))
+            .sum::<f64>()
+            / lengths.len() as f64;
+        EdgeLengthStats {
+            min: lengths.iter().copied().fold(f64::INFINITY, f64::min) as f32,
+            max: lengths.iter().copied().fold(0.0, f64::max) as f32,
+            mean: mean as f32,
+            std_dev: variance.sqrt() as f32,
+        }
+    }
+
+    /// Pairwise test of the edges sorted by their left end: each edge is only tested against the
+    /// following edges starting before its right end and overlapping it on y.
+    /// Quadratic in the number of edges when every edge spans the layout, e.g. the long edges through
+    /// the centre.
+    fn count_pairwise_crossings(positions: &[NodePositionData], pairs: &[(usize, usize)]) -> usize {
+        let mut segments: Vec<Segment> = pairs
+            .iter()
+            .map(|(source, target)| {
+                let source_point = (positions[*source].x as f64, positions[*source].y as f64);
+                let target_point = (positions[*target].x as f64, positions[*target].y as f64);
+                let (a, b) = match source_point.0 <= target_point.0 {
+                    true => (source_point, target_point),
+                    false => (target_point, source_point),
+                };
+                Segment {
+                    a,
+                    b,
+                    min_y: a.1.min(b.1),
+                    max_y: a.1.max(b.1),
+                    nodes: (*source, *target),
+                }
+            })
+            .collect();
+        segments.par_sort_by(|left, right| left.a.0.total_cmp(&right.a.0));
+        let indexes: Vec<usize> = (0..segments.len()).collect();
+        indexes
+            .par_iter()
+            .map(|index| {
+                let segment = &segments[*index];
+                let following = &segments[index + 1..];
+                let end = following.partition_point(|other| other.a.0 <= segment.b.0);
+                following[..end]
+                    .iter()
+                    .filter(|other| {
+                        other.min_y <= segment.max_y
+                            && segment.min_y <= other.max_y
+                            && Self::is_crossing(segment, other)
+                    })
+                    .count()
+            })
+            .sum()
+    }
+
+    /// Proper intersection, the edges sharing a node or touching each other do not cross
+    fn is_crossing(left: &Segment, right: &Segment) -> bool {
+        let (l0, l1) = left.nodes;
+        let (r0, r1) = right.nodes;
+        if l0 == r0 || l0 == r1 || l1 == r0 || l1 == r1 {
+            return false;
+        }
+        let orientation = |p: (f64, f64), q: (f64, f64), r: (f64, f64)| {
+            (q.0 - p.0) * (r.1 - p.1) - (q.1 - p.1) * (r.0 - p.0)
+        };
+        let d1 = orientation(left.a, left.b, right.a);
+        let d2 = orientation(left.a, left.b, right.b);
+        let d3 = orientation(right.a, right.b, left.a);
+        let d4 = orientation(right.a, right.b, left.b);
+        d1 * d2 < 0.0 && d3 * d4 < 0.0
+    }
+
+    fn get_angular_resolution(
+        positions: &[NodePositionData],
+        pairs: &[(usize, usize)],
+    ) -> (f32, f32) {
+        let mut neighbours: Vec<Vec<usize>> = vec![Vec::new(); positions.len()];
+        for (source, target) in pairs.iter() {
+            neighbours[*source].push(*target);
+            neighbours[*target].push(*source);
+        }
+        let indexes: Vec<usize> = (0..positions.len()).collect();
+        let resolutions: Vec<f64> = indexes
+            .par_iter()
+            .filter_map(|index| {
+                let node = &positions[*index];
+                let mut angles: Vec<f64> = neighbours[*index]
+                    .iter()
+                    .map(|other| &positions[*other])
+                    .filter(|other| other.x != node.x || other.y != node.y)
+                    .map(|other| ((other.y - node.y) as f64).atan2((other.x - node.x) as f64))
+                    .collect();
+                if angles.len() < 2 {
+                    return None;
+                }
+                angles.sort_by(|a, b| a.total_cmp(b));
+                let wrap = angles[0] + std::f64::consts::TAU - angles[angles.len() - 1];
+                let min = angles
+                    .windows(2)
+                    .map(|pair| pair[1] - pair[0])
+                    .fold(wrap, f64::min);
+                Some(min.to_degrees())
+            })
+            .collect();
+        if resolutions.is_empty() {
+            return (0.0, 0.0);
+        }
+        let min = resolutions.iter().copied().fold(f64::INFINITY, f64::min);
+        let avg = resolutions.iter().sum::<f64>() / resolutions.len() as f64;
+        (min as f32, avg as f32)
+    }
+
+    /// The capacity of a ring follows `RingCapacity`, the centre holds a single node.
+    /// The radiuses are the fitted ones, so is the spacing.
+    fn get_ring_occupancy(positions: &[NodePositionData], options: &QualityOptions) -> f32 {
+        let min_node_spacing = options.min_node_spacing * options.fit_scale;
+        let mut rings: HashMap<(u32, u32), usize> = HashMap::new();
+        for item in positions.iter() {
+            *rings.entry((item.index, item.radius)).or_default() += 1;
+        }
+        let capacity: u64 = rings
+            .keys()
+            .map(|(_, radius)| match radius {
+                0 => 1,
+                radius => options
+                    .ring_geometry
+                    .get_max_nodes(*radius, min_node_spacing)
+                    .max(1) as u64,
+            })
+            .sum();
+        match capacity {
+            0 => 0.0,
+            capacity => (positions.len() as f64 / capacity as f64) as f32,
+        }
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+pub mod test_layout_quality {
+    use super::*;
+    use crate::generators::GraphKind;
+    use crate::{BoundingBox, ComputingConfig, ConcentricLayout};
+
+    fn get_position(node_id: u32, x: f32, y: f32) -> NodePositionData {
+        NodePositionData {
+            index: 0,
+            radius: 0,
+            angle_degree: 0.0,
+            angle_radian: 0.0,
+            cx: 0.0,
+            cy: 0.0,
+            x,
+            y,
+            node_id,
+            cz: 0.0,
+            z: 0.0,
+            polar_degree: 90.0,
+            azimuth_degree: 0.0,
+            pinned: false,
+        }
+    }
+
+    fn get_edge(id: u32, source_id: u32, target_id: u32) -> Edge {
+        Edge {
+            id,
+            source_id,
+            target_id,
+        }
+    }
+
+    #[test]
+    fn test_quality_square() {
+        // Unit square scaled by 100 with both diagonals, and a node overlapping the corner 1
+        let positions = vec![
+            get_position(1, 0.0, 0.0),
+            get_position(2, 100.0, 0.0),
+            get_position(3, 100.0, 100.0),
+            get_position(4, 0.0, 100.0),
+            get_position(5, 10.0, 0.0),
+        ];
+        let edges = vec![
+            get_edge(1, 1, 2),
+            get_edge(2, 2, 3),
+            get_edge(3, 3, 4),
+            get_edge(4, 4, 1),
+            get_edge(5, 1, 3),
+            get_edge(6, 2, 4),
+            get_edge(7, 4, 2),
+            get_edge(8, 5, 5),
+        ];
+        let quality = LayoutQuality::compute(&positions, &edges, &QualityOptions::new());
+        assert_eq!(quality.nodes, 5);
+        assert_eq!(quality.edges, 6);
+        assert_eq!(quality.overlaps, 1);
+        assert_eq!(quality.min_neighbour_distance, 10.0);
+        assert_eq!(quality.crossings, 1);
+        assert_eq!(quality.edge_length.min, 100.0);
+        assert!((quality.edge_length.max - 141.42136).abs() < 0.001);
+        assert!((quality.min_angular_resolution - 45.0).abs() < 0.001);
+        assert!((quality.avg_angular_resolution - 45.0).abs() < 0.001);
+
+        let quality =
+            LayoutQuality::compute(&positions, &edges, &QualityOptions::new().node_size(5.0));
+        assert_eq!(quality.overlaps, 0);
+        let quality = LayoutQuality::compute(&[], &[], &QualityOptions::new());
+        assert_eq!(quality, LayoutQuality::default());
+    }
+
+    #[tokio::test]
+    async fn test_quality_layout() {
+        let graph = GraphKind::Telco {
+            nodes: 200,
+            fan_out: 4,
+        }
+        .generate(0);
+        let options = LayoutOptions::new().backend(ComputingConfig::Cpu(2));
+        let quality_options = QualityOptions::from_layout(&options);
+        let mut layout = ConcentricLayout::new(&graph.nodes, &graph.edges, options);
+        let positions = layout.execute().await.unwrap();
+        let quality = LayoutQuality::compute(&positions, &graph.edges, &quality_options);
+        assert_eq!(quality.nodes, 200);
+        assert_eq!(quality.edges, graph.edges.len());
+        // The ring spacing keeps the default nodes apart
+        assert_eq!(quality.overlaps, 0);
+        assert!(quality.min_neighbour_distance >= 30.0, "{:?}", quality);
+        assert!(quality.ring_occupancy > 0.5 && quality.ring_occupancy <= 1.0);
+        assert!(quality.crossings > 0);
+        assert!(quality.edge_length.min <= quality.edge_length.mean);
+        assert!(quality.edge_length.mean <= quality.edge_length.max);
+
+        // The occupancy of a layout fitted into a small bounding box uses the fitted spacing
+        let options = LayoutOptions::new()
+            .backend(ComputingConfig::Cpu(2))
+            .bounding_box(BoundingBox::new(0.0, 0.0, 200.0, 200.0));
+        let mut layout = ConcentricLayout::new(&graph.nodes, &graph.edges, options);
+        let fitted = layout.execute().await.unwrap();
+        let fit_scale = layout.stats.as_ref().unwrap().fit_scale.unwrap();
+        assert!(fit_scale < 0.5);
+        let fitted_options = QualityOptions::from_layout(&layout.options).fit_scale(fit_scale);
+        let fitted = LayoutQuality::compute(&fitted, &graph.edges, &fitted_options);
+        assert!(
+            (fitted.ring_occupancy - quality.ring_occupancy).abs() < 0.1,
+            "{} {}",
+            fitted.ring_occupancy,
+            quality.ring_occupancy
+        );
+    }
+}
@@ -0,0 +1,210 @@
+use crate::compound::{Compound, CompoundNode};
+use crate::entities::{GraphLayoutData, NodePositionData, PresetPosition};
+use crate::formats::{Cytoscape, Json};
+use crate::server::ServerState;
+use crate::{
+    BoundingBox, ComputingConfig, ConcentricLayout, LayoutOptions, LayoutStats, Levels, Pinning,
+    Timer, ValidationError,
+};
+use axum::Json as JsonResponse;
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+
+/// Backend requested by the client
+/// - Auto: gpu for large graphs when the server has an adapter, otherwise cpu. See `ComputingConfig::Auto`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum LayoutBackend {
+    Cpu,
+    Gpu,
+    #[default]
+    Auto,
+}
+
+/// Options of a layout request: the `LayoutOptions` fields with the backend of the request.
+/// Missing fields take their default value. The gpu adapter and the threads are the server's.
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct LayoutRequestOptions {
+    #[serde(default)]
+    pub backend: LayoutBackend,
+    #[serde(flatten)]
+    pub layout: LayoutOptions,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LayoutResponse {
+    pub backend: LayoutBackend,
+    pub timer: Option<Timer>,
+    pub stats: Option<LayoutStats>,
+    pub bounding_box: Option<BoundingBox>,
+    pub positions: Vec<NodePositionData>,
+    pub preset: HashMap<String, PresetPosition>,
+    /// Discs of the parent nodes, empty without `parents` (Cytoscape.js `parent`)
+    #[serde(default)]
+    pub compounds: Vec<CompoundNode>,
+}
+
+fn error_response(status: StatusCode, message: impl ToString) -> Response {
+    (
+        status,
+        JsonResponse(json!({ "error": message.to_string() })),
+    )
+        .into_response()
+}
+
+/// Read the graph of the request body. Either native (`nodes`, `edges`) or Cytoscape.js `elements`.
+fn get_graph(body: &Value) -> anyhow::Result<GraphLayoutData> {
+    if body.is_array() || body.get("elements").is_some() {
+        Cytoscape::from_value(body)
+    } else {
+        Json::from_value(body)
+    }
+}
+
+/// GET /health
+pub async fn health() -> Response {
+    JsonResponse(json!({ "status": "ok" })).into_response()
+}
+
+/// GET /capabilities
+pub async fn capabilities(State(state): State<ServerState>) -> Response {
+    let gpu = match &state.pool.gpu_adapter {
+        Some(gpu_adapter) => {
+            let info = gpu_adapter.adapter.get_info();
+            json!({
+                "available": true,
+                "adapter": info.name,
+                "backend": info.backend.to_string(),
+                "device_type": format!("{:?}", info.device_type),
+            })
+        }
+        None => json!({ "available": false }),
+    };
+    JsonResponse(json!({
+        "gpu": gpu,
+        "cpu": { "threads": state.pool.thread_pool.current_num_threads() },
+        "input_formats": ["json", "cytoscape"],
+        "limits": {
+            "max_body_bytes": state.config.max_body_bytes,
+            "max_nodes": state.config.max_nodes,
+            "max_edges": state.config.max_edges,
+            "max_concurrent_layouts": state.config.max_concurrent_layouts,
+        },
+    }))
+    .into_response()
+}
+
+/// POST /layout
+/// Body: the graph (native or Cytoscape.js elements) with an optional `options` object. See `LayoutRequestOptions`.
+pub async fn layout(State(state): State<ServerState>, body: Bytes) -> Response {
+    let body = match serde_json::from_slice::<Value>(&body) {
+        Ok(body) => body,
+        Err(error) => return error_response(StatusCode::BAD_REQUEST, error),
+    };
+    let options = match body.get("options") {
+        Some(options) => match LayoutRequestOptions::deserialize(options) {
+            Ok(options) => options,
+            Err(error) => return error_response(StatusCode::BAD_REQUEST, error),
+        },
+        None => LayoutRequestOptions::default(),
+    };
+    let graph = match get_graph(&body) {
+        Ok(graph) => graph,
+        Err(error) => return error_response(StatusCode::BAD_REQUEST, error),
+    };
+    if graph.nodes.len() > state.config.max_nodes || graph.edges.len() > state.config.max_edges {
+        return error_response(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!(
+                "the graph exceeds the limits of {} nodes and {} edges",
+                state.config.max_nodes, state.config.max_edges
+            ),
+        );
+    }
+    if let Some(bounding_box) = options.layout.bounding_box
+        && !bounding_box.is_valid()
+    {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            "the bounding box width and height must be positive",
+        );
+    }
+    if let Err(error) = options
+        .layout
+        .validate()
+        .and_then(|_| Levels::validate(&options.layout.levels, &graph.nodes))
+        .and_then(|_| Pinning::validate(&options.layout.pinned, &graph.nodes))
+    {
+        return error_response(StatusCode::BAD_REQUEST, error);
+    }
+    if let Err(error) = Compound::validate(&graph.parents, &graph.nodes) {
+        return error_response(StatusCode::BAD_REQUEST, error);
+    }
+    let cpu = ComputingConfig::Cpu(state.pool.thread_pool.current_num_threads());
+    let computing_config = match (options.backend, &state.pool.gpu_adapter) {
+        (LayoutBackend::Gpu, None) => {
+            return error_response(StatusCode::BAD_REQUEST, "gpu backend is not available");
+        }
+        (LayoutBackend::Cpu, _) | (LayoutBackend::Auto, None) => cpu,
+        (LayoutBackend::Gpu, Some(_)) => ComputingConfig::Gpu,
+        (LayoutBackend::Auto, Some(_)) => ComputingConfig::Auto,
+    };
+
+    let permit = match state.pool.permits.clone().acquire_owned().await {
+        Ok(permit) => permit,
+        Err(error) => return error_response(StatusCode::SERVICE_UNAVAILABLE, error),
+    };
+    let layout_options = options.layout.backend(computing_config);
+    let thread_pool = state.pool.thread_pool.clone();
+    let gpu_adapter = state.pool.gpu_adapter.clone();
+    // The cpu work blocks so it runs outside of the async workers.
+    // The graph moves in and out of the blocking task, the layout borrows it there.
+    // The permit moves in as well: a client disconnecting drops this future, not the running layout.
+    let result = tokio::task::spawn_blocking(move || {
+        let _permit = permit;
+        let mut layout = ConcentricLayout::new(&graph.nodes, &graph.edges, layout_options);
+        layout.parents = &graph.parents;
+        layout.thread_pool = Some(thread_pool);
+        layout.gpu_adapter = gpu_adapter;
+        let result = layout.execute_blocking();
+        let timer = layout.timer;
+        let stats = layout.stats;
+        let compounds = layout.compounds;
+        let backend = match layout.used_backend {
+            Some(ComputingConfig::Gpu) => LayoutBackend::Gpu,
+            _ => LayoutBackend::Cpu,
+        };
+        (graph, timer, stats, compounds, backend, result)
+    })
+    .await;
+    let (graph, timer, stats, compounds, backend, positions) = match result {
+        Ok((graph, timer, stats, compounds, backend, Ok(positions))) => {
+            (graph, timer, stats, compounds, backend, positions)
+        }
+        Ok((_, _, _, _, _, Err(error))) => {
+            return match error.downcast_ref::<ValidationError>() {
+                Some(_) => error_response(StatusCode::UNPROCESSABLE_ENTITY, error),
+                None => error_response(StatusCode::INTERNAL_SERVER_ERROR, error),
+            };
+        }
+        Err(error) => return error_response(StatusCode::INTERNAL_SERVER_ERROR, error),
+    };
+
+    let preset = graph.get_preset_positions(&positions);
+    let bounding_box = stats.as_ref().and_then(|stats| stats.bounding_box);
+    JsonResponse(LayoutResponse {
+        backend,
+        timer,
+        stats,
+        bounding_box,
+        positions,
+        preset,
+        compounds,
+    })
+    .into_response()
+}
@@ -0,0 +1,259 @@
+pub mod handlers;
+pub mod state;
+pub use handlers::{LayoutBackend, LayoutRequestOptions, LayoutResponse};
+pub use state::{BackendPool, ServerConfig, ServerState};
+
+use axum::Router;
+use axum::extract::DefaultBodyLimit;
+use axum::routing::{get, post};
+use tokio::net::TcpListener;
+
+/// Routes of the layout service
+/// - GET /health
+/// - GET /capabilities
+/// - POST /layout
+pub fn router(state: ServerState) -> Router {
+    Router::new()
+        .route("/health", get(handlers::health))
+        .route("/capabilities", get(handlers::capabilities))
+        .route("/layout", post(handlers::layout))
+        .layer(DefaultBodyLimit::max(state.config.max_body_bytes))
+        .with_state(state)
+}
+
+/// Start the layout service and serve until the process stops.
+pub async fn serve(config: ServerConfig) -> anyhow::Result<()> {
+    let state = ServerState::new(config).await?;
+    let listener = TcpListener::bind(&state.config.address).await?;
+    axum::serve(listener, router(state)).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod test_server {
+    use super::*;
+    use crate::generators::GraphKind;
+    use serde_json::{Value, json};
+    use std::net::SocketAddr;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+
+    /// Request body of a generated telco graph, 52 nodes
+    fn get_sample_data() -> String {
+        let graph = GraphKind::Telco {
+            nodes: 52,
+            fan_out: 3,
+        }
+        .generate(0);
+        json!({ "nodes": graph.nodes, "edges": graph.edges }).to_string()
+    }
+
+    async fn start_server(config: ServerConfig) -> SocketAddr {
+        let state = ServerState::new(config).await.unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router(state)).await });
+        address
+    }
+
+    /// Send a request over localhost and return the status code and the body.
+    async fn request(address: SocketAddr, method: &str, path: &str, body: &str) -> (u16, String) {
+        let mut stream = TcpStream::connect(address).await.unwrap();
+        let request = format!(
+            "{} {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            method,
+            path,
+            address,
+            body.len(),
+            body
+        );
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        let status = response
+            .split(' ')
+            .nth(1)
+            .and_then(|value| value.parse::<u16>().ok())
+            .unwrap();
+        let body = response
+            .split_once("\r\n\r\n")
+            .map(|(_, body)| body.to_string())
+            .unwrap_or_default();
+        (status, body)
+    }
+
+    #[tokio::test]
+    async fn test_health_and_capabilities() {
+        let address = start_server(ServerConfig {
+            gpu: false,
+            ..Default::default()
+        })
+        .await;
+        let (status, _) = request(address, "GET", "/health", "").await;
+        assert_eq!(status, 200);
+        let (status, body) = request(address, "GET", "/capabilities", "").await;
+        assert_eq!(status, 200);
+        let body = serde_json::from_str::<Value>(&body).unwrap();
+        assert_eq!(body["gpu"]["available"], false);
+    }
+
+    #[tokio::test]
+    async fn test_layout() {
+        let address = start_server(ServerConfig {
+            gpu: false,
+            cpu_threads: 2,
+            ..Default::default()
+        })
+        .await;
+        let sample_data = get_sample_data();
+        let (status, body) = request(address, "POST", "/layout", &sample_data).await;
+        assert_eq!(status, 200, "{}", body);
+        let response = serde_json::from_str::<LayoutResponse>(&body).unwrap();
+        assert_eq!(response.backend, LayoutBackend::Cpu);
+        assert_eq!(response.positions.len(), 52);
+        assert_eq!(response.preset.len(), 52);
+
+        let elements = r#"{
+            "elements": [
+                { "data": { "id": "core" } },
+                { "data": { "id": "access" } },
+                { "data": { "id": "uplink", "source": "core", "target": "access" } }
+            ],
+            "options": { "backend": "cpu", "cx": 100, "cy": 100 }
+        }"#;
+        let (status, body) = request(address, "POST", "/layout", elements).await;
+        assert_eq!(status, 200, "{}", body);
+        let response = serde_json::from_str::<LayoutResponse>(&body).unwrap();
+        assert!(response.preset.contains_key("core"));
+        assert!(response.preset.contains_key("access"));
+
+        let fitted = r#"{
+            "nodes": [{"id": 1}, {"id": 2}, {"id": 3}, {"id": 4}],
+            "edges": [
+                {"id": 1, "source_id": 1, "target_id": 2},
+                {"id": 2, "source_id": 1, "target_id": 3},
+                {"id": 3, "source_id": 1, "target_id": 4}
+            ],
+            "options": { "backend": "cpu", "bounding_box": { "x1": 0, "y1": 0, "w": 400, "h": 200 }, "padding": 10 }
+        }"#;
+        let (status, body) = request(address, "POST", "/layout", fitted).await;
+        assert_eq!(status, 200, "{}", body);
+        let response = serde_json::from_str::<LayoutResponse>(&body).unwrap();
+        let bounding_box = response.bounding_box.unwrap();
+        assert!(bounding_box.x1 >= 10.0 - 0.01 && bounding_box.x2() <= 390.0 + 0.01);
+        assert!((bounding_box.h - 180.0).abs() < 0.01, "{:?}", bounding_box);
+
+        let invalid = r#"{"nodes": [{"id": 1}], "edges": [], "options": {"bounding_box": {"x1": 0, "y1": 0, "w": 0, "h": 10}}}"#;
+        let (status, _) = request(address, "POST", "/layout", invalid).await;
+        assert_eq!(status, 400);
+
+        let pinned = fitted.replace(
+            r#""padding": 10"#,
+            r#""pinned": [{"node_id": 3, "position": {"coordinate": {"x": 50, "y": -20}}}]"#,
+        );
+        let (status, body) = request(address, "POST", "/layout", &pinned).await;
+        assert_eq!(status, 200, "{}", body);
+        let response = serde_json::from_str::<LayoutResponse>(&body).unwrap();
+        let node = response
+            .positions
+            .iter()
+            .find(|item| item.node_id == 3)
+            .unwrap();
+        assert!(node.pinned);
+        let levels = fitted.replace(
+            r#""padding": 10"#,
+            r#""levels": [{"node_id": 1, "level": 2}]"#,
+        );
+        let (status, body) = request(address, "POST", "/layout", &levels).await;
+        assert_eq!(status, 200, "{}", body);
+        let response = serde_json::from_str::<LayoutResponse>(&body).unwrap();
+        let node = response
+            .positions
+            .iter()
+            .find(|item| item.node_id == 1)
+            .unwrap();
+        assert_eq!(node.index, 2);
+        let unknown = r#"{"nodes": [{"id": 1}], "edges": [], "options": {"levels": [{"node_id": 9, "level": 1}]}}"#;
+        let (status, _) = request(address, "POST", "/layout", unknown).await;
+        assert_eq!(status, 400);
+        let spacing = fitted.replace(
+            r#""bounding_box": { "x1": 0, "y1": 0, "w": 400, "h": 200 }, "padding": 10"#,
+            r#""step_radius": 50, "min_node_spacing": 10, "order": "degree_ascending""#,
+        );
+        let (status, body) = request(address, "POST", "/layout", &spacing).await;
+        assert_eq!(status, 200, "{}", body);
+        let response = serde_json::from_str::<LayoutResponse>(&body).unwrap();
+        assert_eq!(response.positions.last().unwrap().radius, 70);
+        let invalid = spacing.replace(r#""min_node_spacing": 10"#, r#""min_node_spacing": 0"#);
+        let (status, _) = request(address, "POST", "/layout", &invalid).await;
+        assert_eq!(status, 400);
+        let huge = r#"{"nodes": [{"id": 1}], "edges": [], "options": {"levels": [{"node_id": 1, "level": 4000000000}]}}"#;
+        let (status, _) = request(address, "POST", "/layout", huge).await;
+        assert_eq!(status, 400);
+        let unknown = r#"{"nodes": [{"id": 1}], "edges": [], "options": {"pinned": [{"node_id": 9, "position": {"ring": {"index": 0, "angle_degree": 0}}}]}}"#;
+        let (status, _) = request(address, "POST", "/layout", unknown).await;
+        assert_eq!(status, 400);
+
+        let compound = r#"[
+            { "data": { "id": "rack" } },
+            { "data": { "id": "a", "parent": "rack" } },
+            { "data": { "id": "b", "parent": "rack" } },
+            { "data": { "id": "c" } },
+            { "data": { "id": "ac", "source": "a", "target": "c" } }
+        ]"#;
+        let (status, body) = request(address, "POST", "/layout", compound).await;
+        assert_eq!(status, 200, "{}", body);
+        let response = serde_json::from_str::<LayoutResponse>(&body).unwrap();
+        assert_eq!(response.positions.len(), 4);
+        assert_eq!(response.compounds.len(), 1);
+        assert_eq!(response.compounds[0].node_id, 0);
+        assert!(response.compounds[0].radius > 0.0);
+        let cycle = r#"{"nodes": [{"id": 1}, {"id": 2}], "edges": [], "parents": [{"node_id": 1, "parent_id": 2}, {"node_id": 2, "parent_id": 1}]}"#;
+        let (status, _) = request(address, "POST", "/layout", cycle).await;
+        assert_eq!(status, 400);
+
+        let (status, _) = request(
+            address,
+            "POST",
+            "/layout",
+            r#"{"nodes": [{"id": 1}], "edges": [], "options": {"backend": "gpu"}}"#,
+        )
+        .await;
+        assert_eq!(status, 400);
+
+        let (status, _) = request(
+            address,
+            "POST",
+            "/layout",
+            r#"{"nodes": [{"id": 1}], "edges": [{"id": 1, "source_id": 1, "target_id": 2}]}"#,
+        )
+        .await;
+        assert_eq!(status, 422);
+    }
+
+    #[tokio::test]
+    async fn test_layout_limits() {
+        let address = start_server(ServerConfig {
+            gpu: false,
+            max_body_bytes: 1024,
+            max_nodes: 10,
+            ..Default::default()
+        })
+        .await;
+        let sample_data = get_sample_data();
+        let (status, _) = request(address, "POST", "/layout", &sample_data).await;
+        assert_eq!(status, 413);
+        let nodes = (1..=20)
+            .map(|id| format!("{{\"id\": {}}}", id))
+            .collect::<Vec<String>>()
+            .join(",");
+        let (status, _) = request(
+            address,
+            "POST",
+            "/layout",
+            &format!("{{\"nodes\": [{}], \"edges\": []}}", nodes),
+        )
+        .await;
+        assert_eq!(status, 413);
+    }
+}
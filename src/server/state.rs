@@ -0,0 +1,90 @@
+use crate::gpu::{GpuAdapter, GpuOptions};
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+
+/// Configuration of the layout service
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerConfig {
+    /// Address to listen on. e.g. `127.0.0.1:8080`
+    pub address: String,
+    /// Maximum size of a request body in bytes
+    pub max_body_bytes: usize,
+    /// Maximum number of nodes per layout request
+    pub max_nodes: usize,
+    /// Maximum number of edges per layout request
+    pub max_edges: usize,
+    /// Number of threads of the shared cpu thread pool
+    pub cpu_threads: usize,
+    /// Maximum number of layouts computed at the same time. Other requests wait for their turn.
+    pub max_concurrent_layouts: usize,
+    /// Request a gpu adapter on startup
+    pub gpu: bool,
+    /// Selection of the gpu adapter, see `GpuOptions`
+    #[serde(default)]
+    pub gpu_options: GpuOptions,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            address: "127.0.0.1:8080".to_string(),
+            max_body_bytes: 64 * 1024 * 1024,
+            max_nodes: 1_000_000,
+            max_edges: 10_000_000,
+            cpu_threads: std::thread::available_parallelism()
+                .map(|value| value.get())
+                .unwrap_or(1),
+            max_concurrent_layouts: 4,
+            gpu: true,
+            gpu_options: GpuOptions::default(),
+        }
+    }
+}
+
+/// Backends shared by all the requests
+/// - thread_pool: used by the cpu computing
+/// - gpu_adapter: used by the gpu computing. None when no adapter is available.
+/// - permits: limit the number of concurrent layouts, a permit is held by the blocking task of a layout
+#[derive(Debug)]
+pub struct BackendPool {
+    pub thread_pool: Arc<ThreadPool>,
+    pub gpu_adapter: Option<GpuAdapter>,
+    pub permits: Arc<Semaphore>,
+}
+
+impl BackendPool {
+    pub async fn new(config: &ServerConfig) -> anyhow::Result<Self> {
+        let thread_pool = ThreadPoolBuilder::new()
+            .num_threads(config.cpu_threads)
+            .thread_name(|index| format!("concentric-layout-{}", index))
+            .build()?;
+        let gpu_adapter = if config.gpu {
+            GpuAdapter::with_options(&config.gpu_options).await.ok()
+        } else {
+            None
+        };
+        Ok(Self {
+            thread_pool: Arc::new(thread_pool),
+            gpu_adapter,
+            permits: Arc::new(Semaphore::new(config.max_concurrent_layouts.max(1))),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ServerState {
+    pub config: Arc<ServerConfig>,
+    pub pool: Arc<BackendPool>,
+}
+
+impl ServerState {
+    pub async fn new(config: ServerConfig) -> anyhow::Result<Self> {
+        let pool = BackendPool::new(&config).await?;
+        Ok(Self {
+            config: Arc::new(config),
+            pool: Arc::new(pool),
+        })
+    }
+}
@@ -0,0 +1,398 @@
+//! Shapes of the rings beyond circles, e.g. to fill a 16:9 dashboard.
+//! The nodes of a ring are spread by arc length along its perimeter.
+
+use crate::BoundingBox;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+
+/// Segments of the polygon measuring the arc length of an ellipse
+pub const ELLIPSE_SEGMENTS: u32 = 128;
+
+/// Shape of the rings. The ring radius is the half of the shorter side, the longer side follows
+/// the aspect ratio (width / height). Without an aspect ratio it is taken from
+/// `LayoutOptions::bounding_box`, otherwise 1.
+/// Kinds:
+/// - Circle: default
+/// - Ellipse: semi-axes of radius and radius times the aspect ratio
+/// - RoundedRectangle: corners rounded by `corner_radius` times the radius, from 0 (a rectangle,
+///   a square with an aspect ratio of 1) to 1 (a stadium)
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum RingShape {
+    #[default]
+    Circle,
+    Ellipse {
+        aspect_ratio: Option<f32>,
+    },
+    RoundedRectangle {
+        aspect_ratio: Option<f32>,
+        #[cfg_attr(feature = "serde", serde(default))]
+        corner_radius: f32,
+    },
+}
+
+impl RingShape {
+    /// Shape with the aspect ratio and the corner radius resolved, see `RingGeometry`
+    pub fn get_geometry(&self, bounding_box: Option<BoundingBox>) -> RingGeometry {
+        let get_scale = |aspect_ratio: Option<f32>| {
+            let aspect_ratio = aspect_ratio
+                .or(bounding_box
+                    .filter(BoundingBox::is_valid)
+                    .map(|bounding_box| bounding_box.w / bounding_box.h))
+                .filter(|value| value.is_finite() && *value > 0.0)
+                .unwrap_or(1.0);
+            match aspect_ratio >= 1.0 {
+                true => (aspect_ratio, 1.0),
+                false => (1.0, 1.0 / aspect_ratio),
+            }
+        };
+        match *self {
+            RingShape::Circle => RingGeometry::Circle,
+            RingShape::Ellipse { aspect_ratio } => {
+                let (scale_x, scale_y) = get_scale(aspect_ratio);
+                RingGeometry::Ellipse { scale_x, scale_y }
+            }
+            RingShape::RoundedRectangle {
+                aspect_ratio,
+                corner_radius,
+            } => {
+                let (scale_x, scale_y) = get_scale(aspect_ratio);
+                RingGeometry::RoundedRectangle {
+                    scale_x,
+                    scale_y,
+                    corner_radius: corner_radius.clamp(0.0, 1.0),
+                }
+            }
+        }
+    }
+}
+
+/// Resolved shape of the rings, the sizes are relative to the ring radius.
+/// Kinds:
+/// - Circle
+/// - Ellipse: semi-axes `scale_x` and `scale_y`
+/// - RoundedRectangle: half sides `scale_x` and `scale_y`, corner radius `corner_radius`
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum RingGeometry {
+    #[default]
+    Circle,
+    Ellipse {
+        scale_x: f32,
+        scale_y: f32,
+    },
+    RoundedRectangle {
+        scale_x: f32,
+        scale_y: f32,
+        corner_radius: f32,
+    },
+}
+
+impl RingGeometry {
+    /// Kind and sizes as read by `positions.wgsl`: 0 circle, 1 ellipse, 2 rounded rectangle
+    pub fn get_settings(&self) -> [f32; 4] {
+        match *self {
+            RingGeometry::Circle => [0.0, 1.0, 1.0, 0.0],
+            RingGeometry::Ellipse { scale_x, scale_y } => [1.0, scale_x, scale_y, 0.0],
+            RingGeometry::RoundedRectangle {
+                scale_x,
+                scale_y,
+                corner_radius,
+            } => [2.0, scale_x, scale_y, corner_radius],
+        }
+    }
+
+    /// Half of the width and of the height of a ring
+    pub fn get_extent(&self, radius: f32) -> (f32, f32) {
+        match *self {
+            RingGeometry::Circle => (radius, radius),
+            RingGeometry::Ellipse { scale_x, scale_y }
+            | RingGeometry::RoundedRectangle {
+                scale_x, scale_y, ..
+            } => (radius * scale_x, radius * scale_y),
+        }
+    }
+
+    /// Length of the ring, Ramanujan's approximation for the ellipses
+    pub fn get_perimeter(&self, radius: f32) -> f32 {
+        match *self {
+            RingGeometry::Circle => (2_f32 * PI) * radius,
+            RingGeometry::Ellipse { .. } => {
+                let (a, b) = self.get_extent(radius);
+                PI * (3.0 * (a + b) - ((3.0 * a + b) * (a + 3.0 * b)).sqrt())
+            }
+            RingGeometry::RoundedRectangle { corner_radius, .. } => {
+                let (half_width, half_height) = self.get_extent(radius);
+                let corner = corner_radius * radius;
+                4.0 * (half_width - corner + half_height - corner) + 2.0 * PI * corner
+            }
+        }
+    }
+
+    /// Number of nodes fitting on the ring perimeter with at least `min_node_spacing` between them
+    pub fn get_max_nodes(&self, radius: u32, min_node_spacing: f32) -> u32 {
+        (self.get_perimeter(radius as f32) / min_node_spacing).floor() as u32
+    }
+
+    /// Offset from the centre of the point at `angle_degree` / 360 of the perimeter, starting on
+    /// the positive x axis, and the angle of the point seen from the centre
+    pub fn get_position(&self, radius: f32, angle_degree: f32) -> (f32, f32, f32) {
+        let fraction = angle_degree.rem_euclid(360_f32) / 360_f32;
+        let (x, y) = match *self {
+            RingGeometry::Circle => {
+                let angle_radian = angle_degree * (PI / 180_f32);
+                return (
+                    radius * angle_radian.cos(),
+                    radius * angle_radian.sin(),
+                    angle_degree,
+                );
+            }
+            RingGeometry::Ellipse { .. } => {
+                let (a, b) = self.get_extent(radius);
+                Self::get_ellipse_point(a, b, fraction)
+            }
+            RingGeometry::RoundedRectangle { corner_radius, .. } => {
+                let (half_width, half_height) = self.get_extent(radius);
+                Self::get_rounded_rectangle_point(
+                    half_width,
+                    half_height,
+                    corner_radius * radius,
+                    fraction,
+                )
+            }
+        };
+        match radius > 0.0 {
+            true => (x, y, y.atan2(x).to_degrees().rem_euclid(360_f32)),
+            false => (x, y, angle_degree),
+        }
+    }
+
+    /// Point at a fraction of the length of a polygon of `ELLIPSE_SEGMENTS` along the ellipse
+    fn get_ellipse_point(a: f32, b: f32, fraction: f32) -> (f32, f32) {
+        let step = 2.0 * PI / ELLIPSE_SEGMENTS as f32;
+        let point = |angle: f32| (a * angle.cos(), b * angle.sin());
+        let segment = |index: u32| {
+            let (x1, y1) = point(index as f32 * step);
+            let (x2, y2) = point((index + 1) as f32 * step);
+            (x2 - x1).hypot(y2 - y1)
+        };
+        let total: f32 = (0..ELLIPSE_SEGMENTS).map(segment).sum();
+        let mut remaining = fraction * total;
+        for index in 0..ELLIPSE_SEGMENTS {
+            let length = segment(index);
+            if remaining < length {
+                return point((index as f32 + remaining / length) * step);
+            }
+            remaining -= length;
+        }
+        point(0.0)
+    }
+
+    /// Point at a fraction of the perimeter, from the middle of the right side counterclockwise.
+    /// The four quadrants mirror the first one, from the right side to the top side.
+    fn get_rounded_rectangle_point(
+        half_width: f32,
+        half_height: f32,
+        corner: f32,
+        fraction: f32,
+    ) -> (f32, f32) {
+        let side = half_height - corner;
+        let arc = PI * corner / 2.0;
+        let top = half_width - corner;
+        let quadrant = side + arc + top;
+        let first_quadrant = |length: f32| {
+            if length < side {
+                return (half_width, length);
+            }
+            let length = length - side;
+            if length < arc {
+                let angle = length / corner;
+                return (top + corner * angle.cos(), side + corner * angle.sin());
+            }
+            (top - (length - arc), half_height)
+        };
+        let length = fraction * 4.0 * quadrant;
+        let index = ((length / quadrant) as u32).min(3);
+        let length = length - index as f32 * quadrant;
+        match index {
+            0 => first_quadrant(length),
+            1 => {
+                let (x, y) = first_quadrant(quadrant - length);
+                (-x, y)
+            }
+            2 => {
+                let (x, y) = first_quadrant(length);
+                (-x, -y)
+            }
+            _ => {
+                let (x, y) = first_quadrant(quadrant - length);
+                (x, -y)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod test_ring_shape {
+    use super::*;
+    use crate::entities::NodePositionData;
+    use crate::generators::GraphKind;
+    #[cfg(all(feature = "gpu", not(target_arch = "wasm32")))]
+    use crate::options::test_layout_options::get_ring_angles;
+    use crate::{ComputingConfig, ConcentricLayout, LayoutOptions};
+
+    #[test]
+    fn test_ring_geometry() {
+        let circle = RingShape::Circle.get_geometry(None);
+        assert_eq!(circle.get_max_nodes(100, 40.0), 15);
+        let (x, y, angle) = circle.get_position(100.0, 90.0);
+        assert!(x.abs() < 1e-3 && (y - 100.0).abs() < 1e-3 && angle == 90.0);
+
+        // Aspect ratio from the bounding box
+        let ellipse = RingShape::Ellipse { aspect_ratio: None }
+            .get_geometry(Some(BoundingBox::new(0.0, 0.0, 1600.0, 900.0)));
+        assert_eq!(
+            ellipse,
+            RingGeometry::Ellipse {
+                scale_x: 16.0 / 9.0,
+                scale_y: 1.0
+            }
+        );
+        assert!(ellipse.get_max_nodes(100, 40.0) > circle.get_max_nodes(100, 40.0));
+        let (x, y, _) = ellipse.get_position(100.0, 0.0);
+        assert!((x - 177.78).abs() < 0.01 && y.abs() < 1e-3);
+        let (x, y, angle) = ellipse.get_position(100.0, 90.0);
+        assert!(x.abs() < 0.5 && (y - 100.0).abs() < 0.01 && (angle - 90.0).abs() < 0.5);
+
+        let square = RingShape::RoundedRectangle {
+            aspect_ratio: Some(1.0),
+            corner_radius: 0.0,
+        }
+        .get_geometry(None);
+        assert_eq!(square.get_perimeter(100.0), 800.0);
+        assert_eq!(square.get_max_nodes(100, 40.0), 20);
+        // Corners of the square at an eighth of the perimeter
+        for (angle, expected) in [
+            (45.0, (100.0, 100.0)),
+            (135.0, (-100.0, 100.0)),
+            (225.0, (-100.0, -100.0)),
+            (315.0, (100.0, -100.0)),
+        ] {
+            let (x, y, _) = square.get_position(100.0, angle);
+            assert!((x - expected.0).abs() < 0.01, "{} {}", angle, x);
+            assert!((y - expected.1).abs() < 0.01, "{} {}", angle, y);
+        }
+
+        // The nodes of a rounded rectangle are equally spaced along the perimeter
+        let rounded = RingShape::RoundedRectangle {
+            aspect_ratio: Some(2.0),
+            corner_radius: 0.5,
+        }
+        .get_geometry(None);
+        let perimeter = rounded.get_perimeter(100.0);
+        let points: Vec<(f32, f32)> = (0..400)
+            .map(|index| {
+                let (x, y, _) = rounded.get_position(100.0, index as f32 * 360.0 / 400.0);
+                (x, y)
+            })
+            .collect();
+        for (index, (x, y)) in points.iter().enumerate() {
+            let (next_x, next_y) = points[(index + 1) % points.len()];
+            let distance = (next_x - x).hypot(next_y - y);
+            assert!(distance <= perimeter / 400.0 + 1e-2, "{}", index);
+            assert!(distance >= perimeter / 400.0 * 0.95, "{}", index);
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn test_ring_shape_layout() {
+        let graph = GraphKind::Tree {
+            nodes: 300,
+            arity: 3,
+        }
+        .generate(0);
+        let circle = {
+            let options = LayoutOptions::new().backend(ComputingConfig::Cpu(2));
+            let mut layout = ConcentricLayout::new(&graph.nodes, &graph.edges, options);
+            layout.execute_blocking().unwrap()
+        };
+        let shapes = [
+            RingShape::Ellipse { aspect_ratio: None },
+            RingShape::RoundedRectangle {
+                aspect_ratio: Some(1.0),
+                corner_radius: 0.0,
+            },
+            RingShape::RoundedRectangle {
+                aspect_ratio: None,
+                corner_radius: 0.5,
+            },
+        ];
+        for shape in shapes {
+            let options = LayoutOptions::new()
+                .backend(ComputingConfig::Cpu(2))
+                .shape(shape)
+                .bounding_box(BoundingBox::new(0.0, 0.0, 1600.0, 900.0))
+                .fit(false);
+            let mut layout = ConcentricLayout::new(&graph.nodes, &graph.edges, options.clone());
+            let positions = layout.execute_blocking().unwrap();
+            assert_eq!(positions.len(), graph.nodes.len());
+            // The perimeters are longer than the circles: fewer rings
+            let rings = |positions: &[NodePositionData]| {
+                positions.iter().map(|item| item.index).max().unwrap()
+            };
+            assert!(rings(&positions) < rings(&circle), "{:?}", shape);
+            let bounding_box = BoundingBox::from_positions(&positions).unwrap();
+            match shape {
+                RingShape::Ellipse { .. } => assert!(bounding_box.w / bounding_box.h > 1.5),
+                _ => assert!(bounding_box.w >= bounding_box.h * 0.95),
+            }
+            // The nodes of each ring are not closer than the spacing, but across a sharp corner
+            let geometry = options.get_ring_geometry();
+            let min_distance = match shape {
+                RingShape::RoundedRectangle {
+                    corner_radius: 0.0, ..
+                } => options.min_node_spacing / 2_f32.sqrt(),
+                _ => options.min_node_spacing,
+            };
+            for item in positions.iter().filter(|item| item.radius > 0) {
+                let x = item.x - item.cx;
+                let y = item.y - item.cy;
+                assert!(
+                    (y.atan2(x).to_degrees().rem_euclid(360.0) - item.angle_degree).abs() < 0.01
+                );
+                let (rx, ry) = geometry.get_extent(item.radius as f32);
+                assert!(x.abs() <= rx + 0.01 && y.abs() <= ry + 0.01, "{:?}", item);
+                let closest = positions
+                    .iter()
+                    .filter(|other| other.index == item.index && other.node_id != item.node_id)
+                    .map(|other| (other.x - item.x).hypot(other.y - item.y))
+                    .fold(f32::INFINITY, f32::min);
+                assert!(closest > min_distance * 0.9, "{:?} {}", shape, closest);
+            }
+
+            #[cfg(feature = "gpu")]
+            {
+                let options = options.backend(ComputingConfig::Gpu);
+                let mut layout = ConcentricLayout::new(&graph.nodes, &graph.edges, options);
+                let rings = get_ring_angles(&layout.execute_blocking().unwrap());
+                let expected = get_ring_angles(&positions);
+                assert_eq!(rings.len(), expected.len());
+                for (ring, expected) in rings.iter().zip(expected.iter()) {
+                    for (angle, expected) in ring.iter().zip(expected.iter()) {
+                        assert!(
+                            (angle - expected).abs() < 1e-2,
+                            "{:?}: {} {}",
+                            shape,
+                            angle,
+                            expected
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
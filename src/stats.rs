@@ -0,0 +1,120 @@
+use crate::BoundingBox;
+use crate::ComputingConfig;
+use crate::entities::NodePositionData;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Duration of a stage in microseconds.
+/// The gpu stages also break the time down, the cpu stages leave these fields empty:
+/// - upload_micros: host to device copies of the input buffers
+/// - dispatch_micros: compute passes, measured by timestamp queries when the device supports them,
+///   otherwise on the host from the submission until the work is done
+/// - readback_micros: device to host copy of the result
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct StageTiming {
+    pub micros: u64,
+    pub upload_micros: Option<u64>,
+    pub dispatch_micros: Option<u64>,
+    pub readback_micros: Option<u64>,
+}
+
+impl StageTiming {
+    pub fn new(elapsed: Duration) -> Self {
+        Self {
+            micros: elapsed.as_micros() as u64,
+            ..Default::default()
+        }
+    }
+
+    /// Sum of two durations, e.g. of the layouts of a compound graph
+    pub fn add(&self, other: &StageTiming) -> Self {
+        let add = |a: Option<u64>, b: Option<u64>| match (a, b) {
+            (None, None) => None,
+            _ => Some(a.unwrap_or(0) + b.unwrap_or(0)),
+        };
+        Self {
+            micros: self.micros + other.micros,
+            upload_micros: add(self.upload_micros, other.upload_micros),
+            dispatch_micros: add(self.dispatch_micros, other.dispatch_micros),
+            readback_micros: add(self.readback_micros, other.readback_micros),
+        }
+    }
+}
+
+/// Report of a layout computation, e.g. to track performance regressions.
+/// Parameters:
+/// - backend: backend that computed the layout
+/// - threads: cpu threads, or gpu invocations per dispatch
+/// - nodes, edges, rings: size of the graph and of the layout
+/// - total_micros: duration of the whole computation
+/// - degree, normalize, sort, positions: duration of each stage, see `StageTiming`
+/// - gpu_timestamps: the gpu dispatch durations come from timestamp queries
+/// - bounding_box: extent of the final positions, after the fit into `LayoutOptions::bounding_box`
+/// - fit_scale: factor of the fit into the bounding box, see `Viewport::get_scale`. None without bounding box
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LayoutStats {
+    pub backend: ComputingConfig,
+    pub threads: usize,
+    pub nodes: usize,
+    pub edges: usize,
+    pub rings: usize,
+    pub total_micros: u64,
+    pub degree: StageTiming,
+    pub normalize: StageTiming,
+    pub sort: StageTiming,
+    pub positions: StageTiming,
+    pub gpu_timestamps: bool,
+    pub bounding_box: Option<BoundingBox>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub fit_scale: Option<f32>,
+}
+
+impl LayoutStats {
+    /// Number of rings holding at least one node
+    pub fn count_rings(positions: &[NodePositionData]) -> usize {
+        positions
+            .iter()
+            .map(|item| item.index as usize + 1)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(all(test, feature = "formats", not(target_arch = "wasm32")))]
+pub mod test_layout_stats {
+    use super::*;
+    use crate::entities::{Edge, Node};
+    use crate::{ConcentricLayout, LayoutOptions};
+
+    #[tokio::test]
+    async fn test_layout_stats() {
+        let nodes: Vec<Node> = (1..=100).map(|id| Node { id }).collect();
+        let edges: Vec<Edge> = (2..=100)
+            .map(|id| Edge {
+                id,
+                source_id: 1,
+                target_id: id,
+            })
+            .collect();
+        let options = LayoutOptions::new().backend(ComputingConfig::Cpu(1));
+        let mut layout = ConcentricLayout::new(&nodes, &edges, options);
+        let positions = layout.execute().await.unwrap();
+        let stats = layout.stats.unwrap();
+        assert_eq!(stats.rings, LayoutStats::count_rings(&positions));
+        assert!(stats.rings > 1);
+        assert!(
+            stats.total_micros
+                >= stats.degree.micros
+                    + stats.normalize.micros
+                    + stats.sort.micros
+                    + stats.positions.micros
+        );
+
+        let json = serde_json::to_string(&stats).unwrap();
+        assert_eq!(serde_json::from_str::<LayoutStats>(&json).unwrap(), stats);
+        assert_eq!(LayoutStats::count_rings(&[]), 0);
+    }
+}
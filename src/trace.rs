@@ -0,0 +1,90 @@
+//! Tracing events of the pipeline.
+//! `tracing` events with the `tracing` feature, otherwise the events are removed at compile time.
+//! The spans are added with `#[cfg_attr(feature = "tracing", tracing::instrument(...))]`.
+
+/// Emit a `tracing` event. e.g. `event!(debug, nodes = 10, "validation passed")`
+#[cfg(feature = "tracing")]
+macro_rules! event {
+    ($level:ident, $($arg:tt)+) => {
+        tracing::$level!($($arg)+)
+    };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! event {
+    ($level:ident, $($arg:tt)+) => {};
+}
+
+pub(crate) use event;
+
+#[cfg(all(test, feature = "tracing", not(target_arch = "wasm32")))]
+pub mod test_trace {
+    use crate::entities::{Edge, Node};
+    use crate::{ComputingConfig, ConcentricLayout, LayoutOptions};
+    use std::sync::{Arc, Mutex};
+
+    /// Subscriber keeping the names and the fields of the spans
+    #[derive(Default)]
+    struct SpanRecorder {
+        spans: Mutex<Vec<String>>,
+    }
+
+    impl tracing::Subscriber for SpanRecorder {
+        fn enabled(&self, _metadata: &tracing::Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+            let mut spans = self.spans.lock().unwrap();
+            spans.push(format!("{} {:?}", span.metadata().name(), span.values()));
+            tracing::span::Id::from_u64(spans.len() as u64)
+        }
+
+        fn record(&self, _span: &tracing::span::Id, _values: &tracing::span::Record<'_>) {}
+
+        fn record_follows_from(&self, _span: &tracing::span::Id, _follows: &tracing::span::Id) {}
+
+        fn event(&self, _event: &tracing::Event<'_>) {}
+
+        fn enter(&self, _span: &tracing::span::Id) {}
+
+        fn exit(&self, _span: &tracing::span::Id) {}
+    }
+
+    #[test]
+    fn test_layout_spans() {
+        let nodes: Vec<Node> = (1..=10).map(|id| Node { id }).collect();
+        let edges: Vec<Edge> = (2..=10)
+            .map(|id| Edge {
+                id,
+                source_id: 1,
+                target_id: id,
+            })
+            .collect();
+        let recorder = Arc::new(SpanRecorder::default());
+        tracing::subscriber::with_default(recorder.clone(), || {
+            let options = LayoutOptions::new().backend(ComputingConfig::Cpu(1));
+            let mut layout = ConcentricLayout::new(&nodes, &edges, options);
+            layout.execute_blocking().unwrap();
+        });
+        let spans = recorder.spans.lock().unwrap();
+        let names: Vec<&str> = spans
+            .iter()
+            .map(|span| span.split(' ').next().unwrap())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "concentric_layout.execute",
+                "cpu.layout",
+                "cpu.degree",
+                "cpu.normalize",
+                "cpu.sort",
+                "cpu.positions"
+            ]
+        );
+        assert!(spans[0].contains("nodes: 10"), "{}", spans[0]);
+        assert!(spans[0].contains("edges: 9"), "{}", spans[0]);
+        assert!(spans[0].contains("backend: Cpu(1)"), "{}", spans[0]);
+    }
+}
@@ -0,0 +1,89 @@
+use crate::entities::{Edge, Node};
+use std::collections::HashSet;
+use std::fmt::Display;
+
+/// Errors found on the layout input
+/// Kinds:
+/// - NoNodes: there is nothing to layout.
+/// - DuplicateNodeId: the node id is used by more than one node.
+/// - UnknownNode: the edge source or target is not in the nodes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+    NoNodes,
+    DuplicateNodeId(u32),
+    UnknownNode { edge_id: u32, node_id: u32 },
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NoNodes => write!(f, "the graph has no nodes"),
+            Self::DuplicateNodeId(node_id) => write!(f, "duplicate node id {}", node_id),
+            Self::UnknownNode { edge_id, node_id } => {
+                write!(f, "edge {} references unknown node {}", edge_id, node_id)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+#[derive(Debug)]
+pub struct Validation {}
+
+impl Validation {
+    /// Validate the nodes and edges before computing the layout.
+    pub fn validate(nodes: &[Node], edges: &[Edge]) -> Result<(), ValidationError> {
+        if nodes.is_empty() {
+            return Err(ValidationError::NoNodes);
+        }
+        let mut node_ids: HashSet<u32> = HashSet::with_capacity(nodes.len());
+        for node in nodes.iter() {
+            if !node_ids.insert(node.id) {
+                return Err(ValidationError::DuplicateNodeId(node.id));
+            }
+        }
+        for edge in edges.iter() {
+            for node_id in [edge.source_id, edge.target_id] {
+                if !node_ids.contains(&node_id) {
+                    return Err(ValidationError::UnknownNode {
+                        edge_id: edge.id,
+                        node_id,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+pub mod test_validation {
+    use super::*;
+
+    #[test]
+    fn test_validation() {
+        let nodes = vec![Node { id: 1 }, Node { id: 2 }];
+        let edges = vec![Edge {
+            id: 1,
+            source_id: 1,
+            target_id: 2,
+        }];
+        assert!(Validation::validate(&nodes, &edges).is_ok());
+        assert_eq!(
+            Validation::validate(&[], &[]),
+            Err(ValidationError::NoNodes)
+        );
+        assert_eq!(
+            Validation::validate(&[Node { id: 1 }, Node { id: 1 }], &[]),
+            Err(ValidationError::DuplicateNodeId(1))
+        );
+        assert_eq!(
+            Validation::validate(&nodes[..1], &edges),
+            Err(ValidationError::UnknownNode {
+                edge_id: 1,
+                node_id: 2
+            })
+        );
+    }
+}
@@ -0,0 +1,228 @@
+//! Scaling of the computed coordinates into a target rectangle, as the Cytoscape.js `fit`, `padding`
+//! and `boundingBox` options.
+
+use crate::LayoutOptions;
+use crate::entities::NodePositionData;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Rectangle of coordinates, as the Cytoscape.js `boundingBox`: top left corner `x1`, `y1` with a
+/// width `w` and a height `h`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct BoundingBox {
+    pub x1: f32,
+    pub y1: f32,
+    pub w: f32,
+    pub h: f32,
+}
+
+impl BoundingBox {
+    pub fn new(x1: f32, y1: f32, w: f32, h: f32) -> Self {
+        Self { x1, y1, w, h }
+    }
+
+    pub fn x2(&self) -> f32 {
+        self.x1 + self.w
+    }
+
+    pub fn y2(&self) -> f32 {
+        self.y1 + self.h
+    }
+
+    pub fn center(&self) -> (f32, f32) {
+        (self.x1 + self.w / 2.0, self.y1 + self.h / 2.0)
+    }
+
+    /// Smallest box holding the node positions, None without positions
+    pub fn from_positions(positions: &[NodePositionData]) -> Option<Self> {
+        let first = positions.first()?;
+        let [min_x, min_y, max_x, max_y] = positions.iter().fold(
+            [first.x, first.y, first.x, first.y],
+            |[min_x, min_y, max_x, max_y], item| {
+                [
+                    min_x.min(item.x),
+                    min_y.min(item.y),
+                    max_x.max(item.x),
+                    max_y.max(item.y),
+                ]
+            },
+        );
+        Some(Self::new(min_x, min_y, max_x - min_x, max_y - min_y))
+    }
+
+    /// Box reduced by the padding on each side, never smaller than its centre
+    pub fn inset(&self, padding: f32) -> Self {
+        let (x, y) = self.center();
+        let w = (self.w - 2.0 * padding).max(0.0);
+        let h = (self.h - 2.0 * padding).max(0.0);
+        Self::new(x - w / 2.0, y - h / 2.0, w, h)
+    }
+
+    /// Both sizes are positive finite numbers
+    pub fn is_valid(&self) -> bool {
+        [self.x1, self.y1, self.w, self.h]
+            .iter()
+            .all(|value| value.is_finite())
+            && self.w > 0.0
+            && self.h > 0.0
+    }
+}
+
+#[derive(Debug)]
+pub struct Viewport {}
+
+impl Viewport {
+    /// Move the positions into `options.bounding_box` minus `options.padding`, centred, and scaled
+    /// uniformly to the box when `options.fit` is set. The ring centres and radiuses follow, and the
+    /// depth of the shells is scaled around their centre.
+    /// Returns the bounding box of the final positions.
+    pub fn fit(
+        positions: &mut [NodePositionData],
+        options: &LayoutOptions,
+    ) -> anyhow::Result<Option<BoundingBox>> {
+        let (Some(target), Some(current)) =
+            (options.bounding_box, BoundingBox::from_positions(positions))
+        else {
+            return Ok(BoundingBox::from_positions(positions));
+        };
+        if !target.is_valid() {
+            anyhow::bail!(
+                "invalid bounding box {:?}, the size must be positive",
+                target
+            );
+        }
+        let target = target.inset(options.padding);
+        let scale = Self::get_fit_scale(&target, &current, options.fit);
+        let (from_x, from_y) = current.center();
+        let (to_x, to_y) = target.center();
+        let apply = |x: f32, y: f32| ((x - from_x) * scale + to_x, (y - from_y) * scale + to_y);
+        for item in positions.iter_mut() {
+            (item.x, item.y) = apply(item.x, item.y);
+            (item.cx, item.cy) = apply(item.cx, item.cy);
+            item.z = item.cz + (item.z - item.cz) * scale;
+            item.radius = (item.radius as f32 * scale).round() as u32;
+        }
+        Ok(BoundingBox::from_positions(positions))
+    }
+
+    /// Factor `fit` scales the positions by, None without a valid `options.bounding_box`
+    pub fn get_scale(positions: &[NodePositionData], options: &LayoutOptions) -> Option<f32> {
+        let target = options.bounding_box.filter(BoundingBox::is_valid)?;
+        let current = BoundingBox::from_positions(positions)?;
+        Some(Self::get_fit_scale(
+            &target.inset(options.padding),
+            &current,
+            options.fit,
+        ))
+    }
+
+    fn get_fit_scale(target: &BoundingBox, current: &BoundingBox, fit: bool) -> f32 {
+        match (fit, current.w > 0.0, current.h > 0.0) {
+            (false, _, _) | (true, false, false) => 1.0,
+            (true, true, false) => target.w / current.w,
+            (true, false, true) => target.h / current.h,
+            (true, true, true) => (target.w / current.w).min(target.h / current.h),
+        }
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+pub mod test_viewport {
+    use super::*;
+    use crate::ComputingConfig;
+    use crate::ConcentricLayout;
+    use crate::generators::GraphKind;
+
+    fn assert_inside(bounding_box: &BoundingBox, target: &BoundingBox) {
+        let epsilon = 0.01;
+        assert!(bounding_box.x1 >= target.x1 - epsilon, "{:?}", bounding_box);
+        assert!(bounding_box.y1 >= target.y1 - epsilon, "{:?}", bounding_box);
+        assert!(
+            bounding_box.x2() <= target.x2() + epsilon,
+            "{:?}",
+            bounding_box
+        );
+        assert!(
+            bounding_box.y2() <= target.y2() + epsilon,
+            "{:?}",
+            bounding_box
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fit() {
+        let graph = GraphKind::Tree {
+            nodes: 100,
+            arity: 3,
+        }
+        .generate(0);
+        let target = BoundingBox::new(100.0, 50.0, 800.0, 400.0);
+        let options = LayoutOptions::new()
+            .backend(ComputingConfig::Cpu(2))
+            .bounding_box(target)
+            .padding(20.0);
+        let mut layout = ConcentricLayout::new(&graph.nodes, &graph.edges, options.clone());
+        let positions = layout.execute().await.unwrap();
+        let bounding_box = layout.stats.as_ref().unwrap().bounding_box.unwrap();
+        assert_eq!(BoundingBox::from_positions(&positions), Some(bounding_box));
+        assert_inside(&bounding_box, &target.inset(20.0));
+        // Uniform scaling: the height fills the box, the width is centred
+        assert!((bounding_box.h - 360.0).abs() < 0.01, "{:?}", bounding_box);
+        assert!((bounding_box.center().0 - 500.0).abs() < 0.01);
+        assert!((bounding_box.center().1 - 250.0).abs() < 0.01);
+
+        // Not fitted: same size, centred in the box
+        let mut layout = ConcentricLayout::new(
+            &graph.nodes,
+            &graph.edges,
+            LayoutOptions::new().backend(ComputingConfig::Cpu(2)),
+        );
+        let raw = layout.execute().await.unwrap();
+        let raw_box = BoundingBox::from_positions(&raw).unwrap();
+        let mut moved = raw.clone();
+        let centred = Viewport::fit(&mut moved, &options.clone().fit(false))
+            .unwrap()
+            .unwrap();
+        assert!((centred.w - raw_box.w).abs() < 0.01);
+        assert!((centred.center().0 - 500.0).abs() < 0.01);
+        assert_eq!(moved[0].node_id, raw[0].node_id);
+
+        #[cfg(feature = "gpu")]
+        {
+            let options = options.backend(ComputingConfig::Gpu);
+            let mut layout = ConcentricLayout::new(&graph.nodes, &graph.edges, options);
+            let positions = layout.execute().await.unwrap();
+            let gpu_box = layout.stats.as_ref().unwrap().bounding_box.unwrap();
+            assert_eq!(BoundingBox::from_positions(&positions), Some(gpu_box));
+            assert!((gpu_box.h - bounding_box.h).abs() < 0.1, "{:?}", gpu_box);
+        }
+    }
+
+    #[test]
+    fn test_fit_single_node() {
+        let mut positions = vec![NodePositionData {
+            index: 0,
+            radius: 0,
+            angle_degree: 0.0,
+            angle_radian: 0.0,
+            cx: 0.0,
+            cy: 0.0,
+            x: 0.0,
+            y: 0.0,
+            node_id: 1,
+            cz: 0.0,
+            z: 0.0,
+            polar_degree: 90.0,
+            azimuth_degree: 0.0,
+            pinned: false,
+        }];
+        let options = LayoutOptions::new().bounding_box(BoundingBox::new(0.0, 0.0, 200.0, 100.0));
+        let bounding_box = Viewport::fit(&mut positions, &options).unwrap();
+        assert_eq!(bounding_box, Some(BoundingBox::new(100.0, 50.0, 0.0, 0.0)));
+        assert_eq!((positions[0].cx, positions[0].cy), (100.0, 50.0));
+        assert_eq!(Viewport::fit(&mut [], &options).unwrap(), None);
+        let options = options.bounding_box(BoundingBox::new(0.0, 0.0, 0.0, 100.0));
+        assert!(Viewport::fit(&mut positions, &options).is_err());
+    }
+}
@@ -0,0 +1,60 @@
+use crate::cpu::CpuConcentric;
+use crate::entities::PresetPosition;
+use crate::formats::Cytoscape;
+use crate::{LayoutOptions, Validation, ValidationPolicy};
+use serde::Serialize;
+use std::collections::HashMap;
+use wasm_bindgen::prelude::*;
+
+/// Compute the layout of Cytoscape.js elements on the current thread.
+/// The backend of the options is ignored, the cpu is always used.
+/// Returns the positions keyed by the original node id.
+pub fn layout_elements(
+    elements_json: &str,
+    options: &LayoutOptions,
+) -> anyhow::Result<HashMap<String, PresetPosition>> {
+    let graph = Cytoscape::import(elements_json)?;
+    if options.validation == ValidationPolicy::Strict {
+        Validation::validate(&graph.nodes, &graph.edges)?;
+    }
+    let mut layout = CpuConcentric::new(&graph.nodes, &graph.edges, options);
+    let positions = layout.get()?;
+    Ok(graph.get_preset_positions(&positions))
+}
+
+/// JS: `layout(elementsJson, options) -> positions`
+/// - elementsJson: Cytoscape.js elements as JSON string
+/// - options: `LayoutOptions` e.g. `{ cx, cy, start_angle }`, all optional
+///
+/// The result can be used as is by the Cytoscape.js `preset` layout: `{ [id]: { x, y } }`.
+#[wasm_bindgen]
+pub fn layout(elements_json: &str, options: JsValue) -> Result<JsValue, JsError> {
+    let options = if options.is_undefined() || options.is_null() {
+        LayoutOptions::default()
+    } else {
+        serde_wasm_bindgen::from_value::<LayoutOptions>(options)?
+    };
+    let positions = layout_elements(elements_json, &options)
+        .map_err(|error| JsError::new(&error.to_string()))?;
+    Ok(positions.serialize(&serde_wasm_bindgen::Serializer::json_compatible())?)
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+pub mod test_wasm {
+    use super::*;
+
+    #[test]
+    fn test_layout_elements() {
+        let elements = r#"[
+            { "data": { "id": "core" } },
+            { "data": { "id": "agg" } },
+            { "data": { "id": "access" } },
+            { "data": { "source": "core", "target": "agg" } },
+            { "data": { "source": "agg", "target": "access" } }
+        ]"#;
+        let options = LayoutOptions::new().center(50.0, 50.0);
+        let positions = layout_elements(elements, &options).unwrap();
+        assert_eq!(positions.len(), 3);
+        assert_eq!(positions["agg"], PresetPosition { x: 50.0, y: 50.0 });
+    }
+}
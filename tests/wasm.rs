@@ -0,0 +1,36 @@
+//! Run with `wasm-pack test --node -- --no-default-features --features wasm`
+//! The unit tests of the lib use tokio, they only run on the native targets
+#![cfg(all(target_arch = "wasm32", feature = "wasm"))]
+
+use concentric_layout::wasm::layout;
+use wasm_bindgen::JsValue;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+const ELEMENTS: &str = r#"{
+    "elements": {
+        "nodes": [
+            { "data": { "id": "core" } },
+            { "data": { "id": "agg" } },
+            { "data": { "id": "access" } }
+        ],
+        "edges": [
+            { "data": { "id": "e1", "source": "core", "target": "agg" } },
+            { "data": { "id": "e2", "source": "agg", "target": "access" } }
+        ]
+    }
+}"#;
+
+#[wasm_bindgen_test]
+fn test_layout() {
+    let positions = layout(ELEMENTS, JsValue::UNDEFINED).unwrap();
+    for id in ["core", "agg", "access"] {
+        let position = js_sys::Reflect::get(&positions, &JsValue::from_str(id)).unwrap();
+        let x = js_sys::Reflect::get(&position, &JsValue::from_str("x")).unwrap();
+        assert!(x.as_f64().is_some());
+    }
+}
+
+#[wasm_bindgen_test]
+fn test_layout_invalid_elements() {
+    assert!(layout("{\"elements\": 1}", JsValue::UNDEFINED).is_err());
+}